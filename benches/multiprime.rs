@@ -0,0 +1,54 @@
+// Decryption speed of 2, 3 and 4 prime keys, each timed with the CRT and
+// with a single c ^ d mod n. Run with cargo bench.
+
+use rsaencryptiontutorial::multiprime::{RSAMultiPrimeKey, MAX_PRIMES};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const BLOCKS:u64 = 20000;
+const ROUNDS:u32 = 5;
+
+// Square and multiply without the CRT, the way the tutorial decrypts.
+fn power_mod(base:u64, exp:u64, modulus:u64) -> u64{
+    let modulus = modulus as u128;
+    let mut result = 1u128 % modulus;
+    let mut base = base as u128 % modulus;
+    let mut exp = exp;
+    while exp > 0{
+        if exp & 1 == 1{
+            result = result * base % modulus;
+        }
+        base = base * base % modulus;
+        exp >>= 1;
+    }
+    result as u64
+}
+
+// Fastest of ROUNDS runs over the blocks, per block.
+fn time<F:Fn(u64) -> u64>(blocks:&[u64], decrypt:F) -> Duration{
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS{
+        let now = Instant::now();
+        for block in blocks{
+            black_box(decrypt(black_box(*block)));
+        }
+        best = best.min(now.elapsed());
+    }
+    best / blocks.len() as u32
+}
+
+fn main(){
+    println!("Decrypting {} blocks, best of {} runs.", BLOCKS, ROUNDS);
+    for count in 2..=MAX_PRIMES{
+        let key = match RSAMultiPrimeKey::generate(count, 7){
+            Ok(s) => s,
+            Err(e) => panic!("Unable to make a {} prime key. {}", count, e),
+        };
+        let n = key.get_n();
+        let blocks:Vec<u64> = (1..=BLOCKS).map(|i| power_mod(i.wrapping_mul(0x9e3779b97f4a7c15) % n, key.get_e(), n)).collect();
+        let plain = time(&blocks, |c| power_mod(c, key.get_d(), n));
+        let crt = time(&blocks, |c| key.decrypt_block(c));
+        println!("{} primes: c ^ d mod n {:?} per block, CRT {:?} per block, {:.1} times faster.",
+            count, plain, crt, plain.as_secs_f64() / crt.as_secs_f64());
+    }
+}
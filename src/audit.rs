@@ -0,0 +1,254 @@
+// Checks a key for the weaknesses the attacks on RSA rely on, and ranks what
+// it finds by severity. A public key is checked by trying the cheap attacks
+// against it within a budget. With the private key the primes and d can be
+// looked at directly as well.
+//
+// Every key the tutorial makes is 64 bits, which any of the factoring
+// methods here break in well under a second, so every key is reported
+// critical for its size. The other findings show which mistakes would still
+// matter with a modulus of a safe size.
+
+use crate::keys::{RSAPrivateKey, RSAPublicKey};
+use crate::fermat;
+use crate::fips186;
+use crate::pollard;
+use crate::wiener;
+use std::fmt;
+
+// Steps given to each factoring method by audit_public_key.
+pub const DEFAULT_BUDGET:u64 = 1 << 20;
+
+// Bound for Pollard's p - 1 method and the smoothness of p - 1 and q - 1.
+pub const SMOOTHNESS_BOUND:u64 = 10000;
+
+// Smallest modulus NIST still allows for RSA.
+const MIN_SAFE_BITS:u32 = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity{
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl fmt::Display for Severity{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        // pad so the report can line the names up with a width.
+        f.pad(match self{
+            Severity::Info => "INFO",
+            Severity::Low => "LOW",
+            Severity::Medium => "MEDIUM",
+            Severity::High => "HIGH",
+            Severity::Critical => "CRITICAL",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding{
+    pub severity:Severity,
+    pub check:&'static str,
+    pub detail:String,
+}
+
+// Findings, the most severe first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditReport{
+    pub n:u64,
+    pub e:u64,
+    pub findings:Vec<Finding>,
+}
+
+impl AuditReport{
+    fn new(key:&RSAPublicKey) -> AuditReport{
+        AuditReport{ n: key.get_n(), e: key.get_e(), findings: Vec::new() }
+    }
+
+    fn add(&mut self, severity:Severity, check:&'static str, detail:String){
+        self.findings.push(Finding{ severity, check, detail });
+    }
+
+    // Sorts by severity, keeping the order checks ran in within one level.
+    fn finish(mut self) -> AuditReport{
+        self.findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+        self
+    }
+
+    pub fn worst(&self) -> Option<Severity>{
+        self.findings.first().map(|f| f.severity)
+    }
+
+    pub fn contains(&self, check:&str) -> bool{
+        self.findings.iter().any(|f| f.check == check)
+    }
+}
+
+impl fmt::Display for AuditReport{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        writeln!(f, "Audit of the key with n = {} and e = {}", self.n, self.e)?;
+        if self.findings.is_empty(){
+            return writeln!(f, "No weaknesses found.");
+        }
+        for finding in &self.findings{
+            writeln!(f, "{:<9} {}: {}", finding.severity, finding.check, finding.detail)?;
+        }
+        Ok(())
+    }
+}
+
+// Checks a public key, trying each factoring method for up to budget steps.
+pub fn audit_public_key(key:&RSAPublicKey, budget:u64) -> AuditReport{
+    let mut report = AuditReport::new(key);
+    public_checks(&mut report, key, budget);
+    report.finish()
+}
+
+// Checks a key pair, the public checks and then the primes and d.
+pub fn audit_private_key(key:&RSAPrivateKey, budget:u64) -> AuditReport{
+    let mut report = AuditReport::new(&key.public_key());
+    public_checks(&mut report, &key.public_key(), budget);
+    private_checks(&mut report, key);
+    report.finish()
+}
+
+fn public_checks(report:&mut AuditReport, key:&RSAPublicKey, budget:u64){
+    let (n, e) = (key.get_n(), key.get_e());
+    let bits = 64 - n.leading_zeros();
+    if bits < MIN_SAFE_BITS{
+        report.add(Severity::Critical, "small modulus", format!("n has {} bits, below the {} NIST allows", bits, MIN_SAFE_BITS));
+    }
+
+    if e == 1{
+        report.add(Severity::Critical, "public exponent", String::from("e = 1 leaves every message unchanged"));
+    }else if e.is_multiple_of(2){
+        report.add(Severity::Critical, "public exponent", format!("e = {} is even, it has no inverse and decryption can't work", e));
+    }else if e < 65537{
+        report.add(Severity::Low, "public exponent", format!("e = {} is below 65537, unpadded messages sent to several keys can be recovered", e));
+    }
+    if e >= n{
+        report.add(Severity::Medium, "public exponent", String::from("e is not below n"));
+    }
+
+    if n < 4 || fips186::is_prime(n){
+        report.add(Severity::Critical, "modulus", String::from("n is prime, so phi(n) = n - 1 and d follows from e"));
+        return;
+    }
+    if let Some(factor) = (2..SMOOTHNESS_BOUND).find(|f| n.is_multiple_of(*f) && n != *f){
+        report.add(Severity::Critical, "small factor", format!("n is divisible by {}", factor));
+    }
+    let root = n.isqrt();
+    if root * root == n{
+        report.add(Severity::Critical, "modulus", format!("n is the square of {}", root));
+    }
+
+    if let Some((p, q, steps)) = fermat::factor(n, budget){
+        report.add(Severity::Critical, "close primes", format!("Fermat's method found {} * {} after {} steps", p, q, steps));
+    }
+    if let Some((d, _, _)) = wiener::attack(e, n){
+        report.add(Severity::Critical, "small d", format!("Wiener's attack found d = {} from e and n", d));
+    }
+    if let Some((factor, steps)) = pollard::rho(n, budget){
+        report.add(Severity::Critical, "factored", format!("Pollard's rho found the factor {} after {} steps", factor, steps));
+    }
+    if let Some((factor, stage)) = pollard::p_minus_1(n, SMOOTHNESS_BOUND, pollard::DEFAULT_B2){
+        report.add(Severity::Critical, "smooth p - 1", format!("Pollard's p - 1 method with bounds {} and {} found the factor {} in stage {}", SMOOTHNESS_BOUND, pollard::DEFAULT_B2, factor, stage));
+    }
+}
+
+fn private_checks(report:&mut AuditReport, key:&RSAPrivateKey){
+    if let Err(e) = key.validate(){
+        report.add(Severity::Critical, "key pair", format!("{}", e));
+        return;
+    }
+    let (n, p, q, d) = (key.get_n(), key.get_p(), key.get_q(), key.get_d());
+
+    // Fermat's method needs about (p - q)^2 / (8 * sqrt(n)) steps.
+    let gap = p.abs_diff(q);
+    if gap < 1 << 16 || (gap as u128 * gap as u128) / (8 * n.isqrt() as u128) < DEFAULT_BUDGET as u128{
+        report.add(Severity::High, "close primes", format!("|p - q| = {}, Fermat's method needs under {} steps", gap, DEFAULT_BUDGET));
+    }
+    let (pbits, qbits) = (64 - p.leading_zeros(), 64 - q.leading_zeros());
+    if pbits.abs_diff(qbits) > 2{
+        report.add(Severity::Medium, "unbalanced primes", format!("p has {} bits and q {}, the smaller is easier to find", pbits, qbits));
+    }
+
+    // Wiener's attack works for d < n^(1/4) / 3.
+    if d < wiener::bound(n){
+        report.add(Severity::Critical, "small d", format!("d = {} is below n^(1/4) / 3", d));
+    }else if d < 1 << 32{
+        report.add(Severity::Medium, "small d", format!("d = {} has {} bits, under half of n's", d, 64 - d.leading_zeros()));
+    }
+
+    for (name, prime) in [("p - 1", p), ("q - 1", q)]{
+        let rest = remove_small_factors(prime - 1, SMOOTHNESS_BOUND);
+        if rest == 1{
+            report.add(Severity::High, "smooth p - 1", format!("{} = {} has no prime factor above {}", name, prime - 1, SMOOTHNESS_BOUND));
+        }
+    }
+}
+
+fn remove_small_factors(mut value:u64, bound:u64) -> u64{
+    for f in 2..=bound{
+        while value > 1 && value.is_multiple_of(f){
+            value /= f;
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+#[test]
+fn test_audit_generated_key(){
+    // A generated key is only weak for its size.
+    let key = crate::keys::other_test_key();
+    let report = audit_public_key(&key.public_key(), DEFAULT_BUDGET);
+    assert_eq!(report.worst(), Some(Severity::Critical));
+    assert_eq!(report.findings[0].check, "small modulus");
+    assert!(report.contains("factored"));
+    // These two primes differ by 12, Fermat's method finds them at once.
+    assert!(report.contains("close primes"));
+    assert!(!report.contains("public exponent"));
+    assert!(!report.contains("small d"));
+
+    let text = audit_private_key(&key, DEFAULT_BUDGET).to_string();
+    assert!(text.starts_with("Audit of the key with n = 18446743979220271189 and e = 65537\nCRITICAL  small modulus: "));
+    assert!(text.contains("HIGH      close primes: |p - q| = 12,"));
+}
+
+#[test]
+fn test_audit_weak_exponents(){
+    let n = 4294967291u64 * 4294967279;
+    let report = audit_public_key(&RSAPublicKey::new(n, 1), 1000);
+    assert!(report.findings.iter().any(|f| f.check == "public exponent" && f.severity == Severity::Critical));
+    let report = audit_public_key(&RSAPublicKey::new(n, 65536), 1000);
+    assert!(report.findings.iter().any(|f| f.detail.contains("is even")));
+    let report = audit_public_key(&RSAPublicKey::new(n, 3), 1000);
+    assert!(report.findings.iter().any(|f| f.check == "public exponent" && f.severity == Severity::Low));
+    let report = audit_public_key(&RSAPublicKey::new(4294967291, 65537), 1000);
+    assert!(report.findings.iter().any(|f| f.detail.contains("n is prime")));
+    let report = audit_public_key(&RSAPublicKey::new(4294967291 * 7, 65537), 1000);
+    assert!(report.contains("small factor"));
+}
+
+#[test]
+fn test_audit_small_d(){
+    // d = 7, far below n^(1/4) / 3, e is its inverse modulo phi(n).
+    let (p, q) = (4294967291u64, 3037000507u64);
+    let phi = (p - 1) as u128 * (q - 1) as u128;
+    let e = (1..=7).map(|k:u128| k * phi + 1).find(|x| x % 7 == 0).unwrap() / 7;
+    let key = RSAPrivateKey::from_parts(p, q, e as u64, 7).unwrap();
+    let report = audit_private_key(&key, 1000);
+    assert!(report.findings.iter().any(|f| f.check == "small d" && f.detail.contains("Wiener")));
+    assert!(report.findings.iter().any(|f| f.check == "small d" && f.detail.contains("below n^(1/4) / 3")));
+}
+
+#[test]
+fn test_audit_factoring(){
+    // 65537 - 1 = 2^16, 1000003 - 1 = 2 * 3 * 166667.
+    let report = audit_public_key(&RSAPublicKey::new(65537 * 1000003, 65537), 1000);
+    assert!(report.findings.iter().any(|f| f.check == "smooth p - 1" && f.detail.contains("found the factor 65537 in stage 1")));
+    assert_eq!(remove_small_factors(65536, 100), 1);
+    assert_eq!(remove_small_factors(2 * 3 * 166667, 100), 166667);
+}
@@ -0,0 +1,172 @@
+// Base64 encoding and decoding (RFC 4648), used for PEM armoring, and the
+// unpadded base64url variant used by JSON Web Keys.
+
+use std::fmt;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const URL_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+// Errors returned when decoding text that isn't valid base64.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Base64Error{
+    // Character outside the alphabet, with its position.
+    InvalidCharacter(usize),
+    // Length or padding doesn't match a base64 encoding.
+    InvalidLength,
+}
+
+impl fmt::Display for Base64Error{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            Base64Error::InvalidCharacter(i) => write!(f, "invalid base64 character at position {}", i),
+            Base64Error::InvalidLength => write!(f, "invalid base64 length or padding"),
+        }
+    }
+}
+
+impl std::error::Error for Base64Error{}
+
+// Encodes bytes as base64 with = padding.
+pub fn encode(data:&[u8]) -> String{
+    encode_with(data, ALPHABET)
+}
+
+// Encodes bytes as base64url without padding (RFC 7515 appendix C).
+pub fn encode_url(data:&[u8]) -> String{
+    let mut result = encode_with(data, URL_ALPHABET);
+    while result.ends_with('='){
+        result.pop();
+    }
+    result
+}
+
+fn encode_with(data:&[u8], alphabet:&[u8; 64]) -> String{
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3){
+        let b0 = chunk[0] as u32;
+        let b1 = *chunk.get(1).unwrap_or(&0) as u32;
+        let b2 = *chunk.get(2).unwrap_or(&0) as u32;
+        let triple = (b0 << 16) | (b1 << 8) | b2;
+
+        result.push(alphabet[(triple >> 18) as usize & 0x3f] as char);
+        result.push(alphabet[(triple >> 12) as usize & 0x3f] as char);
+        if chunk.len() > 1{
+            result.push(alphabet[(triple >> 6) as usize & 0x3f] as char);
+        }else{
+            result.push('=');
+        }
+        if chunk.len() > 2{
+            result.push(alphabet[triple as usize & 0x3f] as char);
+        }else{
+            result.push('=');
+        }
+    }
+    result
+}
+
+// Encodes bytes as base64 without the = padding, as OpenSSH fingerprints are shown.
+pub fn encode_unpadded(data:&[u8]) -> String{
+    let mut result = encode(data);
+    while result.ends_with('='){
+        result.pop();
+    }
+    result
+}
+
+// Decodes padded base64, the length must be a multiple of 4.
+pub fn decode(text:&str) -> Result<Vec<u8>, Base64Error>{
+    let bytes = text.as_bytes();
+    if !bytes.len().is_multiple_of(4){
+        return Err(Base64Error::InvalidLength);
+    }
+    let padding = bytes.iter().rev().take_while(|b| **b == b'=').count();
+    if padding > 2{
+        return Err(Base64Error::InvalidLength);
+    }
+    decode_with(&bytes[..bytes.len() - padding], decode_character)
+}
+
+// Decodes unpadded base64url, padding characters are not allowed.
+pub fn decode_url(text:&str) -> Result<Vec<u8>, Base64Error>{
+    // One leftover character can't hold a whole byte.
+    if text.len() % 4 == 1{
+        return Err(Base64Error::InvalidLength);
+    }
+    decode_with(text.as_bytes(), decode_url_character)
+}
+
+fn decode_with(bytes:&[u8], lookup:fn(u8) -> Option<u32>) -> Result<Vec<u8>, Base64Error>{
+    let mut result:Vec<u8> = Vec::with_capacity(bytes.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+    for (i, b) in bytes.iter().enumerate(){
+        let value = match lookup(*b){
+            Some(s) => s,
+            None => return Err(Base64Error::InvalidCharacter(i)),
+        };
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8{
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    // Leftover bits must be zero in a canonical encoding.
+    if buffer != 0{
+        return Err(Base64Error::InvalidLength);
+    }
+    Ok(result)
+}
+
+fn decode_character(c:u8) -> Option<u32>{
+    match c{
+        b'A'..=b'Z' => Some((c - b'A') as u32),
+        b'a'..=b'z' => Some((c - b'a' + 26) as u32),
+        b'0'..=b'9' => Some((c - b'0' + 52) as u32),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+fn decode_url_character(c:u8) -> Option<u32>{
+    match c{
+        b'-' => Some(62),
+        b'_' => Some(63),
+        b'+' | b'/' => None,
+        _ => decode_character(c),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_base64_vectors(){
+    // Test vectors from RFC 4648 section 10.
+    let vectors = [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg=="), ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy")];
+    for (plain, encoded) in vectors{
+        assert_eq!(encode(plain.as_bytes()), encoded);
+        assert_eq!(decode(encoded).unwrap(), plain.as_bytes());
+        assert_eq!(encode_unpadded(plain.as_bytes()), encoded.trim_end_matches('='));
+    }
+}
+
+#[test]
+fn test_base64_invalid(){
+    assert_eq!(decode("Zm9"), Err(Base64Error::InvalidLength));
+    assert_eq!(decode("Zm9v!A=="), Err(Base64Error::InvalidCharacter(4)));
+    assert_eq!(decode("Zh=="), Err(Base64Error::InvalidLength));
+    assert_eq!(decode("Z==="), Err(Base64Error::InvalidLength));
+}
+
+#[test]
+fn test_base64_url(){
+    assert_eq!(encode_url(&[0x01, 0x00, 0x01]), "AQAB");
+    assert_eq!(encode_url(&[0xfb, 0xff]), "-_8");
+    assert_eq!(decode_url("-_8"), Ok(vec![0xfb, 0xff]));
+    assert_eq!(decode_url(""), Ok(Vec::new()));
+    assert_eq!(decode_url("AQAB"), Ok(vec![0x01, 0x00, 0x01]));
+    assert_eq!(decode_url("+/8"), Err(Base64Error::InvalidCharacter(0)));
+    assert_eq!(decode_url("AQ=="), Err(Base64Error::InvalidCharacter(2)));
+    assert_eq!(decode_url("AQABA"), Err(Base64Error::InvalidLength));
+}
@@ -0,0 +1,139 @@
+// Finds moduli that share a prime with Bernstein's batch GCD. Two keys that
+// share a prime are both broken by gcd(n1, n2), but checking every pair of a
+// long list takes a GCD per pair. Instead all the moduli are multiplied
+// together in a product tree, the product P is brought back down a remainder
+// tree as P mod n^2 for each modulus n, and (P mod n^2) / n is the product of
+// all the other moduli mod n, so one GCD with n shows whether n shares a
+// prime with any of them. Only the moduli that do are then checked in pairs.
+//
+// The prime search in the tutorial depends only on the number it starts
+// from, so two people who start from the same number get the same first
+// prime.
+
+use crate::biguint::BigUint;
+use crate::utility;
+use std::fmt;
+
+// Two moduli, by their position in the list, and what they have in common.
+// When the moduli are the same, prime is the whole modulus and the cofactors
+// are 1, a GCD can't split it, but each owner can decrypt for the other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedFactor{
+    pub first:usize,
+    pub second:usize,
+    pub prime:u64,
+    pub first_cofactor:u64,
+    pub second_cofactor:u64,
+}
+
+impl SharedFactor{
+    pub fn is_duplicate(&self) -> bool{
+        self.first_cofactor == 1 && self.second_cofactor == 1
+    }
+}
+
+impl fmt::Display for SharedFactor{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        if self.is_duplicate(){
+            return write!(f, "moduli {} and {} are the same, {}", self.first + 1, self.second + 1, self.prime);
+        }
+        write!(f, "moduli {} and {} share the prime {}: {} = {} * {}, {} = {} * {}", self.first + 1, self.second + 1, self.prime,
+            self.prime as u128 * self.first_cofactor as u128, self.prime, self.first_cofactor,
+            self.prime as u128 * self.second_cofactor as u128, self.prime, self.second_cofactor)
+    }
+}
+
+// Levels of the product tree, the moduli first and the product of all of
+// them last. Each node is the product of two nodes on the level below, an odd
+// one out is carried up as it is.
+pub fn product_tree(moduli:&[u64]) -> Vec<Vec<BigUint>>{
+    let mut levels = vec![moduli.iter().map(|n| BigUint::from_u64(*n)).collect::<Vec<BigUint>>()];
+    while levels[levels.len() - 1].len() > 1{
+        let next = levels[levels.len() - 1].chunks(2).map(|pair| match pair{
+            [a, b] => a.mul(b),
+            _ => pair[0].clone(),
+        }).collect();
+        levels.push(next);
+    }
+    levels
+}
+
+// P mod n^2 for each modulus n, where P is the root of the tree. Going down a
+// level, each node's remainder is its parent's remainder mod the node
+// squared, so the numbers shrink on the way down instead of dividing P by
+// every modulus.
+pub fn remainder_tree(tree:&[Vec<BigUint>]) -> Vec<u128>{
+    let mut remainders = match tree.last(){
+        Some(root) => root.clone(),
+        None => return Vec::new(),
+    };
+    for level in tree.iter().rev().skip(1){
+        remainders = level.iter().enumerate().map(|(i, node)| remainders[i / 2].rem(&node.mul(node))).collect();
+    }
+    // n^2 fits in a u128, so each remainder does too.
+    remainders.iter().map(|r| r.to_u128().unwrap_or(0)).collect()
+}
+
+// gcd(n, product of the other moduli) for each modulus, 1 when it shares no
+// prime with the rest. Zero moduli are left out of the product and get 0.
+pub fn batch_gcd(moduli:&[u64]) -> Vec<u64>{
+    let nonzero:Vec<u64> = moduli.iter().map(|n| (*n).max(1)).collect();
+    let remainders = remainder_tree(&product_tree(&nonzero));
+    nonzero.iter().zip(remainders).zip(moduli).map(|((n, r), original)| {
+        if *original == 0{
+            return 0;
+        }
+        utility::gcd((r / *n as u128) as u64, *n)
+    }).collect()
+}
+
+// Every pair of moduli sharing a prime, found by batch GCD and then checked
+// pair by pair among the moduli it flags.
+pub fn find_shared_factors(moduli:&[u64]) -> Vec<SharedFactor>{
+    let flagged:Vec<usize> = batch_gcd(moduli).iter().enumerate().filter(|(_, g)| **g > 1).map(|(i, _)| i).collect();
+    let mut shared:Vec<SharedFactor> = Vec::new();
+    for (k, i) in flagged.iter().enumerate(){
+        for j in &flagged[k + 1..]{
+            let prime = utility::gcd(moduli[*i], moduli[*j]);
+            if prime > 1{
+                shared.push(SharedFactor{ first: *i, second: *j, prime, first_cofactor: moduli[*i] / prime, second_cofactor: moduli[*j] / prime });
+            }
+        }
+    }
+    shared
+}
+
+#[cfg(test)]
+#[test]
+fn test_product_and_remainder_trees(){
+    let moduli = [15u64, 77, 221, 11];
+    let tree = product_tree(&moduli);
+    assert_eq!(tree.len(), 3);
+    assert_eq!(tree[2][0], BigUint::from_u64(15 * 77 * 221 * 11));
+    let product = 15u128 * 77 * 221 * 11;
+    assert_eq!(remainder_tree(&tree), moduli.iter().map(|n| product % (*n as u128 * *n as u128)).collect::<Vec<u128>>());
+    // 5 leaves makes an odd one out on the first two levels.
+    assert_eq!(product_tree(&[2, 3, 5, 7, 11]).last().unwrap()[0], BigUint::from_u64(2310));
+    assert!(product_tree(&[]).last().unwrap().is_empty());
+}
+
+#[test]
+fn test_find_shared_factors(){
+    // Keys from the same start number share p = 50000000021.
+    let p = 50000000021u64;
+    let q1 = 368934871u64;
+    let q2 = 368934869u64;
+    let other = 4294967291u64 * 4294967279;
+    let moduli = [p * q1, other, 3 * 5, p * q2, 7 * 11, other];
+    assert_eq!(batch_gcd(&moduli), vec![p, other, 1, p, 1, other]);
+    let shared = find_shared_factors(&moduli);
+    assert_eq!(shared, vec![
+        SharedFactor{ first: 0, second: 3, prime: p, first_cofactor: q1, second_cofactor: q2 },
+        SharedFactor{ first: 1, second: 5, prime: other, first_cofactor: 1, second_cofactor: 1 },
+    ]);
+    assert!(!shared[0].is_duplicate());
+    assert!(shared[1].is_duplicate());
+    assert_eq!(shared[0].to_string(), format!("moduli 1 and 4 share the prime {}: {} = {} * {}, {} = {} * {}", p, p * q1, p, q1, p * q2, p, q2));
+    assert!(find_shared_factors(&[15, 77, 0]).is_empty());
+    assert_eq!(batch_gcd(&[15, 0, 35]), vec![5, 0, 5]);
+}
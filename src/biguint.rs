@@ -0,0 +1,283 @@
+// Unsigned integers of any size, just enough arithmetic for the attacks that
+// have to keep products of several 64 bit moduli whole, the batch GCD trees
+// and the CRT in Håstad's attack. Numbers are stored as 64 bit limbs, least
+// significant first, with no zero limbs at the top, so zero has no limbs at
+// all.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigUint{
+    limbs:Vec<u64>,
+}
+
+impl BigUint{
+    pub fn zero() -> BigUint{
+        BigUint{ limbs: Vec::new() }
+    }
+
+    pub fn from_u64(value:u64) -> BigUint{
+        BigUint::from_limbs(vec![value])
+    }
+
+    pub fn from_u128(value:u128) -> BigUint{
+        BigUint::from_limbs(vec![value as u64, (value >> 64) as u64])
+    }
+
+    fn from_limbs(mut limbs:Vec<u64>) -> BigUint{
+        while limbs.last() == Some(&0){
+            limbs.pop();
+        }
+        BigUint{ limbs }
+    }
+
+    pub fn is_zero(&self) -> bool{
+        self.limbs.is_empty()
+    }
+
+    pub fn bits(&self) -> u64{
+        match self.limbs.last(){
+            Some(top) => self.limbs.len() as u64 * 64 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    // The value if it fits in a u128.
+    pub fn to_u128(&self) -> Option<u128>{
+        match self.limbs.len(){
+            0 => Some(0),
+            1 => Some(self.limbs[0] as u128),
+            2 => Some(self.limbs[0] as u128 | (self.limbs[1] as u128) << 64),
+            _ => None,
+        }
+    }
+
+    pub fn add(&self, other:&BigUint) -> BigUint{
+        let (long, short) = if self.limbs.len() >= other.limbs.len() {(self, other)} else {(other, self)};
+        let mut result:Vec<u64> = Vec::with_capacity(long.limbs.len() + 1);
+        let mut carry = 0u128;
+        for (i, limb) in long.limbs.iter().enumerate(){
+            let t = *limb as u128 + *short.limbs.get(i).unwrap_or(&0) as u128 + carry;
+            result.push(t as u64);
+            carry = t >> 64;
+        }
+        result.push(carry as u64);
+        BigUint::from_limbs(result)
+    }
+
+    // Schoolbook multiplication, each limb of one times each limb of the other.
+    pub fn mul(&self, other:&BigUint) -> BigUint{
+        if self.is_zero() || other.is_zero(){
+            return BigUint::zero();
+        }
+        let mut result = vec![0u64; self.limbs.len() + other.limbs.len()];
+        for (i, a) in self.limbs.iter().enumerate(){
+            let mut carry = 0u128;
+            for (j, b) in other.limbs.iter().enumerate(){
+                let t = *a as u128 * *b as u128 + result[i + j] as u128 + carry;
+                result[i + j] = t as u64;
+                carry = t >> 64;
+            }
+            result[i + other.limbs.len()] = carry as u64;
+        }
+        BigUint::from_limbs(result)
+    }
+
+    // Quotient and remainder, panics when dividing by zero.
+    pub fn div_rem(&self, divisor:&BigUint) -> (BigUint, BigUint){
+        if divisor.is_zero(){
+            panic!("Division of a BigUint by zero.");
+        }
+        if self < divisor{
+            return (BigUint::zero(), self.clone());
+        }
+        if divisor.limbs.len() == 1{
+            let (quotient, remainder) = self.div_rem_u64(divisor.limbs[0]);
+            return (quotient, BigUint::from_u64(remainder));
+        }
+        long_division(&self.limbs, &divisor.limbs)
+    }
+
+    pub fn rem(&self, divisor:&BigUint) -> BigUint{
+        self.div_rem(divisor).1
+    }
+
+    pub fn div_rem_u64(&self, divisor:u64) -> (BigUint, u64){
+        if divisor == 0{
+            panic!("Division of a BigUint by zero.");
+        }
+        let mut quotient = vec![0u64; self.limbs.len()];
+        let mut remainder = 0u128;
+        for i in (0..self.limbs.len()).rev(){
+            let current = remainder << 64 | self.limbs[i] as u128;
+            quotient[i] = (current / divisor as u128) as u64;
+            remainder = current % divisor as u128;
+        }
+        (BigUint::from_limbs(quotient), remainder as u64)
+    }
+}
+
+// Knuth's algorithm D (The Art of Computer Programming, volume 2, 4.3.1).
+// Both numbers are shifted so the divisor's top limb has its top bit set,
+// then each quotient limb is estimated from the top two limbs of what is
+// left, corrected at most twice, and the remainder shifted back at the end.
+fn long_division(u:&[u64], v:&[u64]) -> (BigUint, BigUint){
+    let shift = v[v.len() - 1].leading_zeros();
+    let v = shift_left(v, shift);
+    let mut u = shift_left(u, shift);
+    u.push(0);
+    if u.len() < v.len() + 1{
+        u.push(0);
+    }
+    let n = v.len();
+    let m = u.len() - n - 1;
+    let base = 1u128 << 64;
+    let mut quotient = vec![0u64; m + 1];
+    for j in (0..=m).rev(){
+        let top = (u[j + n] as u128) << 64 | u[j + n - 1] as u128;
+        let mut qhat = top / v[n - 1] as u128;
+        let mut rhat = top % v[n - 1] as u128;
+        while qhat >= base || qhat * v[n - 2] as u128 > (rhat << 64 | u[j + n - 2] as u128){
+            qhat -= 1;
+            rhat += v[n - 1] as u128;
+            if rhat >= base{
+                break;
+            }
+        }
+
+        // u[j..=j + n] -= qhat * v
+        let mut borrow = 0i128;
+        let mut carry = 0u128;
+        for i in 0..n{
+            let product = qhat * v[i] as u128 + carry;
+            carry = product >> 64;
+            let t = u[i + j] as i128 - borrow - (product as u64) as i128;
+            u[i + j] = t as u64;
+            borrow = if t < 0 {1} else {0};
+        }
+        let t = u[j + n] as i128 - borrow - carry as i128;
+        u[j + n] = t as u64;
+
+        quotient[j] = qhat as u64;
+        if t < 0{
+            // qhat was one too large, add v back.
+            quotient[j] -= 1;
+            let mut carry = 0u128;
+            for i in 0..n{
+                let sum = u[i + j] as u128 + v[i] as u128 + carry;
+                u[i + j] = sum as u64;
+                carry = sum >> 64;
+            }
+            u[j + n] = u[j + n].wrapping_add(carry as u64);
+        }
+    }
+    u.truncate(n);
+    (BigUint::from_limbs(quotient), BigUint::from_limbs(shift_right(&u, shift)))
+}
+
+fn shift_left(limbs:&[u64], shift:u32) -> Vec<u64>{
+    if shift == 0{
+        return limbs.to_vec();
+    }
+    let mut result = Vec::with_capacity(limbs.len() + 1);
+    let mut carry = 0u64;
+    for limb in limbs{
+        result.push(limb << shift | carry);
+        carry = limb >> (64 - shift);
+    }
+    if carry != 0{
+        result.push(carry);
+    }
+    result
+}
+
+fn shift_right(limbs:&[u64], shift:u32) -> Vec<u64>{
+    if shift == 0{
+        return limbs.to_vec();
+    }
+    let mut result = vec![0u64; limbs.len()];
+    for i in 0..limbs.len(){
+        let high = if i + 1 < limbs.len() {limbs[i + 1] << (64 - shift)} else {0};
+        result[i] = limbs[i] >> shift | high;
+    }
+    result
+}
+
+impl Ord for BigUint{
+    fn cmp(&self, other:&BigUint) -> Ordering{
+        self.limbs.len().cmp(&other.limbs.len()).then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint{
+    fn partial_cmp(&self, other:&BigUint) -> Option<Ordering>{
+        Some(self.cmp(other))
+    }
+}
+
+// Decimal, 19 digits at a time.
+impl fmt::Display for BigUint{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        if self.is_zero(){
+            return write!(f, "0");
+        }
+        let mut parts:Vec<u64> = Vec::new();
+        let mut rest = self.clone();
+        while !rest.is_zero(){
+            let (quotient, remainder) = rest.div_rem_u64(10_000_000_000_000_000_000);
+            parts.push(remainder);
+            rest = quotient;
+        }
+        write!(f, "{}", parts[parts.len() - 1])?;
+        for part in parts.iter().rev().skip(1){
+            write!(f, "{:019}", part)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_biguint_small(){
+    let a = BigUint::from_u128(0x1234_5678_9abc_def0_1122_3344_5566_7788);
+    let b = BigUint::from_u64(0xfedc_ba98_7654_3210);
+    let (q, r) = a.div_rem(&b);
+    assert_eq!(q.to_u128(), Some(0x1234_5678_9abc_def0_1122_3344_5566_7788 / 0xfedc_ba98_7654_3210));
+    assert_eq!(r.to_u128(), Some(0x1234_5678_9abc_def0_1122_3344_5566_7788 % 0xfedc_ba98_7654_3210));
+    assert_eq!(BigUint::from_u64(0), BigUint::zero());
+    assert_eq!(BigUint::zero().bits(), 0);
+    assert_eq!(BigUint::from_u128(1 << 100).bits(), 101);
+    assert_eq!(BigUint::from_u64(u64::MAX).mul(&BigUint::from_u64(u64::MAX)).to_u128(), Some(u64::MAX as u128 * u64::MAX as u128));
+    assert!(BigUint::from_u64(5) < BigUint::from_u128(1 << 64));
+    assert_eq!(BigUint::from_u128(u128::MAX).to_string(), u128::MAX.to_string());
+    assert_eq!(BigUint::from_u64(7).rem(&BigUint::from_u128(1 << 70)), BigUint::from_u64(7));
+    assert_eq!(BigUint::from_u64(u64::MAX).add(&BigUint::from_u64(1)).to_u128(), Some(1 << 64));
+    assert_eq!(BigUint::zero().add(&BigUint::from_u64(3)), BigUint::from_u64(3));
+}
+
+#[test]
+fn test_biguint_long_division(){
+    // (a * b + r) / b = a remainder r, for multi limb a and b.
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for size in [2usize, 3, 5, 9]{
+        for _ in 0..20{
+            let a = BigUint::from_limbs((0..size + 2).map(|_| next()).collect());
+            let mut b_limbs:Vec<u64> = (0..size).map(|_| next()).collect();
+            // Small top limbs take the shifting and correction paths.
+            b_limbs[size - 1] >>= next() % 64;
+            b_limbs[size - 1] |= 1;
+            let b = BigUint::from_limbs(b_limbs);
+            let r = BigUint::from_limbs((0..size).map(|_| next()).collect()).rem(&b);
+            let (q, remainder) = a.mul(&b).add(&r).div_rem(&b);
+            assert_eq!(q, a);
+            assert_eq!(remainder, r);
+        }
+    }
+}
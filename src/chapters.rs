@@ -0,0 +1,498 @@
+// Chapters of the tutorial on breaking RSA keys. Each one makes a key with a
+// weakness, or takes the key made in the tutorial, and shows an attack on it
+// step by step.
+
+use crate::{convert_raw_bytes, decrypt_message, depad_the_bytes, encrypt_message, encryption_process, get_integers, get_user_string, getbytes, pad_the_bytes};
+use crate::batchgcd;
+use crate::commonmodulus;
+use crate::fermat;
+use crate::fips186;
+use crate::hastad;
+use crate::keys;
+use crate::pollard;
+use crate::structures;
+use crate::utility;
+use crate::wiener;
+use std::time::Instant;
+
+// Chapters on breaking RSA keys, picked from a list until the user is done.
+// Some of them attack the key made in the tutorial.
+pub fn attack_chapters(info:&structures::RSAInfo){
+    loop{
+        println!("");
+        println!("Would you like to see how RSA keys are broken? Type the number of a chapter, or press enter to finish.");
+        println!("1. Keys that share a prime, found with batch GCD.");
+        println!("2. Factoring your key with Pollard's rho and p - 1.");
+        println!("3. Primes close together, found with Fermat's method.");
+        println!("4. A small d, found with Wiener's attack.");
+        println!("5. One message sent to three keys with e = 3, read with Håstad's broadcast attack.");
+        println!("6. Two keys sharing your n, read with the common modulus attack.");
+        let s = get_user_string();
+        println!("");
+        println!("-----------------------------------------------------------------");
+        println!("");
+        match &s[..]{
+            "1" => shared_prime_chapter(),
+            "2" => factoring_chapter(&keys::RSAPublicKey::new(info.get_n(), info.get_e())),
+            "3" => fermat_chapter(),
+            "4" => wiener_chapter(),
+            "5" => broadcast_chapter(),
+            "6" => common_modulus_chapter(info),
+            _ => {break;}
+        }
+    }
+}
+
+// Makes two keys from the same start number that share a prime, hides them
+// among other keys and finds them with batch GCD, then decrypts a message
+// sent to each of them.
+fn shared_prime_chapter(){
+    println!("The primes the tutorial finds depend only on the number you start from.");
+    println!("Lets make two keys, Alice's and Bob's, from the same start number. Enter a number, or press enter for 50000000000.");
+    let start = get_user_string().parse::<u64>().unwrap_or(50000000000);
+    let (p, alice_q) = utility::find_prime(start);
+    // The same start gives the same pair, so Bob's program takes the next prime down.
+    let bob_q = match (2..alice_q).rev().find(|i| fips186::is_prime(*i)){
+        Some(s) => s,
+        None => {
+            println!("There is no prime below {} for Bob.", alice_q);
+            return;
+        },
+    };
+    let mut e = utility::DEFAULT_E;
+    let (alice, bob) = loop{
+        match (utility::recover_private_key(p, alice_q, e), utility::recover_private_key(p, bob_q, e)){
+            (Some(a), Some(b)) => break (a.public_key(), b.public_key()),
+            _ => e += 2,
+        }
+    };
+    println!("Alice's primes are {} and {}, her modulus is {}.", p, alice_q, alice.get_n());
+    println!("Bob's program searched a little differently for the second prime and picked {}, his modulus is {}.", bob_q, bob.get_n());
+    println!("The moduli are different, but both have {} as a factor. Had Bob picked the same second prime, the keys would be identical.", p);
+    println!("Press enter to continue.");
+    _ = get_user_string();
+
+    let mut moduli:Vec<u64> = vec![alice.get_n()];
+    for _ in 0..6{
+        match keys::RSAPrivateKey::generate(){
+            Ok(s) => moduli.push(s.get_n()),
+            Err(e) => println!("Unable to make another key. Error: {}", e),
+        }
+    }
+    moduli.push(bob.get_n());
+    println!("An attacker collects public keys, here are {} of them with Alice's first and Bob's last:", moduli.len());
+    for n in &moduli{
+        println!("{}", n);
+    }
+    println!("Checking every pair with a GCD takes a GCD per pair, which for millions of keys is too many.");
+    println!("Batch GCD multiplies the moduli together in pairs, then the pairs in pairs, up a product tree:");
+    let started = Instant::now();
+    let tree = batchgcd::product_tree(&moduli);
+    for (level, nodes) in tree.iter().enumerate(){
+        let bits:Vec<String> = nodes.iter().map(|n| n.bits().to_string()).collect();
+        println!("level {}: {} bits", level, bits.join(", "));
+    }
+    println!("The root is the product P of all of them, {}.", tree[tree.len() - 1][0]);
+    println!("Going back down a remainder tree, each node keeps its parent's remainder mod the node squared,");
+    println!("so each modulus n gets P mod n^2, and (P mod n^2) / n is the product of the other moduli mod n.");
+    let remainders = batchgcd::remainder_tree(&tree);
+    let gcds = batchgcd::batch_gcd(&moduli);
+    for ((n, r), g) in moduli.iter().zip(&remainders).zip(&gcds){
+        println!("n = {}, P mod n^2 = {}, gcd(n, (P mod n^2) / n) = {}", n, r, g);
+    }
+    let shared = batchgcd::find_shared_factors(&moduli);
+    let elapsed = started.elapsed();
+    println!("A gcd above 1 means the modulus shares a prime with another, only those are checked in pairs.");
+    for pair in &shared{
+        println!("{}", pair);
+    }
+    println!("That took {:?}.", elapsed);
+    println!("Press enter to continue.");
+    _ = get_user_string();
+
+    let message = b"Meet me at noon";
+    println!("Alice and Bob are each sent the message \"{}\" encrypted with their public key.", String::from_utf8_lossy(message));
+    for pair in shared.iter().filter(|s| !s.is_duplicate()){
+        for (index, cofactor) in [(pair.first, pair.first_cofactor), (pair.second, pair.second_cofactor)]{
+            let public = if moduli[index] == alice.get_n() {&alice} else {&bob};
+            let key = match utility::recover_private_key(pair.prime, cofactor, public.get_e()){
+                Some(s) => s,
+                None => continue,
+            };
+            println!("");
+            println!("n = {} = {} * {}, so lambda is {} and d = e ^ -1 mod lambda = {}.", key.get_n(), pair.prime, cofactor, key.to_rsainfo().get_lambda(), key.get_d());
+            let decrypted = encrypt_message(public, message).and_then(|c| decrypt_message(&key, &c));
+            match decrypted{
+                Some(s) => println!("With the recovered d the message decrypts to \"{}\".", String::from_utf8_lossy(&s)),
+                None => println!("The message could not be encrypted with this key."),
+            }
+        }
+    }
+    println!("");
+    println!("Primes must come from a good random source, never from a number people are likely to pick.");
+}
+
+// Factors the tutorial's key from its public half with Pollard's rho and
+// p - 1, then works out d and decrypts a message sent to the key.
+fn factoring_chapter(public:&keys::RSAPublicKey){
+    let (n, e) = (public.get_n(), public.get_e());
+    let message = b"The eagle has landed";
+    let cypher = match encrypt_message(public, message){
+        Some(s) => s,
+        None => {
+            println!("Your key leaves part of the message unchanged, try the chapter again with another key.");
+            return;
+        },
+    };
+    println!("An attacker only has your public key, n = {} and e = {}.", n, e);
+    println!("Someone sends you \"{}\", and the attacker sees it encrypted:", String::from_utf8_lossy(message));
+    println!("{:?}", cypher);
+    println!("If the attacker can split n into p and q, they can work out d the same way you did.");
+    println!("Press enter to continue.");
+    _ = get_user_string();
+    println!("");
+
+    println!("Pollard's rho walks x -> x^2 + c mod n. Mod the smaller prime p the walk repeats within about sqrt(p) steps,");
+    println!("and when two points meet mod p, p divides their difference, so gcd(difference, n) is p.");
+    println!("Brent's method saves a point at every power of two steps and compares the points after it with it.");
+    let started = Instant::now();
+    // Far more steps than a 64 bit n needs.
+    let rho = pollard::rho(n, 1 << 26);
+    let rho_time = started.elapsed();
+    match rho{
+        Some((factor, steps)) => println!("Rho found {} after {} steps, n = {} * {}. That took {:?}.", factor, steps, factor, n / factor, rho_time),
+        None => println!("Rho didn't find a factor, which took {:?}.", rho_time),
+    }
+    println!("Press enter to continue.");
+    _ = get_user_string();
+    println!("");
+
+    println!("Pollard's p - 1 method finds p when p - 1 has only small prime factors. Stage 1 raises 2 to every prime");
+    println!("power up to a bound B1, stage 2 also lets p - 1 have one larger prime factor up to a bound B2.");
+    println!("Larger bounds find more primes but take longer. Enter B1, or press enter for {}.", pollard::DEFAULT_B1);
+    let b1 = get_user_string().parse::<u64>().unwrap_or(pollard::DEFAULT_B1);
+    println!("Enter B2, or press enter for {}. B2 is kept at or below 100000000 so the sieve for it fits in memory.", pollard::DEFAULT_B2);
+    let b2 = get_user_string().parse::<u64>().unwrap_or(pollard::DEFAULT_B2).min(100000000);
+    let started = Instant::now();
+    let smooth = pollard::p_minus_1(n, b1, b2);
+    let smooth_time = started.elapsed();
+    match smooth{
+        Some((factor, stage)) => println!("Stage {} found {}, so {} - 1 has no prime factor above {}. That took {:?}.", stage, factor, factor, if stage == 1 {b1} else {b2}, smooth_time),
+        None => println!("p - 1 didn't find a factor in {:?}, neither p - 1 nor q - 1 is smooth enough for these bounds.", smooth_time),
+    }
+    println!("Press enter to continue.");
+    _ = get_user_string();
+    println!("");
+
+    let factor = match rho.map(|(f, _)| f).or(smooth.map(|(f, _)| f)){
+        Some(s) => s,
+        None => {
+            println!("Neither method found a factor this time, though with more steps or larger bounds they would.");
+            return;
+        },
+    };
+    let key = match utility::recover_private_key(factor, n / factor, e){
+        Some(s) => s,
+        None => {
+            println!("e has no inverse modulo lambda for these primes, so this isn't a working key.");
+            return;
+        },
+    };
+    println!("With p = {} and q = {}, lambda = lcm(p - 1, q - 1) = {}.", key.get_p(), key.get_q(), key.to_rsainfo().get_lambda());
+    println!("The extended euclidean algorithm gives d = {}, the inverse of e modulo lambda.", key.get_d());
+    match decrypt_message(&key, &cypher){
+        Some(s) => println!("The attacker decrypts the message: \"{}\".", String::from_utf8_lossy(&s)),
+        None => println!("The message didn't decrypt with the recovered key."),
+    }
+    println!("Factoring took {:?} in all. A 2048 bit modulus has primes of over 300 digits, far beyond both methods.", rho_time + smooth_time);
+}
+
+// Makes a key from a start near sqrt(u64::MAX), where the two primes
+// find_prime picks are close, and factors it with Fermat's method one step
+// at a time.
+fn fermat_chapter(){
+    println!("find_prime takes the prime nearest your start number and then the largest prime that keeps n below u64::MAX.");
+    println!("Starting near sqrt(u64::MAX), about 4294967296, the second prime is close to the first.");
+    println!("Enter a start number, or press enter for 4294000000.");
+    let start = get_user_string().parse::<u64>().unwrap_or(4294000000);
+    let (p, q) = utility::find_prime(start);
+    // find_prime keeps p * q below u64::MAX.
+    let n = p * q;
+    println!("The primes are {} and {}, {} apart, and n = {}.", p, q, p.abs_diff(q), n);
+    if p == q{
+        println!("find_prime picked the same prime twice, so n is a square and the first step finds it.");
+    }
+    println!("");
+    println!("Fermat noticed that an odd n = p * q is a difference of two squares, a^2 - b^2 = (a - b)(a + b),");
+    println!("with a = (p + q) / 2 halfway between the primes and b = (q - p) / 2 half the gap between them.");
+    println!("When the primes are close, a is only a little above sqrt(n). So we try a = ceil(sqrt(n)), then one more,");
+    println!("and so on until a^2 - n is a square b^2. It takes about (q - p)^2 / (8 * sqrt(n)) steps, here {}.",
+        (p.abs_diff(q) as u128 * p.abs_diff(q) as u128) / (8 * n.isqrt() as u128) + 1);
+    println!("Enter the most steps to try, or press enter for {}.", fermat::DEFAULT_BUDGET);
+    let budget = get_user_string().parse::<u64>().unwrap_or(fermat::DEFAULT_BUDGET);
+    println!("Press enter after each step, or type all to run the rest without stopping.");
+
+    let mut stepping = true;
+    let mut found = None;
+    for (index, step) in fermat::steps(n).take(budget as usize).enumerate(){
+        if stepping{
+            match step.b{
+                Some(b) => println!("Step {}: a = {}, a^2 - n = {} = {}^2, a square.", index + 1, step.a, step.b_squared, b),
+                None => println!("Step {}: a = {}, a^2 - n = {}, its square root is {} and a bit, not a square.", index + 1, step.a, step.b_squared, step.b_squared.isqrt()),
+            }
+        }
+        if let Some(b) = step.b{
+            found = Some((step.a, b, index + 1));
+            break;
+        }
+        if stepping && get_user_string() == "all"{
+            stepping = false;
+        }
+    }
+    println!("");
+    let (a, b, steps) = match found{
+        Some(s) => s,
+        None => {
+            println!("a^2 - n wasn't a square within {} steps, the primes are too far apart for this budget.", budget);
+            return;
+        },
+    };
+    println!("After {} steps a = {} and b = {}, so p = a - b = {} and q = a + b = {}.", steps, a, b, a - b, a + b);
+    let started = Instant::now();
+    let result = fermat::factor(n, budget);
+    println!("Without stopping at each step that takes {:?}.", started.elapsed());
+    if let Some((p, q, _)) = result{
+        println!("{} * {} = {}, which is n, so the key is broken and d can be worked out from p and q.", p, q, p as u128 * q as u128);
+    }
+    println!("Primes must be picked independently, FIPS 186 asks for |p - q| > 2^(nlen/2 - 100) for this reason.");
+}
+
+// Makes a key with a small d on purpose, as steering e to make decryption
+// fast would, and finds d from the public key with Wiener's attack.
+fn wiener_chapter(){
+    // Primes of about the same size, which the attack assumes.
+    let (p, q) = utility::find_prime(4000000000);
+    let n = p * q;
+    let phi = (p - 1) * (q - 1);
+    let bound = wiener::bound(n);
+    let inverse = |d:u64| if d > 1 {utility::mod_inverse(d, phi)} else {None};
+    let largest = (3..bound).rev().find(|d| inverse(*d).is_some()).unwrap_or(3);
+    println!("Decryption does a multiplication or two for every bit of d, so a small d decrypts faster.");
+    println!("In the tutorial d is worked out from e, but by trying different values of e you could steer d to be small.");
+    println!("Lets pick d directly with p = {} and q = {}, which are about the same size, so n = {}.", p, q, n);
+    println!("Wiener's attack is sure to work when d is below n^(1/4) / 3, which is {}.", bound);
+    println!("Enter a small d, or press enter for {}.", largest);
+    let typed = get_user_string().parse::<u64>().unwrap_or(largest).clamp(3, phi - 1);
+    let (d, e) = match (typed..phi).find_map(|d| inverse(d).map(|e| (d, e))){
+        Some(s) => s,
+        None => {
+            println!("No d from {} up to phi has an inverse modulo phi, try the chapter again with a smaller d.", typed);
+            return;
+        },
+    };
+    println!("d = {} has e = {} as its inverse modulo phi. The public key is n = {} and e = {}.", d, e, n, e);
+    let message = b"Small d, big mistake";
+    let cypher = match encrypt_message(&keys::RSAPublicKey::new(n, e), message){
+        Some(s) => s,
+        None => {
+            println!("This key leaves part of the message unchanged, try the chapter again with another d.");
+            return;
+        },
+    };
+    println!("Someone sends \"{}\" encrypted with it.", String::from_utf8_lossy(message));
+    println!("Press enter to continue.");
+    _ = get_user_string();
+    println!("");
+
+    println!("e * d = 1 + k * phi for some k, and phi is close to n, so k / d is close to e / n.");
+    println!("Written as a continued fraction, e / n = {} / {} has the quotients", e, n);
+    println!("{:?}", wiener::continued_fraction(e, n));
+    println!("Cutting it off after each quotient gives the convergents, fractions closer and closer to e / n.");
+    println!("When d is small enough k / d is one of them. For each convergent phi would be (e * d - 1) / k,");
+    println!("and p and q would be the whole number roots of x^2 - (n - phi + 1) x + n.");
+    println!("Press enter to try them.");
+    _ = get_user_string();
+    let mut found = None;
+    for (index, convergent) in wiener::convergents(e, n).into_iter().enumerate(){
+        match wiener::check(e, n, convergent){
+            Some((p, q)) => {
+                let phi = (e as u128 * convergent.d as u128 - 1) / convergent.k as u128;
+                println!("{}: k / d = {} / {}, phi = {}, the roots are {} and {}, and {} * {} = n.", index + 1, convergent.k, convergent.d, phi, p, q, p, q);
+                found = Some((convergent.d, p, q));
+                break;
+            },
+            None => println!("{}: k / d = {} / {}, no whole number roots.", index + 1, convergent.k, convergent.d),
+        }
+    }
+    let started = Instant::now();
+    _ = wiener::attack(e, n);
+    let elapsed = started.elapsed();
+    println!("");
+    let (d, p, q) = match found{
+        Some(s) => s,
+        None => {
+            println!("No convergent gave the key, {} is too large for Wiener's attack.", d);
+            return;
+        },
+    };
+    println!("The attack found d = {} in {:?}.", d, elapsed);
+    match keys::RSAPrivateKey::from_parts(p, q, e, d).ok().and_then(|key| decrypt_message(&key, &cypher)){
+        Some(s) => println!("With it the attacker decrypts the message: \"{}\".", String::from_utf8_lossy(&s)),
+        None => println!("The message didn't decrypt with the recovered d."),
+    }
+    println!("FIPS 186 asks for d above 2^(nlen / 2) so that this attack, and its extensions up to d < n^0.292, can't work.");
+}
+
+// Encrypts one message for three keys with e = 3 and no random padding,
+// then reads it from the three cyphertexts with Håstad's broadcast attack.
+fn broadcast_chapter(){
+    println!("A small e makes encryption fast, and 3 is the smallest e that works. Lets make three keys with e = 3.");
+    let policy = match keys::KeyPolicy::new(3, keys::ExponentModulus::Phi){
+        Ok(s) => s,
+        Err(e) => {
+            println!("Unable to use e = 3. Error: {}", e);
+            return;
+        },
+    };
+    let names = ["Alice", "Bob", "Carol"];
+    let mut moduli:Vec<u64> = Vec::new();
+    for name in names{
+        match keys::RSAPrivateKey::generate_with(&policy){
+            Ok(s) => {
+                println!("{}'s key has n = {} and e = {}.", name, s.get_n(), s.get_e());
+                moduli.push(s.get_n());
+            },
+            Err(e) => {
+                println!("Unable to make a key. Error: {}", e);
+                return;
+            },
+        }
+    }
+    println!("Type a message to send to all three, or press enter for \"Attack at dawn\".");
+    let mut input = get_user_string();
+    if input.is_empty(){
+        input = String::from("Attack at dawn");
+    }
+    let blocks = match get_integers(&pad_the_bytes(convert_raw_bytes(&input))){
+        Ok(s) => s,
+        Err(e) => {
+            println!("Unable to split the message into blocks. Error: {}", e);
+            return;
+        },
+    };
+    println!("pad_the_bytes adds a length byte to every 7 bytes of the message, which makes the blocks");
+    println!("{:?}", blocks);
+    println!("Nothing in them is random or depends on the key, so all three are sent the same numbers, each cubed mod their n.");
+    let cyphers:Vec<Vec<u64>> = moduli.iter().map(|n| encryption_process(blocks.clone(), 3, *n)).collect();
+    for (name, cypher) in names.iter().zip(&cyphers){
+        println!("{} is sent {:?}", name, cypher);
+    }
+    println!("Press enter to continue.");
+    _ = get_user_string();
+    println!("");
+
+    println!("For each block the attacker knows m^3 mod each n. The Chinese remainder theorem joins the three into");
+    println!("m^3 mod n1 * n2 * n3, a number of about 192 bits. m is below every n, so m^3 is below their product,");
+    println!("which means this is m^3 itself, not just its remainder, and an ordinary cube root gives m.");
+    let started = Instant::now();
+    let mut recovered:Vec<u64> = Vec::new();
+    for index in 0..blocks.len(){
+        let column:Vec<u64> = cyphers.iter().map(|c| c[index]).collect();
+        let cube = match hastad::crt(&column, &moduli){
+            Some(s) => s,
+            None => {
+                println!("Two of the moduli share a factor, which is a different weakness, see the batch GCD chapter.");
+                return;
+            },
+        };
+        match hastad::root(&cube, 3){
+            Some(m) => {
+                println!("Block {}: m^3 = {}, the cube root is {}.", index + 1, cube, m);
+                recovered.push(m);
+            },
+            None => {
+                println!("Block {}: {} isn't a cube, something went wrong.", index + 1, cube);
+                return;
+            },
+        }
+    }
+    let elapsed = started.elapsed();
+    match depad_the_bytes(getbytes(recovered)){
+        Some(s) => println!("Removing the length bytes gives \"{}\", without any private key, in {:?}.", String::from_utf8_lossy(&s), elapsed),
+        None => println!("The recovered blocks don't have the length bytes pad_the_bytes adds."),
+    }
+    println!("Real padding such as OAEP puts fresh random bytes in every encryption, so each key is sent a different");
+    println!("number and there is nothing for the CRT to join. A larger e like 65537 also needs 65537 keys for this attack.");
+}
+
+// Gives a second person the tutorial key's n with their own e, picked with
+// get_e, sends the same message to both and reads it from the two
+// cyphertexts with the common modulus attack.
+fn common_modulus_chapter(info:&structures::RSAInfo){
+    let (n, e1) = (info.get_n(), info.get_e());
+    println!("Making primes is slow, so suppose whoever made your key hands the same n to a colleague,");
+    println!("with their own e and d. Your key is n = {} and e = {}. Lets pick the colleague's e,", n, e1);
+    println!("which must have no factor in common with yours, so type something other than {}, such as 17.", e1);
+    let mut shared = structures::RSAInfo::new();
+    shared.set_p(info.get_p());
+    shared.set_q(info.get_q());
+    shared.set_n(n);
+    shared.set_phi(info.get_phi());
+    shared.set_lambda(info.get_lambda());
+    // n is fixed here, so the colleague picks another e rather than new primes.
+    utility::get_e(&mut shared);
+    while !utility::check_e(&shared) || commonmodulus::bezout(e1, shared.get_e()).is_none(){
+        println!("Your colleague needs an e with no factor in common with lambda or with {}, try another.", e1);
+        utility::get_e(&mut shared);
+    }
+    let e2 = shared.get_e();
+    println!("Your colleague's key is n = {} and e = {}.", n, e2);
+    println!("");
+
+    let message = "Meet at the usual place";
+    let blocks = match get_integers(&pad_the_bytes(convert_raw_bytes(message))){
+        Ok(s) => s,
+        Err(e) => {
+            println!("Unable to split the message into blocks. Error: {}", e);
+            return;
+        },
+    };
+    let first = encryption_process(blocks.clone(), e1, n);
+    let second = encryption_process(blocks.clone(), e2, n);
+    println!("Someone sends \"{}\" to both of you. The padded blocks are {:?},", message, blocks);
+    println!("you are sent {:?}", first);
+    println!("and your colleague {:?}.", second);
+    println!("Press enter to continue.");
+    _ = get_user_string();
+    println!("");
+
+    let (a, b) = match commonmodulus::bezout(e1, e2){
+        Some(s) => s,
+        None => return,
+    };
+    println!("e1 and e2 have no common factor, so the extended euclidean algorithm gives a and b with a * e1 + b * e2 = 1:");
+    println!("{} * {} + {} * {} = 1", a, e1, b, e2);
+    println!("For each block c1^a * c2^b = m^(a * e1 + b * e2) = m mod n. The negative one of a and b");
+    println!("is a power of the inverse of that cyphertext mod n, which extended_gcd finds too.");
+    let started = Instant::now();
+    let mut recovered:Vec<u64> = Vec::new();
+    for (index, (c1, c2)) in first.iter().zip(&second).enumerate(){
+        match commonmodulus::attack(*c1, *c2, e1, e2, n){
+            Some(m) => {
+                println!("Block {}: {}^{} * {}^{} mod n = {}", index + 1, c1, a, c2, b, m);
+                recovered.push(m);
+            },
+            None => {
+                println!("Block {}: {} has no inverse mod n, so it shares a prime with n, which breaks the key another way.", index + 1, c1);
+                return;
+            },
+        }
+    }
+    let elapsed = started.elapsed();
+    match depad_the_bytes(getbytes(recovered)){
+        Some(s) => println!("Removing the padding gives \"{}\", without either d, in {:?}.", String::from_utf8_lossy(&s), elapsed),
+        None => println!("The recovered blocks don't have the padding pad_the_bytes adds."),
+    }
+    println!("Worse, either of you can factor n from your own e and d and then work out the other's d.");
+    println!("Every key needs its own n.");
+}
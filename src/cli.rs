@@ -0,0 +1,997 @@
+// Command line interface for using saved keys without going through the
+// tutorial. main.rs hands over here when any arguments are given.
+
+use crate::audit;
+use crate::batchgcd;
+use crate::csr::{self, CertificateRequest};
+use crate::fermat;
+use crate::fingerprint::Fingerprint;
+use crate::fips186::{self, FipsError};
+use crate::jwk::{self, Jwk, JwkKey};
+use crate::keys::{self, ExponentModulus, KeyPolicy, RSAPrivateKey, RSAPublicKey, PKCS1_PRIVATE_LABEL, PKCS8_LABEL};
+use crate::keystore::{KeyMetadata, KeyOperation, Keystore, KeystoreError};
+use crate::pem::{self, PemHeaders};
+use crate::random;
+use crate::ssh;
+use crate::timestamp;
+use crate::x509::{self, Certificate, CertificateBuilder, Name};
+use std::fmt;
+use std::io::{BufRead, Read, Write};
+
+// PEM label of encrypted messages.
+pub const MESSAGE_LABEL: &str = "RSA TUTORIAL MESSAGE";
+
+// PEM label of signatures.
+pub const SIGNATURE_LABEL: &str = "RSA TUTORIAL SIGNATURE";
+
+// Header naming the key a message was encrypted for or signed with, by its fingerprint.
+pub const FINGERPRINT_HEADER: &str = "Key-Fingerprint";
+
+// Passphrase for encrypted keys, asked for on the terminal when it isn't set.
+pub const PASSPHRASE_ENV: &str = "RSA_TUTORIAL_PASSPHRASE";
+
+// Printed after each certificate or request is written, see x509::sign_der.
+const CERTIFICATE_NOTE: &str = "Note: the signature uses the tutorial's own 64 bit algorithm, which other tools don't know.";
+
+const USAGE: &str = "Usage:
+    rsaencryptiontutorial                                   run the tutorial
+    rsaencryptiontutorial keys list
+    rsaencryptiontutorial keys show NAME
+    rsaencryptiontutorial keys generate NAME [--days N] [--comment TEXT] [--encrypt] [--exponent E] [--lambda | --fips]
+    rsaencryptiontutorial keys revoke NAME [--reason TEXT]
+    rsaencryptiontutorial keys delete NAME
+    rsaencryptiontutorial keys export NAME [--format FORMAT] [--private]
+    rsaencryptiontutorial rotate NAME [--new NAME] [--days N] [--exponent E] [--lambda | --fips]
+    rsaencryptiontutorial encrypt [--key NAME] [MESSAGE]    reads stdin without MESSAGE
+    rsaencryptiontutorial decrypt [--key NAME] [FILE]       reads stdin without FILE
+    rsaencryptiontutorial sign [--key NAME] [FILE]          reads stdin without FILE
+    rsaencryptiontutorial verify [--key NAME] SIGNATURE [FILE]
+    rsaencryptiontutorial certificate create NAME [--subject DN] [--days N] [--dns HOST]... [--ca]
+    rsaencryptiontutorial certificate request NAME [--subject DN] [--dns HOST]...
+    rsaencryptiontutorial certificate issue --key NAME [--subject DN] [--days N] [REQUEST]
+    rsaencryptiontutorial certificate show [FILE]           certificate or request, reads stdin without FILE
+    rsaencryptiontutorial audit [--key NAME] [FILE]          public or private key PEM file, or a keystore key
+    rsaencryptiontutorial attack batch-gcd [FILE]           moduli one per line, reads stdin without FILE
+    rsaencryptiontutorial attack fermat [--budget N] [FILE]  key PEM file or a modulus, reads stdin without FILE
+
+decrypt and verify find the key by the fingerprint in the message or signature.
+Certificates and requests are signed with the tutorial's 64 bit signatures under an
+algorithm OID of their own (1.3.6.1.4.1.32473.1.1), only this program can check them.
+
+Options:
+    --keystore DIR       keystore directory, default $RSA_TUTORIAL_KEYSTORE or ~/.rsaencryptiontutorial/keys
+    --key NAME           key to use, can be left out when the keystore holds one key
+    --format FORMAT      public keys: spki (default), pkcs1, openssh or jwk
+                         private keys: pkcs8 (default), pkcs1 or jwk
+    --days N             new key expires after N days, rotate keeps the old key's lifetime
+    --encrypt            protect the new key with a passphrase
+    --exponent E         public exponent of the new key, an odd number of at least 3, default 65537
+    --lambda             work out the new key's d modulo lambda(n) instead of phi(n)
+    --fips               make the new key as FIPS 186-5 asks, e above 2^16 and d modulo lambda(n),
+                         and print the constraints checked
+    --new NAME           name of the replacement key, default NAME-YYYY-MM-DD
+    --ignore-validity    use a key even if it is expired, revoked or rotated
+    --subject DN         certificate subject like \"CN=localhost, O=Example\", default CN=NAME,
+                         for issue the name of the issuing key's certificate
+    --dns HOST           host name for the certificate, can be given more than once
+    --ca                 make a certificate authority certificate
+    --budget N           steps an attack may take before giving up, default 1048576";
+
+// Errors that stop a command, usage errors exit with 2 and the rest with 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliError{
+    Usage(String),
+    Keystore(KeystoreError),
+    Failed(String),
+}
+
+impl fmt::Display for CliError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            CliError::Usage(s) => write!(f, "{}\n\n{}", s, USAGE),
+            CliError::Keystore(e) => write!(f, "{}", e),
+            CliError::Failed(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl std::error::Error for CliError{}
+
+impl From<KeystoreError> for CliError{
+    fn from(e:KeystoreError) -> CliError{
+        CliError::Keystore(e)
+    }
+}
+
+// Options and the words left over once they are taken out.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Options{
+    keystore:Option<String>,
+    key:Option<String>,
+    format:Option<String>,
+    private:bool,
+    days:Option<u64>,
+    comment:Option<String>,
+    encrypt:bool,
+    exponent:Option<u64>,
+    lambda:bool,
+    fips:bool,
+    reason:Option<String>,
+    new_name:Option<String>,
+    ignore_validity:bool,
+    subject:Option<String>,
+    dns:Vec<String>,
+    ca:bool,
+    budget:Option<u64>,
+    help:bool,
+    words:Vec<String>,
+}
+
+// Runs the command in args (without the program name) and returns the exit code.
+pub fn run(args:&[String]) -> i32{
+    match run_command(args){
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            match e{
+                CliError::Usage(_) => 2,
+                _ => 1,
+            }
+        },
+    }
+}
+
+fn run_command(args:&[String]) -> Result<(), CliError>{
+    let options = parse_options(args)?;
+    if options.help{
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let store = match &options.keystore{
+        Some(dir) => Keystore::new(dir),
+        None => Keystore::open_default()?,
+    };
+    let words:Vec<&str> = options.words.iter().map(|w| w.as_str()).collect();
+    match words.as_slice(){
+        ["keys", "list"] => keys_list(&store),
+        ["keys", "show", name] => keys_show(&store, name),
+        ["keys", "delete", name] => keys_delete(&store, name),
+        ["keys", "generate", name] => keys_generate(&store, name, &options),
+        ["keys", "revoke", name] => keys_revoke(&store, name, &options),
+        ["keys", "export", name] => keys_export(&store, name, &options),
+        ["rotate", name] => rotate(&store, name, &options),
+        ["encrypt", message @ ..] => encrypt(&store, &options, message),
+        ["decrypt"] => decrypt(&store, &options, None),
+        ["decrypt", file] => decrypt(&store, &options, Some(file)),
+        ["sign"] => sign(&store, &options, None),
+        ["sign", file] => sign(&store, &options, Some(file)),
+        ["verify", signature] => verify(&store, &options, signature, None),
+        ["verify", signature, file] => verify(&store, &options, signature, Some(file)),
+        ["certificate", "create", name] => certificate_create(&store, name, &options),
+        ["certificate", "request", name] => certificate_request(&store, name, &options),
+        ["certificate", "issue"] => certificate_issue(&store, &options, None),
+        ["certificate", "issue", file] => certificate_issue(&store, &options, Some(file)),
+        ["certificate", "show"] => certificate_show(None),
+        ["certificate", "show", file] => certificate_show(Some(file)),
+        ["audit"] => audit(&store, &options, None),
+        ["audit", file] => audit(&store, &options, Some(file)),
+        ["attack", "batch-gcd"] => attack_batch_gcd(None),
+        ["attack", "batch-gcd", file] => attack_batch_gcd(Some(file)),
+        ["attack", "fermat"] => attack_fermat(&options, None),
+        ["attack", "fermat", file] => attack_fermat(&options, Some(file)),
+        [] => Err(CliError::Usage(String::from("no command given"))),
+        _ => Err(CliError::Usage(format!("unknown command \"{}\"", options.words.join(" ")))),
+    }
+}
+
+// Takes the options out of the arguments, anything after -- is kept as words.
+fn parse_options(args:&[String]) -> Result<Options, CliError>{
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next(){
+        let mut value = |name:&str| match args.next(){
+            Some(s) => Ok(Some(s.clone())),
+            None => Err(CliError::Usage(format!("{} needs a value", name))),
+        };
+        match arg.as_str(){
+            "--keystore" => options.keystore = value("--keystore")?,
+            "--key" => options.key = value("--key")?,
+            "--format" => options.format = value("--format")?,
+            "--private" => options.private = true,
+            "--days" => {
+                let days = value("--days")?.unwrap_or_default();
+                match days.parse::<u64>(){
+                    Ok(d) if d > 0 && d <= 36500 => options.days = Some(d),
+                    _ => return Err(CliError::Usage(format!("--days must be a number of days from 1 to 36500, not {}", days))),
+                }
+            },
+            "--comment" => options.comment = value("--comment")?,
+            "--encrypt" => options.encrypt = true,
+            "--exponent" => {
+                let exponent = value("--exponent")?.unwrap_or_default();
+                match exponent.parse::<u64>(){
+                    Ok(e) if keys::check_public_exponent(e).is_ok() => options.exponent = Some(e),
+                    _ => return Err(CliError::Usage(format!("--exponent must be an odd number of at least 3, not {}", exponent))),
+                }
+            },
+            "--lambda" => options.lambda = true,
+            "--fips" => options.fips = true,
+            "--reason" => options.reason = value("--reason")?,
+            "--new" => options.new_name = value("--new")?,
+            "--ignore-validity" => options.ignore_validity = true,
+            "--subject" => options.subject = value("--subject")?,
+            "--dns" => options.dns.extend(value("--dns")?),
+            "--ca" => options.ca = true,
+            "--budget" => {
+                let budget = value("--budget")?.unwrap_or_default();
+                match budget.parse::<u64>(){
+                    Ok(b) if b > 0 => options.budget = Some(b),
+                    _ => return Err(CliError::Usage(format!("--budget must be a number of steps above 0, not {}", budget))),
+                }
+            },
+            "--help" | "-h" => options.help = true,
+            "--" => {
+                options.words.extend(args.cloned());
+                break;
+            },
+            s if s.starts_with("--") => return Err(CliError::Usage(format!("unknown option {}", s))),
+            _ => options.words.push(arg.clone()),
+        }
+    }
+    Ok(options)
+}
+
+fn keys_list(store:&Keystore) -> Result<(), CliError>{
+    let keys = store.list()?;
+    if keys.is_empty(){
+        println!("No keys in {}.", store.dir().display());
+        return Ok(());
+    }
+    let now = timestamp::now();
+    for metadata in keys{
+        let encrypted = if metadata.encrypted {"  (passphrase)"} else {""};
+        println!("{:<20} {:>3} bits  {:<13}  {}  {}  {}{}", metadata.name, metadata.bits, metadata.status(now),
+            timestamp::format_utc(metadata.created), metadata.fingerprint, metadata.comment, encrypted);
+    }
+    Ok(())
+}
+
+fn keys_show(store:&Keystore, name:&str) -> Result<(), CliError>{
+    let metadata = store.metadata(name)?;
+    let public = store.public_key(name)?;
+    let fingerprint = Fingerprint::of(&public);
+    println!("Name:        {}", metadata.name);
+    println!("Fingerprint: {}", fingerprint);
+    println!("Hex:         {}", fingerprint.to_hex());
+    println!("Short ID:    {}", fingerprint.short_id());
+    println!("Size:        {} bits", metadata.bits);
+    println!("Created:     {}", timestamp::format_utc(metadata.created));
+    println!("Valid from:  {}", timestamp::format_utc(metadata.not_before));
+    match metadata.not_after{
+        Some(t) => println!("Valid until: {}", timestamp::format_utc(t)),
+        None => println!("Valid until: no expiry"),
+    }
+    println!("Status:      {}", metadata.status(timestamp::now()));
+    if let Some(r) = &metadata.replaced_by{
+        println!("Replaced by: {}", r);
+    }
+    println!("Comment:     {}", metadata.comment);
+    println!("Passphrase:  {}", if metadata.encrypted {"yes"} else {"no"});
+    println!("Modulus:     {}", public.get_n());
+    println!("Exponent:    {}", public.get_e());
+    println!("{}", ssh::to_openssh_line(&public, &metadata.comment));
+    print!("{}", fingerprint.randomart("RSA", metadata.bits));
+    Ok(())
+}
+
+fn keys_delete(store:&Keystore, name:&str) -> Result<(), CliError>{
+    store.delete(name)?;
+    println!("Deleted key {}.", name);
+    Ok(())
+}
+
+// Makes a new key and saves it, with an expiry when --days is given.
+fn keys_generate(store:&Keystore, name:&str, options:&Options) -> Result<(), CliError>{
+    if store.contains(name){
+        return Err(CliError::Keystore(KeystoreError::AlreadyExists(name.to_string())));
+    }
+    let passphrase = if options.encrypt {Some(new_passphrase(name)?)} else {None};
+    let key = generate_key(options)?;
+    let mut metadata = store.save(name, &key, options.comment.as_deref().unwrap_or(""), passphrase.as_deref())?;
+    if let Some(days) = options.days{
+        metadata.not_after = Some(metadata.not_before + days * 86400);
+        store.update_metadata(&metadata)?;
+    }
+    print_saved(&metadata);
+    Ok(())
+}
+
+fn keys_revoke(store:&Keystore, name:&str, options:&Options) -> Result<(), CliError>{
+    let metadata = store.revoke(name, options.reason.as_deref().unwrap_or(""))?;
+    println!("Revoked key {} ({}), it can no longer be used.", metadata.name, metadata.fingerprint);
+    Ok(())
+}
+
+// Replaces the key with a new one. The old key is kept, decrypt only, so
+// messages already sent to it can still be read.
+fn rotate(store:&Keystore, name:&str, options:&Options) -> Result<(), CliError>{
+    let old = store.metadata(name)?;
+    if old.revoked{
+        return Err(CliError::Keystore(KeystoreError::Revoked(name.to_string())));
+    }
+    let new_name = match &options.new_name{
+        Some(s) => s.clone(),
+        None => {
+            let (year, month, day, _, _, _) = timestamp::to_civil(timestamp::now());
+            format!("{}-{:04}-{:02}-{:02}", name, year, month, day)
+        },
+    };
+    if store.contains(&new_name){
+        return Err(CliError::Usage(format!("a key named {} already exists, choose the new name with --new", new_name)));
+    }
+    let passphrase = if old.encrypted {Some(new_passphrase(&new_name)?)} else {None};
+    let key = generate_key(options)?;
+    let mut metadata = store.rotate(name, &new_name, &key, passphrase.as_deref())?;
+    if let Some(days) = options.days{
+        metadata.not_after = Some(metadata.not_before + days * 86400);
+        store.update_metadata(&metadata)?;
+    }
+    print_saved(&metadata);
+    println!("Key {} can now only decrypt and verify.", name);
+    Ok(())
+}
+
+// Makes a key with the exponent and modulus options, or with --fips as
+// FIPS 186-5 asks, printing the report.
+fn generate_key(options:&Options) -> Result<RSAPrivateKey, CliError>{
+    if options.fips{
+        return match fips186::generate(options.exponent.unwrap_or(KeyPolicy::default().get_e())){
+            Ok((key, report)) => {
+                print!("{}", report);
+                Ok(key)
+            },
+            Err(FipsError::InvalidExponent(e)) => Err(CliError::Usage(FipsError::InvalidExponent(e).to_string())),
+            Err(e) => Err(CliError::Failed(e.to_string())),
+        };
+    }
+    let modulus = if options.lambda {ExponentModulus::Lambda} else {ExponentModulus::Phi};
+    let policy = match KeyPolicy::new(options.exponent.unwrap_or(KeyPolicy::default().get_e()), modulus){
+        Ok(s) => s,
+        Err(e) => return Err(CliError::Usage(e.to_string())),
+    };
+    match RSAPrivateKey::generate_with(&policy){
+        Ok(s) => Ok(s),
+        Err(e) => Err(CliError::Failed(format!("unable to make a new key: {}", e))),
+    }
+}
+
+fn print_saved(metadata:&KeyMetadata){
+    println!("Saved key {} ({}).", metadata.name, metadata.fingerprint);
+    if let Some(t) = metadata.not_after{
+        println!("It expires at {}.", timestamp::format_utc(t));
+    }
+}
+
+// Writes the public key, or the private key with --private, to stdout.
+fn keys_export(store:&Keystore, name:&str, options:&Options) -> Result<(), CliError>{
+    let output = if options.private{
+        let key = load_private_key(store, name)?;
+        match options.format.as_deref().unwrap_or("pkcs8"){
+            "pkcs8" => key.to_pkcs8_pem(),
+            "pkcs1" => key.to_pkcs1_pem(),
+            "jwk" => format!("{}\n", Jwk{ key: JwkKey::Private(key), kid: Some(jwk::thumbprint(&key.public_key())) }.to_json()),
+            other => return Err(CliError::Usage(format!("unknown private key format {}", other))),
+        }
+    }else{
+        let key = store.public_key(name)?;
+        match options.format.as_deref().unwrap_or("spki"){
+            "spki" => key.to_spki_pem(),
+            "pkcs1" => key.to_pkcs1_pem(),
+            "openssh" => format!("{}\n", ssh::to_openssh_line(&key, &store.metadata(name)?.comment)),
+            "jwk" => format!("{}\n", Jwk{ key: JwkKey::Public(key), kid: Some(jwk::thumbprint(&key)) }.to_json()),
+            other => return Err(CliError::Usage(format!("unknown public key format {}", other))),
+        }
+    };
+    print!("{}", output);
+    Ok(())
+}
+
+// Encrypts the message words, or stdin when there are none, with the public key.
+fn encrypt(store:&Keystore, options:&Options, message:&[&str]) -> Result<(), CliError>{
+    let name = choose_key(store, options)?;
+    check_key(store, &name, KeyOperation::Encrypt, options)?;
+    let plaintext = if message.is_empty(){
+        read_stdin()?
+    }else{
+        message.join(" ").into_bytes()
+    };
+    let cyphertext = store.encrypt(&name, &plaintext, options.ignore_validity)?;
+    let fingerprint = Fingerprint::of(&store.public_key(&name)?).to_string();
+    print!("{}", pem::encode_with_headers(MESSAGE_LABEL, &[(FINGERPRINT_HEADER, &fingerprint)], &cyphertext));
+    Ok(())
+}
+
+// Decrypts an armored message from the file, or stdin, and writes the plain
+// bytes. Without --key the key is the one whose fingerprint is in the header.
+fn decrypt(store:&Keystore, options:&Options, file:Option<&str>) -> Result<(), CliError>{
+    let (headers, ciphertext) = read_armor(&read_input(file)?, MESSAGE_LABEL, "an encrypted message")?;
+    let name = choose_message_key(store, options, header_fingerprint(&headers))?;
+    check_key(store, &name, KeyOperation::Decrypt, options)?;
+    let passphrase = read_key_passphrase(store, &name)?;
+    let plaintext = store.decrypt(&name, &ciphertext, passphrase.as_deref(), options.ignore_validity)?;
+    if let Err(e) = std::io::stdout().write_all(&plaintext){
+        return Err(CliError::Failed(e.to_string()));
+    }
+    Ok(())
+}
+
+// Signs the file, or stdin, and writes an armored signature.
+fn sign(store:&Keystore, options:&Options, file:Option<&str>) -> Result<(), CliError>{
+    let name = choose_key(store, options)?;
+    check_key(store, &name, KeyOperation::Sign, options)?;
+    let message = read_input(file)?;
+    let passphrase = read_key_passphrase(store, &name)?;
+    let value = store.sign(&name, &message, passphrase.as_deref(), options.ignore_validity)?;
+    let fingerprint = Fingerprint::of(&store.public_key(&name)?).to_string();
+    print!("{}", pem::encode_with_headers(SIGNATURE_LABEL, &[(FINGERPRINT_HEADER, &fingerprint)], &value.to_be_bytes()));
+    Ok(())
+}
+
+// Checks an armored signature against the file, or stdin.
+fn verify(store:&Keystore, options:&Options, signature_file:&str, file:Option<&str>) -> Result<(), CliError>{
+    let (headers, value) = read_armor(&read_input(Some(signature_file))?, SIGNATURE_LABEL, "a signature")?;
+    let value:[u8; 8] = match value.try_into(){
+        Ok(s) => s,
+        Err(_) => return Err(CliError::Failed(String::from("the signature must be 8 bytes"))),
+    };
+    let name = choose_message_key(store, options, header_fingerprint(&headers))?;
+    check_key(store, &name, KeyOperation::Verify, options)?;
+    let message = read_input(file)?;
+    if store.verify(&name, &message, u64::from_be_bytes(value), options.ignore_validity)?{
+        println!("Good signature from key {} ({}).", name, Fingerprint::of(&store.public_key(&name)?));
+        Ok(())
+    }else{
+        Err(CliError::Failed(format!("bad signature, the message was changed or not signed by key {}", name)))
+    }
+}
+
+// Writes a self-signed certificate for the key.
+fn certificate_create(store:&Keystore, name:&str, options:&Options) -> Result<(), CliError>{
+    check_key(store, name, KeyOperation::Sign, options)?;
+    let subject = subject_name(options, name)?;
+    let (not_before, not_after) = certificate_lifetime(store, name, options)?;
+    let mut builder = CertificateBuilder::new(subject, not_before, not_after);
+    builder.ca = options.ca;
+    builder.dns_names = options.dns.clone();
+    builder.serial = random_serial()?;
+    let key = load_private_key(store, name)?;
+    print!("{}", builder.self_sign(&key).to_pem());
+    eprintln!("{}", CERTIFICATE_NOTE);
+    Ok(())
+}
+
+// Writes a PKCS#10 request for a certificate for the key.
+fn certificate_request(store:&Keystore, name:&str, options:&Options) -> Result<(), CliError>{
+    check_key(store, name, KeyOperation::Sign, options)?;
+    let subject = subject_name(options, name)?;
+    let key = load_private_key(store, name)?;
+    print!("{}", CertificateRequest::create(&subject, &options.dns, &key).to_pem());
+    eprintln!("{}", CERTIFICATE_NOTE);
+    Ok(())
+}
+
+// Issues a certificate for a request, signed by the --key key as a local
+// certificate authority. The issuer name must match the subject of the
+// authority's own certificate, which is CN=NAME unless it was made with --subject.
+fn certificate_issue(store:&Keystore, options:&Options, file:Option<&str>) -> Result<(), CliError>{
+    let name = match &options.key{
+        Some(s) => s.clone(),
+        None => return Err(CliError::Usage(String::from("choose the issuing key with --key"))),
+    };
+    check_key(store, &name, KeyOperation::Sign, options)?;
+    let issuer = subject_name(options, &name)?;
+    let data = read_input(file)?;
+    let request = match CertificateRequest::from_pem(&String::from_utf8_lossy(&data)){
+        Ok(s) => s,
+        Err(e) => return Err(CliError::Failed(format!("not a certificate request: {}", e))),
+    };
+    let public = match request.public_key(){
+        Ok(s) => s,
+        Err(e) => return Err(CliError::Failed(format!("unable to use the key in the request: {}", e))),
+    };
+    let (not_before, not_after) = certificate_lifetime(store, &name, options)?;
+    let mut builder = match request.to_builder(&issuer, not_before, not_after){
+        Ok(s) => s,
+        Err(e) => return Err(CliError::Failed(format!("refusing the request: {}", e))),
+    };
+    builder.serial = random_serial()?;
+    let key = load_private_key(store, &name)?;
+    print!("{}", builder.sign(&public, &key).to_pem());
+    eprintln!("{}", CERTIFICATE_NOTE);
+    Ok(())
+}
+
+// Shows what is in a certificate or certificate request and checks its
+// signature where the key to check it with is known.
+fn certificate_show(file:Option<&str>) -> Result<(), CliError>{
+    let data = read_input(file)?;
+    let text = String::from_utf8_lossy(&data);
+    let label = match pem::decode(&text){
+        Ok((label, _)) => label,
+        Err(e) => return Err(CliError::Failed(format!("not a certificate: {}", e))),
+    };
+    if label == csr::CSR_LABEL{
+        return request_show(&text);
+    }
+    let certificate = match Certificate::from_pem(&text){
+        Ok(s) => s,
+        Err(e) => return Err(CliError::Failed(format!("not a certificate: {}", e))),
+    };
+    let serial:Vec<String> = certificate.get_serial().iter().map(|b| format!("{:02x}", b)).collect();
+    println!("Version:     {}", certificate.get_version());
+    println!("Serial:      {}", serial.join(":"));
+    println!("Subject:     {}", certificate.get_subject());
+    println!("Issuer:      {}", certificate.get_issuer());
+    println!("Valid from:  {}", timestamp::format_utc(certificate.get_not_before()));
+    println!("Valid until: {}", timestamp::format_utc(certificate.get_not_after()));
+    println!("CA:          {}", if certificate.is_ca() {"yes"} else {"no"});
+    for name in certificate.dns_names(){
+        println!("DNS name:    {}", name);
+    }
+    match certificate.public_key(){
+        Ok(key) => {
+            print_public_key(&key);
+            if certificate.is_self_issued(){
+                println!("Signature:   {}", signature_status(certificate.verify(&key), "self-signature"));
+            }
+        },
+        Err(e) => println!("Public key:  {}", e),
+    }
+    Ok(())
+}
+
+fn request_show(text:&str) -> Result<(), CliError>{
+    let request = match CertificateRequest::from_pem(text){
+        Ok(s) => s,
+        Err(e) => return Err(CliError::Failed(format!("not a certificate request: {}", e))),
+    };
+    println!("Request for: {}", request.get_subject());
+    for name in request.dns_names(){
+        println!("DNS name:    {}", name);
+    }
+    match request.public_key(){
+        Ok(key) => print_public_key(&key),
+        Err(e) => println!("Public key:  {}", e),
+    }
+    println!("Signature:   {}", signature_status(request.verify(), "signature"));
+    Ok(())
+}
+
+fn print_public_key(key:&RSAPublicKey){
+    println!("Modulus:     {}", key.get_n());
+    println!("Exponent:    {}", key.get_e());
+    println!("Fingerprint: {}", Fingerprint::of(key));
+}
+
+fn signature_status(result:Result<bool, x509::X509Error>, what:&str) -> String{
+    match result{
+        Ok(true) => format!("good {}", what),
+        Ok(false) => format!("BAD {}", what),
+        Err(e) => e.to_string(),
+    }
+}
+
+// The --subject name, or CN=NAME.
+fn subject_name(options:&Options, name:&str) -> Result<Name, CliError>{
+    match Name::parse(options.subject.as_deref().unwrap_or(&format!("CN={}", name))){
+        Ok(s) => Ok(s),
+        Err(e) => Err(CliError::Usage(e.to_string())),
+    }
+}
+
+// Certificates last for --days, or until the signing key expires, or a year.
+fn certificate_lifetime(store:&Keystore, name:&str, options:&Options) -> Result<(u64, u64), CliError>{
+    let now = timestamp::now();
+    let not_after = match (options.days, store.metadata(name)?.not_after){
+        (Some(days), _) => now + days * 86400,
+        (None, Some(t)) => t,
+        (None, None) => now + 365 * 86400,
+    };
+    Ok((now, not_after))
+}
+
+// A random positive serial, so certificates made in the same second differ.
+fn random_serial() -> Result<u64, CliError>{
+    let mut bytes = [0u8; 8];
+    match random::fill_random(&mut bytes){
+        Ok(_) => Ok(u64::from_be_bytes(bytes) >> 1),
+        Err(e) => Err(CliError::Failed(format!("unable to get random numbers for the serial number: {}", e))),
+    }
+}
+
+// Refuses keys that are revoked, expired or rotated unless --ignore-validity is given.
+fn check_key(store:&Keystore, name:&str, operation:KeyOperation, options:&Options) -> Result<(), CliError>{
+    match store.check(name, operation){
+        Ok(_) => Ok(()),
+        Err(e @ (KeystoreError::Revoked(_) | KeystoreError::Expired(..) | KeystoreError::NotYetValid(..) | KeystoreError::DecryptOnly(..))) => {
+            if options.ignore_validity{
+                eprintln!("Warning: {}, using it anyway.", e);
+                Ok(())
+            }else{
+                Err(CliError::Failed(format!("{}, use --ignore-validity to use it anyway", e)))
+            }
+        },
+        Err(e) => Err(CliError::Keystore(e)),
+    }
+}
+
+// Key for decrypting a message. The fingerprint from its header picks the key,
+// or checks the one given with --key, so the wrong key is caught before the
+// passphrase is asked for.
+fn choose_message_key(store:&Keystore, options:&Options, fingerprint:Option<&str>) -> Result<String, CliError>{
+    let fingerprint = match fingerprint{
+        Some(s) => s,
+        None => return choose_key(store, options),
+    };
+    if let Some(name) = &options.key{
+        let metadata = store.metadata(name)?;
+        if metadata.fingerprint != fingerprint{
+            return Err(CliError::Failed(format!("the message was encrypted for key {}, not {} ({})", fingerprint, name, metadata.fingerprint)));
+        }
+        return Ok(name.clone());
+    }
+    let matching:Vec<String> = store.list()?.into_iter().filter(|m| m.fingerprint == fingerprint).map(|m| m.name).collect();
+    match matching.as_slice(){
+        [only] => Ok(only.clone()),
+        [] => Err(CliError::Failed(format!("no key in {} has the fingerprint {}", store.dir().display(), fingerprint))),
+        _ => Err(CliError::Usage(format!("keys {} all have the fingerprint {}, choose one with --key", matching.join(", "), fingerprint))),
+    }
+}
+
+fn header_fingerprint(headers:&PemHeaders) -> Option<&str>{
+    headers.iter().find(|(n, _)| n == FINGERPRINT_HEADER).map(|(_, v)| v.as_str())
+}
+
+// Headers and bytes of a PEM document that must have the label, what names it in errors.
+fn read_armor(data:&[u8], label:&str, what:&str) -> Result<(PemHeaders, Vec<u8>), CliError>{
+    let text = match std::str::from_utf8(data){
+        Ok(s) => s,
+        Err(_) => return Err(CliError::Failed(format!("not {}, it is not text", what))),
+    };
+    match pem::decode_with_headers(text){
+        Ok((found, headers, data)) if found == label => Ok((headers, data)),
+        Ok((found, _, _)) => Err(CliError::Failed(format!("not {}, found {}", what, found))),
+        Err(e) => Err(CliError::Failed(format!("not {}: {}", what, e))),
+    }
+}
+
+// Looks for weaknesses in a key. Keystore keys and private key files are
+// checked with their primes and d, public key files only with what can be
+// worked out from n and e.
+fn audit(store:&Keystore, options:&Options, file:Option<&str>) -> Result<(), CliError>{
+    let report = match file{
+        Some(path) => {
+            let text = String::from_utf8_lossy(&read_input(Some(path))?).to_string();
+            let label = match pem::decode(&text){
+                Ok((label, _)) => label,
+                Err(e) => return Err(CliError::Failed(format!("not a key: {}", e))),
+            };
+            let result = if label == PKCS1_PRIVATE_LABEL || label == PKCS8_LABEL{
+                RSAPrivateKey::from_pem(&text).map(|k| audit::audit_private_key(&k, audit::DEFAULT_BUDGET))
+            }else{
+                RSAPublicKey::from_pem(&text).map(|k| audit::audit_public_key(&k, audit::DEFAULT_BUDGET))
+            };
+            match result{
+                Ok(s) => s,
+                Err(e) => return Err(CliError::Failed(format!("not a usable key: {}", e))),
+            }
+        },
+        None => {
+            let name = choose_key(store, options)?;
+            audit::audit_private_key(&load_private_key(store, &name)?, audit::DEFAULT_BUDGET)
+        },
+    };
+    print!("{}", report);
+    Ok(())
+}
+
+// Reports the moduli in a list that share a prime, and the primes. The moduli
+// are decimal or 0x hex, one per line, blank lines and lines starting with #
+// are skipped.
+fn attack_batch_gcd(file:Option<&str>) -> Result<(), CliError>{
+    let moduli = parse_moduli(&String::from_utf8_lossy(&read_input(file)?))?;
+    let shared = batchgcd::find_shared_factors(&moduli);
+    println!("Checked {} moduli, {} pairs share a factor.", moduli.len(), shared.len());
+    for pair in shared{
+        println!("{}", pair);
+    }
+    Ok(())
+}
+
+// Factors a modulus with Fermat's method, which is quick when p and q are
+// close. The file holds a key PEM or a modulus in decimal or 0x hex.
+fn attack_fermat(options:&Options, file:Option<&str>) -> Result<(), CliError>{
+    let n = read_modulus(&String::from_utf8_lossy(&read_input(file)?))?;
+    let budget = options.budget.unwrap_or(fermat::DEFAULT_BUDGET);
+    match fermat::factor(n, budget){
+        Some((p, q, steps)) => {
+            println!("{} = {} * {}, found after {} steps.", n, p, q, steps);
+            Ok(())
+        },
+        None => Err(CliError::Failed(format!("Fermat's method found no factor of {} in {} steps", n, budget))),
+    }
+}
+
+fn read_modulus(text:&str) -> Result<u64, CliError>{
+    if let Ok((label, _)) = pem::decode(text){
+        let n = if label == PKCS1_PRIVATE_LABEL || label == PKCS8_LABEL{
+            RSAPrivateKey::from_pem(text).map(|k| k.get_n())
+        }else{
+            RSAPublicKey::from_pem(text).map(|k| k.get_n())
+        };
+        return n.map_err(|e| CliError::Failed(format!("not a usable key: {}", e)));
+    }
+    match parse_moduli(text)?.as_slice(){
+        [n] => Ok(*n),
+        moduli => Err(CliError::Failed(format!("expected one modulus, found {}", moduli.len()))),
+    }
+}
+
+fn parse_moduli(text:&str) -> Result<Vec<u64>, CliError>{
+    let mut moduli:Vec<u64> = Vec::new();
+    for (i, line) in text.lines().enumerate(){
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#'){
+            continue;
+        }
+        let parsed = match line.strip_prefix("0x").or(line.strip_prefix("0X")){
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => line.parse::<u64>(),
+        };
+        match parsed{
+            Ok(n) if n > 1 => moduli.push(n),
+            _ => return Err(CliError::Failed(format!("line {}: \"{}\" is not a modulus", i + 1, line))),
+        }
+    }
+    Ok(moduli)
+}
+
+// The --key option, or the only key in the keystore.
+fn choose_key(store:&Keystore, options:&Options) -> Result<String, CliError>{
+    if let Some(name) = &options.key{
+        return Ok(name.clone());
+    }
+    let keys = store.list()?;
+    match keys.as_slice(){
+        [only] => Ok(only.name.clone()),
+        [] => Err(CliError::Failed(format!("no keys in {}, run the tutorial to create one", store.dir().display()))),
+        _ => Err(CliError::Usage(format!("the keystore holds {} keys, choose one with --key", keys.len()))),
+    }
+}
+
+// Loads a private key, asking for the passphrase when it is encrypted.
+fn load_private_key(store:&Keystore, name:&str) -> Result<RSAPrivateKey, CliError>{
+    let passphrase = read_key_passphrase(store, name)?;
+    Ok(store.private_key(name, passphrase.as_deref())?)
+}
+
+// Passphrase of a stored key from the environment or the terminal, None
+// when the key isn't encrypted.
+fn read_key_passphrase(store:&Keystore, name:&str) -> Result<Option<String>, CliError>{
+    if !store.metadata(name)?.encrypted{
+        return Ok(None);
+    }
+    match std::env::var(PASSPHRASE_ENV){
+        Ok(s) => Ok(Some(s)),
+        Err(_) => Ok(Some(read_passphrase(&format!("Passphrase for key {}: ", name))?)),
+    }
+}
+
+// Passphrase for a new key, from the environment or typed twice on the terminal.
+fn new_passphrase(name:&str) -> Result<String, CliError>{
+    if let Ok(s) = std::env::var(PASSPHRASE_ENV){
+        return Ok(s);
+    }
+    let passphrase = read_passphrase(&format!("New passphrase for key {}: ", name))?;
+    if read_passphrase("Type it again: ")? != passphrase{
+        return Err(CliError::Failed(String::from("the passphrases don't match")));
+    }
+    if passphrase.is_empty(){
+        return Err(CliError::Failed(String::from("the passphrase is empty")));
+    }
+    Ok(passphrase)
+}
+
+// Asks on the terminal rather than stdin, which may be carrying the message.
+// The standard library can't turn off echo, so the passphrase is visible.
+fn read_passphrase(prompt:&str) -> Result<String, CliError>{
+    let failed = |e:std::io::Error| CliError::Failed(format!("unable to ask for the passphrase, set {}: {}", PASSPHRASE_ENV, e));
+    let mut terminal = std::fs::OpenOptions::new().read(true).write(true).open("/dev/tty").map_err(failed)?;
+    write!(terminal, "{}", prompt).map_err(failed)?;
+    let mut line = String::new();
+    std::io::BufReader::new(terminal).read_line(&mut line).map_err(failed)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+// Contents of the file, or stdin when there is no file.
+fn read_input(file:Option<&str>) -> Result<Vec<u8>, CliError>{
+    match file{
+        Some(path) => match std::fs::read(path){
+            Ok(s) => Ok(s),
+            Err(e) => Err(CliError::Failed(format!("{}: {}", path, e))),
+        },
+        None => read_stdin(),
+    }
+}
+
+fn read_stdin() -> Result<Vec<u8>, CliError>{
+    let mut data:Vec<u8> = Vec::new();
+    match std::io::stdin().read_to_end(&mut data){
+        Ok(_) => Ok(data),
+        Err(e) => Err(CliError::Failed(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+fn strings(args:&[&str]) -> Vec<String>{
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+// Runs a command with --keystore pointing at the test keystore.
+#[cfg(test)]
+fn run_in(store:&Keystore, args:&[&str]) -> Result<(), CliError>{
+    run_command(&[strings(&["--keystore", &store.dir().display().to_string()]), strings(args)].concat())
+}
+
+#[test]
+fn test_cli_parse_options(){
+    let options = parse_options(&strings(&["--keystore", "/tmp/k", "encrypt", "--key", "work", "hello", "--", "--private"])).unwrap();
+    assert_eq!(options.keystore.as_deref(), Some("/tmp/k"));
+    assert_eq!(options.key.as_deref(), Some("work"));
+    assert!(!options.private);
+    assert_eq!(options.words, strings(&["encrypt", "hello", "--private"]));
+
+    assert!(matches!(parse_options(&strings(&["keys", "list", "--key"])), Err(CliError::Usage(_))));
+    assert!(matches!(parse_options(&strings(&["keys", "list", "--verbose"])), Err(CliError::Usage(_))));
+
+    assert_eq!(parse_options(&strings(&["keys", "generate", "k", "--exponent", "3"])).unwrap().exponent, Some(3));
+    for exponent in ["1", "65536", "e"]{
+        assert!(matches!(parse_options(&strings(&["keys", "generate", "k", "--exponent", exponent])), Err(CliError::Usage(_))));
+    }
+    assert_eq!(parse_options(&strings(&["attack", "fermat", "--budget", "500"])).unwrap().budget, Some(500));
+    assert!(matches!(parse_options(&strings(&["attack", "fermat", "--budget", "0"])), Err(CliError::Usage(_))));
+}
+
+#[test]
+fn test_cli_commands(){
+    let store = crate::keystore::test_keystore("cli");
+    let key = keys::test_key();
+    let run = |args:&[&str]| run_in(&store, args);
+
+    assert!(matches!(run(&["encrypt", "hi"]), Err(CliError::Failed(_))));
+    store.save("one", &key, "", None).unwrap();
+    store.save("two", &key, "", None).unwrap();
+    assert_eq!(choose_key(&store, &Options::default()), Err(CliError::Usage(String::from("the keystore holds 2 keys, choose one with --key"))));
+    assert_eq!(run(&["keys", "show", "one"]), Ok(()));
+    assert_eq!(run(&["keys", "export", "one", "--format", "jwk"]), Ok(()));
+    assert!(matches!(run(&["keys", "export", "one", "--format", "der"]), Err(CliError::Usage(_))));
+    assert_eq!(run(&["keys", "delete", "two"]), Ok(()));
+    assert_eq!(run(&["keys", "delete", "two"]), Err(CliError::Keystore(KeystoreError::NotFound(String::from("two")))));
+    assert_eq!(choose_key(&store, &Options::default()), Ok(String::from("one")));
+    assert!(matches!(run(&["frobnicate"]), Err(CliError::Usage(_))));
+
+    assert_eq!(run(&["keys", "generate", "small", "--lambda"]), Ok(()));
+    let small = store.private_key("small", None).unwrap();
+    assert!(small.get_d() < small.to_rsainfo().get_lambda());
+    assert_eq!(run(&["keys", "generate", "seventeen", "--exponent", "17"]), Ok(()));
+    assert_eq!(store.public_key("seventeen").unwrap().get_e(), 17);
+    assert_eq!(run(&["keys", "generate", "standard", "--fips"]), Ok(()));
+    let standard = store.private_key("standard", None).unwrap();
+    assert!(standard.get_p() >= fips186::MIN_PRIME && standard.get_q() >= fips186::MIN_PRIME);
+    assert!(matches!(run(&["keys", "generate", "weak", "--fips", "--exponent", "17"]), Err(CliError::Usage(_))));
+    assert!(!store.contains("weak"));
+
+    assert_eq!(run(&["audit", "--key", "one"]), Ok(()));
+    let public = store.dir().join("audit-one.pem");
+    std::fs::write(&public, key.public_key().to_spki_pem()).unwrap();
+    assert_eq!(run(&["audit", &public.display().to_string()]), Ok(()));
+    std::fs::write(&public, "not a key").unwrap();
+    assert!(matches!(run(&["audit", &public.display().to_string()]), Err(CliError::Failed(_))));
+
+    assert_eq!(parse_moduli("# moduli\n18446743979220271189\n\n  0x4d \n"), Ok(vec![18446743979220271189, 77]));
+    assert!(matches!(parse_moduli("15\n1\n"), Err(CliError::Failed(_))));
+    let moduli = store.dir().join("moduli.txt");
+    std::fs::write(&moduli, format!("{}\n{}\n", 50000000021u64 * 368934871, 50000000021u64 * 368934869)).unwrap();
+    assert_eq!(run(&["attack", "batch-gcd", &moduli.display().to_string()]), Ok(()));
+    std::fs::write(&moduli, format!("{}\n", 4293999991u64 * 4295934809)).unwrap();
+    assert_eq!(run(&["attack", "fermat", &moduli.display().to_string()]), Ok(()));
+    assert!(matches!(run(&["attack", "fermat", "--budget", "100", &moduli.display().to_string()]), Err(CliError::Failed(_))));
+    assert_eq!(read_modulus(&key.public_key().to_spki_pem()), Ok(key.get_n()));
+    assert!(matches!(read_modulus("15\n21\n"), Err(CliError::Failed(_))));
+    _ = std::fs::remove_dir_all(store.dir());
+}
+
+#[test]
+fn test_cli_message_key(){
+    let store = crate::keystore::test_keystore("cli-message");
+    let first = keys::test_key();
+    let second = keys::other_test_key();
+    store.save("first", &first, "", None).unwrap();
+    store.save("second", &second, "", None).unwrap();
+    let fingerprint = Fingerprint::of(&second.public_key()).to_string();
+
+    let options = Options::default();
+    assert_eq!(choose_message_key(&store, &options, Some(&fingerprint)), Ok(String::from("second")));
+    assert!(matches!(choose_message_key(&store, &options, Some("SHA256:unknown")), Err(CliError::Failed(_))));
+    assert!(matches!(choose_message_key(&store, &options, None), Err(CliError::Usage(_))));
+    store.save("copy", &second, "", None).unwrap();
+    assert!(matches!(choose_message_key(&store, &options, Some(&fingerprint)), Err(CliError::Usage(_))));
+
+    let options = Options{ key: Some(String::from("first")), ..Options::default() };
+    assert!(matches!(choose_message_key(&store, &options, Some(&fingerprint)), Err(CliError::Failed(_))));
+    assert_eq!(choose_message_key(&store, &options, None), Ok(String::from("first")));
+    _ = std::fs::remove_dir_all(store.dir());
+}
+
+#[test]
+fn test_cli_validity(){
+    let store = crate::keystore::test_keystore("cli-validity");
+    let key = keys::test_key();
+    let mut metadata = store.save("old", &key, "", None).unwrap();
+    let options = Options::default();
+    assert_eq!(check_key(&store, "old", KeyOperation::Encrypt, &options), Ok(()));
+
+    metadata.not_before -= 86400;
+    metadata.not_after = Some(metadata.not_before + 1);
+    store.update_metadata(&metadata).unwrap();
+    assert!(matches!(check_key(&store, "old", KeyOperation::Decrypt, &options), Err(CliError::Failed(_))));
+    let ignore = Options{ ignore_validity: true, ..Options::default() };
+    assert_eq!(check_key(&store, "old", KeyOperation::Decrypt, &ignore), Ok(()));
+    assert!(matches!(check_key(&store, "missing", KeyOperation::Decrypt, &ignore), Err(CliError::Keystore(KeystoreError::NotFound(_)))));
+
+    metadata.not_after = None;
+    store.update_metadata(&metadata).unwrap();
+    let run = |args:&[&str]| run_in(&store, args);
+    assert!(matches!(run(&["rotate", "old", "--days", "0"]), Err(CliError::Usage(_))));
+    assert_eq!(run(&["rotate", "old", "--new", "new", "--days", "30"]), Ok(()));
+    let new = store.metadata("new").unwrap();
+    assert_eq!(new.not_after, Some(new.not_before + 30 * 86400));
+    assert_eq!(store.metadata("old").unwrap().replaced_by.as_deref(), Some("new"));
+    assert!(matches!(check_key(&store, "old", KeyOperation::Encrypt, &options), Err(CliError::Failed(_))));
+    assert_eq!(check_key(&store, "old", KeyOperation::Decrypt, &options), Ok(()));
+    assert!(matches!(run(&["rotate", "old", "--new", "new"]), Err(CliError::Usage(_))));
+
+    assert_eq!(run(&["keys", "revoke", "old", "--reason", "lost laptop"]), Ok(()));
+    assert!(matches!(check_key(&store, "old", KeyOperation::Verify, &options), Err(CliError::Failed(_))));
+    assert!(matches!(run(&["encrypt", "--key", "old", "hi"]), Err(CliError::Failed(_))));
+    assert_eq!(run(&["encrypt", "--key", "old", "--ignore-validity", "hi"]), Ok(()));
+    _ = std::fs::remove_dir_all(store.dir());
+}
+
+#[test]
+fn test_cli_certificate(){
+    let store = crate::keystore::test_keystore("cli-certificate");
+    let key = keys::test_key();
+    store.save("web", &key, "", None).unwrap();
+    let run = |args:&[&str]| run_in(&store, args);
+
+    let options = parse_options(&strings(&["certificate", "create", "web", "--dns", "a.test", "--dns", "b.test", "--ca"])).unwrap();
+    assert_eq!(options.dns, strings(&["a.test", "b.test"]));
+    assert!(options.ca);
+    assert_eq!(run(&["certificate", "create", "web", "--subject", "CN=web, O=Test", "--days", "30"]), Ok(()));
+    assert!(matches!(run(&["certificate", "create", "web", "--subject", "web"]), Err(CliError::Usage(_))));
+    assert_eq!(run(&["certificate", "show", "fixtures/x509_rsa_1024.pem"]), Ok(()));
+    assert!(matches!(run(&["certificate", "show", "fixtures/rsa_public_spki.pem"]), Err(CliError::Failed(_))));
+    assert_eq!(run(&["certificate", "show", "fixtures/csr_rsa_1024.pem"]), Ok(()));
+
+    // The stored key issues a certificate for a request made by another key.
+    let server = keys::other_test_key();
+    let request = CertificateRequest::create(&Name::parse("CN=server").unwrap(), &[], &server);
+    let file = store.dir().join("server.csr");
+    std::fs::write(&file, request.to_pem()).unwrap();
+    let path = file.display().to_string();
+    assert!(matches!(run(&["certificate", "issue", &path]), Err(CliError::Usage(_))));
+    assert_eq!(run(&["certificate", "issue", "--key", "web", &path]), Ok(()));
+    assert!(matches!(run(&["certificate", "issue", "--key", "web", "fixtures/csr_rsa_1024.pem"]), Err(CliError::Failed(_))));
+    _ = std::fs::remove_dir_all(store.dir());
+}
@@ -0,0 +1,150 @@
+// Block codec, converts a vector of bytes into the integer blocks that are
+// encrypted and back again. The tutorial uses 8 byte little endian blocks, but
+// narrower blocks and big endian layouts are supported for other key sizes.
+
+use std::fmt;
+
+// Order of the bytes inside a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder{
+    LittleEndian,
+    BigEndian,
+}
+
+// Errors returned when bytes or blocks don't fit the codec layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecError{
+    // Block width must be between 1 and 8 bytes.
+    InvalidWidth(usize),
+    // Byte length is not a multiple of the block width.
+    TrailingBytes{ length:usize, width:usize },
+    // Block value needs more bytes than the block width.
+    BlockTooLarge{ index:usize, value:u64, width:usize },
+}
+
+impl fmt::Display for CodecError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            CodecError::InvalidWidth(w) => write!(f, "block width must be between 1 and 8 bytes, got {}", w),
+            CodecError::TrailingBytes{ length, width } => write!(f, "{} bytes is not a multiple of the {} byte block width", length, width),
+            CodecError::BlockTooLarge{ index, value, width } => write!(f, "block {} ({}) does not fit in {} bytes", index, value, width),
+        }
+    }
+}
+
+impl std::error::Error for CodecError{}
+
+// Describes how blocks are laid out, width in bytes and byte order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockCodec{
+    width:usize,
+    order:ByteOrder,
+}
+
+impl BlockCodec{
+    // Creates a codec, width must be between 1 and 8 bytes since blocks are u64.
+    pub fn new(width:usize, order:ByteOrder) -> Result<BlockCodec, CodecError>{
+        if width == 0 || width > 8{
+            return Err(CodecError::InvalidWidth(width));
+        }
+        Ok(BlockCodec{ width, order })
+    }
+
+    pub fn width(&self) -> usize{
+        self.width
+    }
+
+    pub fn order(&self) -> ByteOrder{
+        self.order
+    }
+
+    // Converts bytes into blocks. An empty slice gives no blocks, a length
+    // that isn't a multiple of the width is an error.
+    pub fn to_blocks(&self, bytes:&[u8]) -> Result<Vec<u64>, CodecError>{
+        if !bytes.len().is_multiple_of(self.width){
+            return Err(CodecError::TrailingBytes{ length: bytes.len(), width: self.width });
+        }
+
+        let mut blocks:Vec<u64> = Vec::with_capacity(bytes.len() / self.width);
+        for chunk in bytes.chunks_exact(self.width){
+            let mut eight:[u8;8] = [0;8];
+            let value = match self.order{
+                ByteOrder::LittleEndian => {
+                    eight[..self.width].copy_from_slice(chunk);
+                    u64::from_le_bytes(eight)
+                },
+                ByteOrder::BigEndian => {
+                    eight[8 - self.width..].copy_from_slice(chunk);
+                    u64::from_be_bytes(eight)
+                },
+            };
+            blocks.push(value);
+        }
+        Ok(blocks)
+    }
+
+    // Converts blocks back into bytes, every block must fit in the width.
+    pub fn to_bytes(&self, blocks:&[u64]) -> Result<Vec<u8>, CodecError>{
+        let mut bytes:Vec<u8> = Vec::with_capacity(blocks.len() * self.width);
+        for (index, value) in blocks.iter().enumerate(){
+            if self.width < 8 && *value >> (self.width * 8) != 0{
+                return Err(CodecError::BlockTooLarge{ index, value: *value, width: self.width });
+            }
+            match self.order{
+                ByteOrder::LittleEndian => bytes.extend_from_slice(&value.to_le_bytes()[..self.width]),
+                ByteOrder::BigEndian => bytes.extend_from_slice(&value.to_be_bytes()[8 - self.width..]),
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+// The tutorial layout, 8 byte little endian blocks.
+impl Default for BlockCodec{
+    fn default() -> BlockCodec{
+        BlockCodec{ width: 8, order: ByteOrder::LittleEndian }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_codec_little_endian(){
+    let codec = BlockCodec::default();
+    let bytes = [1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    let blocks = codec.to_blocks(&bytes).unwrap();
+    assert_eq!(blocks, vec![1, 256]);
+    assert_eq!(codec.to_bytes(&blocks).unwrap(), bytes.to_vec());
+}
+
+#[test]
+fn test_codec_big_endian(){
+    let codec = BlockCodec::new(8, ByteOrder::BigEndian).unwrap();
+    let bytes = [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0];
+    let blocks = codec.to_blocks(&bytes).unwrap();
+    assert_eq!(blocks, vec![1, 256]);
+    assert_eq!(codec.to_bytes(&blocks).unwrap(), bytes.to_vec());
+}
+
+#[test]
+fn test_codec_narrow_width(){
+    let le = BlockCodec::new(3, ByteOrder::LittleEndian).unwrap();
+    let be = BlockCodec::new(3, ByteOrder::BigEndian).unwrap();
+    let bytes = [1, 2, 3, 4, 5, 6];
+    assert_eq!(le.to_blocks(&bytes).unwrap(), vec![0x030201, 0x060504]);
+    assert_eq!(be.to_blocks(&bytes).unwrap(), vec![0x010203, 0x040506]);
+    assert_eq!(le.to_bytes(&[0x030201, 0x060504]).unwrap(), bytes.to_vec());
+    assert_eq!(be.to_bytes(&[0x010203, 0x040506]).unwrap(), bytes.to_vec());
+
+    assert_eq!(le.to_bytes(&[0x01000000]), Err(CodecError::BlockTooLarge{ index: 0, value: 0x01000000, width: 3 }));
+}
+
+#[test]
+fn test_codec_malformed(){
+    let codec = BlockCodec::default();
+    assert_eq!(codec.to_blocks(&[]), Ok(Vec::new()));
+    assert_eq!(codec.to_blocks(&[1, 2, 3]), Err(CodecError::TrailingBytes{ length: 3, width: 8 }));
+    assert_eq!(codec.to_blocks(&[0; 12]), Err(CodecError::TrailingBytes{ length: 12, width: 8 }));
+
+    assert_eq!(BlockCodec::new(0, ByteOrder::LittleEndian), Err(CodecError::InvalidWidth(0)));
+    assert_eq!(BlockCodec::new(9, ByteOrder::BigEndian), Err(CodecError::InvalidWidth(9)));
+}
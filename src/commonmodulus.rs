@@ -0,0 +1,51 @@
+// The common modulus attack. When two keys share n but have different e
+// with no common factor, the extended euclidean algorithm gives a and b with
+// a * e1 + b * e2 = 1. A message m sent to both arrives as c1 = m^e1 and
+// c2 = m^e2 mod n, and c1^a * c2^b = m^(a * e1 + b * e2) = m mod n, so
+// anyone who sees both cyphertexts reads m without either d. One of a and b
+// is negative, which is a power of the inverse of that cyphertext mod n.
+
+use crate::utility;
+
+// a and b with a * e1 + b * e2 = 1, or None if e1 and e2 share a factor.
+pub fn bezout(e1:u64, e2:u64) -> Option<(i128, i128)>{
+    let (g, a, b) = utility::extended_gcd(i128::from(e1), i128::from(e2));
+    if g == 1 {Some((a, b))} else {None}
+}
+
+// m from its encryptions with e1 and e2 under the same n. None when the
+// exponents share a factor or the cyphertext with the negative exponent has
+// no inverse, in which case it shares a prime with n.
+pub fn attack(c1:u64, c2:u64, e1:u64, e2:u64, n:u64) -> Option<u64>{
+    let (a, b) = bezout(e1, e2)?;
+    let first = power(c1, a, n)?;
+    let second = power(c2, b, n)?;
+    Some(((first as u128 * second as u128) % n as u128) as u64)
+}
+
+// c^exponent mod n for a negative exponent too.
+fn power(c:u64, exponent:i128, n:u64) -> Option<u64>{
+    if exponent >= 0{
+        return Some(crate::modded_exponent(c, exponent as u64, n));
+    }
+    let inverse = utility::mod_inverse(c, n)?;
+    Some(crate::modded_exponent(inverse, (-exponent) as u64, n))
+}
+
+#[cfg(test)]
+#[test]
+fn test_common_modulus(){
+    let n = 50000000021u64 * 368934871;
+    let m = 0x0765_6c6c_6f48_6c6cu64;
+    let (e1, e2) = (65537u64, 92829719u64);
+    let (a, b) = bezout(e1, e2).unwrap();
+    assert_eq!(a * i128::from(e1) + b * i128::from(e2), 1);
+    let c1 = crate::modded_exponent(m, e1, n);
+    let c2 = crate::modded_exponent(m, e2, n);
+    assert_eq!(attack(c1, c2, e1, e2, n), Some(m));
+    assert_eq!(attack(c2, c1, e2, e1, n), Some(m));
+    assert_eq!(bezout(15, 21), None);
+    assert_eq!(attack(c1, c2, 15, 21, n), None);
+    // A cyphertext that is a multiple of p has no inverse.
+    assert_eq!(power(50000000021, -1, n), None);
+}
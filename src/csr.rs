@@ -0,0 +1,218 @@
+// PKCS#10 certificate signing requests (RFC 2986). A request holds the
+// subject's name and public key, and any extensions it asks for, signed with
+// the subject's own private key to show it holds the key. A certificate
+// authority checks that signature and issues a certificate from the request.
+// Signatures are the ones described in the x509 module.
+
+use crate::der::{self, DerReader};
+use crate::keys::{RSAPrivateKey, RSAPublicKey};
+use crate::pem;
+use crate::x509::{self, CertificateBuilder, Extension, Name, X509Error};
+
+pub const CSR_LABEL: &str = "CERTIFICATE REQUEST";
+
+// extensionRequest from PKCS#9, the attribute carrying extensions for the certificate.
+const OID_EXTENSION_REQUEST: &[u64] = &[1, 2, 840, 113549, 1, 9, 14];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateRequest{
+    der:Vec<u8>,
+    info:Vec<u8>,
+    subject:Name,
+    spki:Vec<u8>,
+    extensions:Vec<Extension>,
+    signature_algorithm:Vec<u64>,
+    signature:Vec<u8>,
+}
+
+impl CertificateRequest{
+    // CertificationRequestInfo ::= SEQUENCE { version INTEGER 0, subject Name,
+    //     subjectPKInfo, attributes [0] IMPLICIT SET OF Attribute }
+    // Host names are asked for with a subject alternative name in an
+    // extensionRequest attribute.
+    pub fn create(subject:&Name, dns_names:&[String], key:&RSAPrivateKey) -> CertificateRequest{
+        let mut attributes:Vec<Vec<u8>> = Vec::new();
+        if !dns_names.is_empty(){
+            let extensions = der::encode_sequence(&[Extension::subject_alt_name(dns_names).to_der()]);
+            attributes.push(der::encode_sequence(&[x509::encode_oid(OID_EXTENSION_REQUEST), der::encode_set(&[extensions])]));
+        }
+        attributes.sort();
+        let info = der::encode_sequence(&[
+            der::encode_integer(0),
+            subject.to_der(),
+            key.public_key().to_spki_der(),
+            der::encode_tlv(der::context_tag(0), &attributes.concat()),
+        ]);
+        match CertificateRequest::from_der(&x509::sign_der(info, key)){
+            Ok(s) => s,
+            Err(e) => panic!("Built certificate request can't be read back. {}", e),
+        }
+    }
+
+    pub fn from_der(data:&[u8]) -> Result<CertificateRequest, X509Error>{
+        let (info, signature_algorithm, signature) = x509::read_signed(data)?;
+        let mut reader = DerReader::new(info);
+        let mut fields = reader.read_sequence()?;
+        reader.finish()?;
+        let version = fields.read_integer()?;
+        if version != 0{
+            return Err(X509Error::UnsupportedVersion(version));
+        }
+        let subject = Name::read(&mut fields)?;
+        let spki = fields.read_raw()?.to_vec();
+
+        // Attributes other than extensionRequest, like challengePassword, are skipped.
+        let mut extensions:Vec<Extension> = Vec::new();
+        let mut attributes = DerReader::new(fields.read_expected(der::context_tag(0))?);
+        fields.finish()?;
+        while !attributes.is_empty(){
+            let mut attribute = attributes.read_sequence()?;
+            let oid = attribute.read_oid()?;
+            let mut values = DerReader::new(attribute.read_expected(der::TAG_SET)?);
+            attribute.finish()?;
+            if oid == OID_EXTENSION_REQUEST{
+                let mut list = values.read_sequence()?;
+                values.finish()?;
+                while !list.is_empty(){
+                    extensions.push(Extension::read(&mut list)?);
+                }
+            }
+        }
+
+        Ok(CertificateRequest{ der: data.to_vec(), info: info.to_vec(), subject, spki, extensions,
+            signature_algorithm, signature: signature.to_vec() })
+    }
+
+    pub fn to_der(&self) -> Vec<u8>{
+        self.der.clone()
+    }
+
+    pub fn from_pem(text:&str) -> Result<CertificateRequest, X509Error>{
+        CertificateRequest::from_der(&pem::decode_expected(text, CSR_LABEL)?)
+    }
+
+    pub fn to_pem(&self) -> String{
+        pem::encode(CSR_LABEL, &self.der)
+    }
+
+    pub fn get_subject(&self) -> &Name{
+        &self.subject
+    }
+
+    // Extensions asked for in the extensionRequest attribute.
+    pub fn get_extensions(&self) -> &[Extension]{
+        &self.extensions
+    }
+
+    pub fn dns_names(&self) -> Vec<String>{
+        x509::dns_names(&self.extensions)
+    }
+
+    pub fn get_signature_algorithm(&self) -> &[u64]{
+        &self.signature_algorithm
+    }
+
+    // The key the certificate is wanted for, fails for keys that aren't RSA or
+    // don't fit in 64 bits.
+    pub fn public_key(&self) -> Result<RSAPublicKey, X509Error>{
+        Ok(RSAPublicKey::from_spki_der(&self.spki)?)
+    }
+
+    // Checks the request was signed with the private half of the key in it,
+    // using the tutorial's signature algorithm.
+    pub fn verify(&self) -> Result<bool, X509Error>{
+        x509::verify_signature(&self.info, &self.signature_algorithm, &self.signature, &self.public_key()?)
+    }
+
+    // Starts a certificate for the request with its subject and host names, to
+    // be signed by the issuer. Only requests with a good signature are accepted.
+    pub fn to_builder(&self, issuer:&Name, not_before:u64, not_after:u64) -> Result<CertificateBuilder, X509Error>{
+        if !self.verify()?{
+            return Err(X509Error::BadSignature);
+        }
+        let mut builder = CertificateBuilder::new(self.subject.clone(), not_before, not_after);
+        builder.issuer = Some(issuer.clone());
+        builder.dns_names = self.dns_names();
+        Ok(builder)
+    }
+}
+
+#[test]
+fn test_csr_round_trip(){
+    let key = crate::keys::other_test_key();
+    let subject = Name::parse("CN=server.test, O=Example").unwrap();
+    let dns_names = vec![String::from("server.test"), String::from("www.server.test")];
+    let request = CertificateRequest::create(&subject, &dns_names, &key);
+
+    let pem = request.to_pem();
+    assert!(pem.starts_with("-----BEGIN CERTIFICATE REQUEST-----\n"));
+    let read = CertificateRequest::from_pem(&pem).unwrap();
+    assert_eq!(read, request);
+    assert_eq!(read.get_subject(), &subject);
+    assert_eq!(read.public_key(), Ok(key.public_key()));
+    assert_eq!(read.dns_names(), dns_names);
+    assert_eq!(read.get_signature_algorithm(), x509::OID_TUTORIAL_SIGNATURE);
+    assert_eq!(read.verify(), Ok(true));
+
+    // Without host names the attributes are empty.
+    let plain = CertificateRequest::create(&subject, &[], &key);
+    assert!(plain.get_extensions().is_empty());
+    assert_eq!(plain.verify(), Ok(true));
+}
+
+#[test]
+fn test_csr_bad_signature(){
+    let key = crate::keys::other_test_key();
+    let request = CertificateRequest::create(&Name::parse("CN=server.test").unwrap(), &[], &key);
+
+    // Swap in another key of the same size, the signature no longer matches it.
+    let other = RSAPublicKey::new(key.get_n(), 65539);
+    let mut der = request.to_der();
+    let old = key.public_key().to_spki_der();
+    let position = der.windows(old.len()).position(|w| w == old.as_slice()).unwrap();
+    der.splice(position..position + old.len(), other.to_spki_der());
+    let forged = CertificateRequest::from_der(&der).unwrap();
+    assert_eq!(forged.public_key(), Ok(other));
+    assert_eq!(forged.verify(), Ok(false));
+    assert_eq!(forged.to_builder(&Name::parse("CN=CA").unwrap(), 0, 86400), Err(X509Error::BadSignature));
+
+    // The same signature labelled sha256WithRSAEncryption isn't checked at all.
+    let signed = request.to_der();
+    let (info, _, value) = x509::read_signed(&signed).unwrap();
+    let sha256_with_rsa:&[u64] = &[1, 2, 840, 113549, 1, 1, 11];
+    let relabelled = der::encode_sequence(&[
+        info.to_vec(),
+        der::encode_sequence(&[x509::encode_oid(sha256_with_rsa), der::encode_null()]),
+        der::encode_bit_string(value),
+    ]);
+    let relabelled = CertificateRequest::from_der(&relabelled).unwrap();
+    assert_eq!(relabelled.verify(), Err(X509Error::UnsupportedAlgorithm(sha256_with_rsa.to_vec())));
+}
+
+#[test]
+fn test_csr_issue(){
+    let ca_key = crate::keys::test_key();
+    let ca_name = Name::parse("CN=Test CA").unwrap();
+    let key = crate::keys::other_test_key();
+    let request = CertificateRequest::create(&Name::parse("CN=server.test").unwrap(), &[String::from("server.test")], &key);
+
+    let builder = request.to_builder(&ca_name, 0, 86400).unwrap();
+    let certificate = builder.sign(&request.public_key().unwrap(), &ca_key);
+    assert_eq!(certificate.get_subject().to_string(), "CN=server.test");
+    assert_eq!(certificate.get_issuer(), &ca_name);
+    assert_eq!(certificate.dns_names(), vec![String::from("server.test")]);
+    assert_eq!(certificate.public_key(), Ok(key.public_key()));
+    assert_eq!(certificate.verify(&ca_key.public_key()), Ok(true));
+}
+
+#[test]
+fn test_csr_openssl_request(){
+    // Made by openssl req -new with a 1024 bit key, too large for the key types.
+    let request = CertificateRequest::from_pem(include_str!("../fixtures/csr_rsa_1024.pem")).unwrap();
+    assert_eq!(request.get_subject().to_string(), "CN=openssl.test, O=Fixture Org");
+    assert_eq!(request.dns_names(), vec![String::from("openssl.test")]);
+    assert_eq!(request.get_signature_algorithm(), &[1, 2, 840, 113549, 1, 1, 11]);
+    assert!(request.public_key().is_err());
+    assert!(request.verify().is_err());
+}
+
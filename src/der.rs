@@ -0,0 +1,542 @@
+// ASN.1 DER encoder and decoder, only the parts needed for the key and
+// certificate formats.
+// Encoding functions return complete TLV (tag, length, value) byte vectors
+// which can be nested, decoding goes through DerReader.
+
+use crate::timestamp;
+use std::fmt;
+
+pub const TAG_BOOLEAN: u8 = 0x01;
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_NULL: u8 = 0x05;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_UTF8_STRING: u8 = 0x0c;
+pub const TAG_PRINTABLE_STRING: u8 = 0x13;
+pub const TAG_TELETEX_STRING: u8 = 0x14;
+pub const TAG_IA5_STRING: u8 = 0x16;
+pub const TAG_UTC_TIME: u8 = 0x17;
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+pub const TAG_BMP_STRING: u8 = 0x1e;
+pub const TAG_SEQUENCE: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
+
+// Tag of a constructed context specific element, [0] is 0xa0.
+pub const fn context_tag(number:u8) -> u8{
+    0xa0 | number
+}
+
+// Errors returned when bytes are not valid DER.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DerError{
+    // Ran out of bytes in the middle of an element.
+    UnexpectedEnd,
+    // Found a different tag than the structure requires.
+    UnexpectedTag{ expected:u8, found:u8 },
+    // Length or value is not in the single allowed DER form.
+    NonCanonical,
+    // Tag number too large for a single byte, not used by any supported format.
+    UnsupportedTag,
+    // Integer is negative where only positive values are allowed.
+    NegativeInteger,
+    // Integer does not fit in 64 bits.
+    IntegerTooLarge,
+    // Object identifier that can't be encoded or decoded.
+    InvalidOid,
+    // BIT STRING with unused bits, the key formats always use whole bytes.
+    InvalidBitString,
+    // Bytes left over after the structure ended.
+    TrailingData,
+    // UTCTime or GeneralizedTime that isn't a valid date in the DER form.
+    InvalidTime,
+    // String that isn't valid for its type.
+    InvalidString,
+}
+
+impl fmt::Display for DerError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            DerError::UnexpectedEnd => write!(f, "unexpected end of DER data"),
+            DerError::UnexpectedTag{ expected, found } => write!(f, "expected DER tag 0x{:02x}, found 0x{:02x}", expected, found),
+            DerError::NonCanonical => write!(f, "DER element is not in canonical form"),
+            DerError::UnsupportedTag => write!(f, "unsupported DER tag"),
+            DerError::NegativeInteger => write!(f, "negative DER integer"),
+            DerError::IntegerTooLarge => write!(f, "DER integer is larger than 64 bits"),
+            DerError::InvalidOid => write!(f, "invalid DER object identifier"),
+            DerError::InvalidBitString => write!(f, "DER bit string has unused bits"),
+            DerError::TrailingData => write!(f, "trailing data after DER structure"),
+            DerError::InvalidTime => write!(f, "invalid DER time"),
+            DerError::InvalidString => write!(f, "invalid DER string"),
+        }
+    }
+}
+
+impl std::error::Error for DerError{}
+
+// Encodes a tag, the length of the content and the content itself.
+pub fn encode_tlv(tag:u8, content:&[u8]) -> Vec<u8>{
+    let mut result:Vec<u8> = Vec::with_capacity(content.len() + 6);
+    result.push(tag);
+    let len = content.len();
+    if len < 0x80{
+        result.push(len as u8);
+    }else{
+        // Long form, number of length bytes followed by the length in big endian.
+        let bytes = (len as u64).to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        result.push(0x80 | (8 - skip) as u8);
+        result.extend_from_slice(&bytes[skip..]);
+    }
+    result.extend_from_slice(content);
+    result
+}
+
+// Encodes a SEQUENCE from already encoded elements.
+pub fn encode_sequence(elements:&[Vec<u8>]) -> Vec<u8>{
+    encode_tlv(TAG_SEQUENCE, &elements.concat())
+}
+
+// Encodes a non negative INTEGER, using the fewest bytes and adding a leading
+// zero byte when the top bit is set so it isn't read as negative.
+pub fn encode_integer(value:u64) -> Vec<u8>{
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take(7).take_while(|b| **b == 0).count();
+    let mut content:Vec<u8> = Vec::with_capacity(9);
+    if bytes[skip] & 0x80 != 0{
+        content.push(0);
+    }
+    content.extend_from_slice(&bytes[skip..]);
+    encode_tlv(TAG_INTEGER, &content)
+}
+
+// Encodes an OCTET STRING.
+pub fn encode_octet_string(content:&[u8]) -> Vec<u8>{
+    encode_tlv(TAG_OCTET_STRING, content)
+}
+
+// Encodes a BIT STRING made of whole bytes, so the unused bit count is 0.
+pub fn encode_bit_string(content:&[u8]) -> Vec<u8>{
+    let mut bits:Vec<u8> = Vec::with_capacity(content.len() + 1);
+    bits.push(0);
+    bits.extend_from_slice(content);
+    encode_tlv(TAG_BIT_STRING, &bits)
+}
+
+pub fn encode_null() -> Vec<u8>{
+    encode_tlv(TAG_NULL, &[])
+}
+
+pub fn encode_boolean(value:bool) -> Vec<u8>{
+    encode_tlv(TAG_BOOLEAN, &[if value {0xff} else {0}])
+}
+
+// Encodes a SET OF, DER wants the elements sorted by their encoding.
+pub fn encode_set(elements:&[Vec<u8>]) -> Vec<u8>{
+    let mut sorted = elements.to_vec();
+    sorted.sort();
+    encode_tlv(TAG_SET, &sorted.concat())
+}
+
+// Encodes a time the way RFC 5280 section 4.1.2.5 asks, UTCTime with a two
+// digit year up to 2049 and GeneralizedTime from 2050, always in UTC with seconds.
+pub fn encode_time(seconds:u64) -> Vec<u8>{
+    let (year, month, day, hour, minute, second) = timestamp::to_civil(seconds);
+    if year < 2050{
+        let text = format!("{:02}{:02}{:02}{:02}{:02}{:02}Z", year % 100, month, day, hour, minute, second);
+        encode_tlv(TAG_UTC_TIME, text.as_bytes())
+    }else{
+        let text = format!("{:04}{:02}{:02}{:02}{:02}{:02}Z", year, month, day, hour, minute, second);
+        encode_tlv(TAG_GENERALIZED_TIME, text.as_bytes())
+    }
+}
+
+// Encodes an OBJECT IDENTIFIER from its arcs, for example [1, 2, 840, 113549, 1, 1, 1].
+// The first two arcs share a byte (40 * first + second), every arc is written in
+// base 128 with the top bit set on all but the last byte.
+pub fn encode_oid(arcs:&[u64]) -> Result<Vec<u8>, DerError>{
+    if arcs.len() < 2 || arcs[0] > 2 || (arcs[0] < 2 && arcs[1] > 39){
+        return Err(DerError::InvalidOid);
+    }
+    let first = match (arcs[0] * 40).checked_add(arcs[1]){
+        Some(s) => s,
+        None => return Err(DerError::InvalidOid),
+    };
+
+    let mut content:Vec<u8> = Vec::new();
+    for arc in std::iter::once(first).chain(arcs[2..].iter().copied()){
+        let mut digits:Vec<u8> = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0{
+            digits.push(0x80 | (rest & 0x7f) as u8);
+            rest >>= 7;
+        }
+        content.extend(digits.iter().rev());
+    }
+    Ok(encode_tlv(TAG_OID, &content))
+}
+
+// Reads DER elements one after another from a byte slice.
+pub struct DerReader<'a>{
+    data:&'a [u8],
+    position:usize,
+}
+
+impl<'a> DerReader<'a>{
+    pub fn new(data:&'a [u8]) -> DerReader<'a>{
+        DerReader{ data, position: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.position >= self.data.len()
+    }
+
+    // Reads the next element, returning its tag and content.
+    pub fn read_tlv(&mut self) -> Result<(u8, &'a [u8]), DerError>{
+        let tag = self.next_byte()?;
+        if tag & 0x1f == 0x1f{
+            return Err(DerError::UnsupportedTag);
+        }
+
+        let first = self.next_byte()?;
+        let len = if first < 0x80{
+            first as usize
+        }else{
+            // 0x80 is the BER indefinite length, which DER doesn't allow.
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > 4{
+                return Err(DerError::NonCanonical);
+            }
+            let mut len = 0usize;
+            for i in 0..count{
+                let b = self.next_byte()?;
+                if i == 0 && b == 0{
+                    return Err(DerError::NonCanonical);
+                }
+                len = (len << 8) | b as usize;
+            }
+            // Lengths below 128 must use the short form.
+            if len < 0x80{
+                return Err(DerError::NonCanonical);
+            }
+            len
+        };
+
+        if self.data.len() - self.position < len{
+            return Err(DerError::UnexpectedEnd);
+        }
+        let content = &self.data[self.position..self.position + len];
+        self.position += len;
+        Ok((tag, content))
+    }
+
+    // Reads the next element and checks it has the expected tag.
+    pub fn read_expected(&mut self, expected:u8) -> Result<&'a [u8], DerError>{
+        let (tag, content) = self.read_tlv()?;
+        if tag != expected{
+            return Err(DerError::UnexpectedTag{ expected, found: tag });
+        }
+        Ok(content)
+    }
+
+    // Reads a SEQUENCE and returns a reader over its elements.
+    pub fn read_sequence(&mut self) -> Result<DerReader<'a>, DerError>{
+        let content = self.read_expected(TAG_SEQUENCE)?;
+        Ok(DerReader::new(content))
+    }
+
+    // Reads a non negative INTEGER that fits in 64 bits.
+    pub fn read_integer(&mut self) -> Result<u64, DerError>{
+        let content = self.read_expected(TAG_INTEGER)?;
+        if content.is_empty(){
+            return Err(DerError::NonCanonical);
+        }
+        if content[0] & 0x80 != 0{
+            return Err(DerError::NegativeInteger);
+        }
+        // A leading zero is only allowed when the next byte has its top bit set.
+        if content.len() > 1 && content[0] == 0 && content[1] & 0x80 == 0{
+            return Err(DerError::NonCanonical);
+        }
+        let digits = if content[0] == 0 && content.len() > 1 {&content[1..]} else {content};
+        if digits.len() > 8{
+            return Err(DerError::IntegerTooLarge);
+        }
+        let mut value = 0u64;
+        for b in digits{
+            value = (value << 8) | *b as u64;
+        }
+        Ok(value)
+    }
+
+    pub fn read_octet_string(&mut self) -> Result<&'a [u8], DerError>{
+        self.read_expected(TAG_OCTET_STRING)
+    }
+
+    // Reads a BIT STRING and returns its bytes, unused bits are not allowed.
+    pub fn read_bit_string(&mut self) -> Result<&'a [u8], DerError>{
+        let content = self.read_expected(TAG_BIT_STRING)?;
+        match content.first(){
+            Some(0) => Ok(&content[1..]),
+            Some(_) => Err(DerError::InvalidBitString),
+            None => Err(DerError::NonCanonical),
+        }
+    }
+
+    pub fn read_null(&mut self) -> Result<(), DerError>{
+        let content = self.read_expected(TAG_NULL)?;
+        if !content.is_empty(){
+            return Err(DerError::NonCanonical);
+        }
+        Ok(())
+    }
+
+    // Reads an OBJECT IDENTIFIER and returns its arcs.
+    pub fn read_oid(&mut self) -> Result<Vec<u64>, DerError>{
+        let content = self.read_expected(TAG_OID)?;
+        if content.is_empty() || content[content.len() - 1] & 0x80 != 0{
+            return Err(DerError::InvalidOid);
+        }
+
+        let mut arcs:Vec<u64> = Vec::new();
+        let mut value = 0u64;
+        let mut start = true;
+        for b in content{
+            // A leading 0x80 would be a padded, non minimal arc.
+            if start && *b == 0x80{
+                return Err(DerError::NonCanonical);
+            }
+            if value >> 57 != 0{
+                return Err(DerError::InvalidOid);
+            }
+            value = (value << 7) | (*b & 0x7f) as u64;
+            start = *b & 0x80 == 0;
+            if start{
+                if arcs.is_empty(){
+                    let first = if value < 80 {value / 40} else {2};
+                    arcs.push(first);
+                    arcs.push(value - first * 40);
+                }else{
+                    arcs.push(value);
+                }
+                value = 0;
+            }
+        }
+        Ok(arcs)
+    }
+
+    pub fn read_boolean(&mut self) -> Result<bool, DerError>{
+        match self.read_expected(TAG_BOOLEAN)?{
+            [0] => Ok(false),
+            [0xff] => Ok(true),
+            _ => Err(DerError::NonCanonical),
+        }
+    }
+
+    // Reads a UTCTime or GeneralizedTime in the RFC 5280 form and returns it in
+    // seconds since 1970. Two digit years from 50 are 19xx, the rest 20xx.
+    pub fn read_time(&mut self) -> Result<u64, DerError>{
+        let (tag, content) = self.read_tlv()?;
+        let year_digits = match tag{
+            TAG_UTC_TIME => 2,
+            TAG_GENERALIZED_TIME => 4,
+            _ => return Err(DerError::UnexpectedTag{ expected: TAG_UTC_TIME, found: tag }),
+        };
+        if content.len() != year_digits + 11 || content[content.len() - 1] != b'Z' || !content[..content.len() - 1].iter().all(u8::is_ascii_digit){
+            return Err(DerError::InvalidTime);
+        }
+        let digits = |start:usize, count:usize| content[start..start + count].iter().fold(0u64, |v, d| v * 10 + (d - b'0') as u64);
+        let mut year = digits(0, year_digits);
+        if year_digits == 2{
+            year += if year >= 50 {1900} else {2000};
+        }
+        let rest = year_digits;
+        match timestamp::from_civil(year, digits(rest, 2), digits(rest + 2, 2), digits(rest + 4, 2), digits(rest + 6, 2), digits(rest + 8, 2)){
+            Some(s) => Ok(s),
+            None => Err(DerError::InvalidTime),
+        }
+    }
+
+    // Reads any of the string types used in names and returns the text.
+    // TeletexString is read as Latin-1, which is what it holds in practice.
+    pub fn read_string(&mut self) -> Result<String, DerError>{
+        let (tag, content) = self.read_tlv()?;
+        match tag{
+            TAG_UTF8_STRING | TAG_PRINTABLE_STRING | TAG_IA5_STRING => match std::str::from_utf8(content){
+                Ok(s) => Ok(s.to_string()),
+                Err(_) => Err(DerError::InvalidString),
+            },
+            TAG_TELETEX_STRING => Ok(content.iter().map(|b| *b as char).collect()),
+            TAG_BMP_STRING => {
+                if content.len() % 2 != 0{
+                    return Err(DerError::InvalidString);
+                }
+                let units:Vec<u16> = content.chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+                match String::from_utf16(&units){
+                    Ok(s) => Ok(s),
+                    Err(_) => Err(DerError::InvalidString),
+                }
+            },
+            _ => Err(DerError::UnexpectedTag{ expected: TAG_UTF8_STRING, found: tag }),
+        }
+    }
+
+    // Reads the next element and returns all of its bytes, tag and length
+    // included, for structures that are signed as they were encoded.
+    pub fn read_raw(&mut self) -> Result<&'a [u8], DerError>{
+        let start = self.position;
+        self.read_tlv()?;
+        Ok(&self.data[start..self.position])
+    }
+
+    // Tag of the next element, without reading it.
+    pub fn peek_tag(&self) -> Option<u8>{
+        self.data.get(self.position).copied()
+    }
+
+    // Checks every byte has been read.
+    pub fn finish(&self) -> Result<(), DerError>{
+        if self.is_empty() {Ok(())} else {Err(DerError::TrailingData)}
+    }
+
+    fn next_byte(&mut self) -> Result<u8, DerError>{
+        match self.data.get(self.position){
+            Some(b) => {
+                self.position += 1;
+                Ok(*b)
+            },
+            None => Err(DerError::UnexpectedEnd),
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_der_integer(){
+    assert_eq!(encode_integer(0), vec![0x02, 0x01, 0x00]);
+    assert_eq!(encode_integer(127), vec![0x02, 0x01, 0x7f]);
+    assert_eq!(encode_integer(128), vec![0x02, 0x02, 0x00, 0x80]);
+    assert_eq!(encode_integer(65537), vec![0x02, 0x03, 0x01, 0x00, 0x01]);
+    assert_eq!(encode_integer(u64::MAX), vec![0x02, 0x09, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+
+    for value in [0u64, 1, 127, 128, 255, 256, 65537, 18446743557747632291, u64::MAX]{
+        let encoded = encode_integer(value);
+        let mut reader = DerReader::new(&encoded);
+        assert_eq!(reader.read_integer(), Ok(value));
+        assert_eq!(reader.finish(), Ok(()));
+    }
+}
+
+#[test]
+fn test_der_integer_invalid(){
+    assert_eq!(DerReader::new(&[0x02, 0x01, 0x80]).read_integer(), Err(DerError::NegativeInteger));
+    assert_eq!(DerReader::new(&[0x02, 0x02, 0x00, 0x7f]).read_integer(), Err(DerError::NonCanonical));
+    assert_eq!(DerReader::new(&[0x02, 0x00]).read_integer(), Err(DerError::NonCanonical));
+    assert_eq!(DerReader::new(&[0x02, 0x09, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]).read_integer(), Err(DerError::IntegerTooLarge));
+    assert_eq!(DerReader::new(&[0x04, 0x01, 0x00]).read_integer(), Err(DerError::UnexpectedTag{ expected: TAG_INTEGER, found: 0x04 }));
+}
+
+#[test]
+fn test_der_length(){
+    let long = vec![7u8; 300];
+    let encoded = encode_tlv(0x04, &long);
+    assert_eq!(&encoded[..4], &[0x04, 0x82, 0x01, 0x2c]);
+    let mut reader = DerReader::new(&encoded);
+    assert_eq!(reader.read_tlv(), Ok((0x04, &long[..])));
+
+    // Indefinite length, long form for a short length, and a leading zero length byte.
+    assert_eq!(DerReader::new(&[0x30, 0x80, 0x00, 0x00]).read_tlv(), Err(DerError::NonCanonical));
+    assert_eq!(DerReader::new(&[0x04, 0x81, 0x05, 1, 2, 3, 4, 5]).read_tlv(), Err(DerError::NonCanonical));
+    assert_eq!(DerReader::new(&[0x04, 0x82, 0x00, 0x80]).read_tlv(), Err(DerError::NonCanonical));
+    // Content shorter than the length.
+    assert_eq!(DerReader::new(&[0x04, 0x03, 1, 2]).read_tlv(), Err(DerError::UnexpectedEnd));
+}
+
+#[test]
+fn test_der_sequence(){
+    let encoded = encode_sequence(&[encode_integer(3), encode_integer(5)]);
+    assert_eq!(encoded, vec![0x30, 0x06, 0x02, 0x01, 0x03, 0x02, 0x01, 0x05]);
+
+    let mut outer = DerReader::new(&encoded);
+    let mut inner = outer.read_sequence().unwrap();
+    assert_eq!(inner.read_integer(), Ok(3));
+    assert_eq!(inner.read_integer(), Ok(5));
+    assert!(inner.finish().is_ok());
+    assert!(outer.finish().is_ok());
+
+    let mut extra = encoded.clone();
+    extra.push(0);
+    let mut reader = DerReader::new(&extra);
+    reader.read_sequence().unwrap();
+    assert_eq!(reader.finish(), Err(DerError::TrailingData));
+}
+
+#[test]
+fn test_der_oid(){
+    let rsa = [1u64, 2, 840, 113549, 1, 1, 1];
+    let encoded = encode_oid(&rsa).unwrap();
+    assert_eq!(encoded, vec![0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01]);
+    assert_eq!(DerReader::new(&encoded).read_oid(), Ok(rsa.to_vec()));
+
+    // Joint iso itu arcs can be larger than 39 in the second position.
+    let large = [2u64, 999, 3];
+    assert_eq!(encode_oid(&large).unwrap(), vec![0x06, 0x03, 0x88, 0x37, 0x03]);
+    assert_eq!(DerReader::new(&encode_oid(&large).unwrap()).read_oid(), Ok(large.to_vec()));
+
+    assert_eq!(encode_oid(&[1]), Err(DerError::InvalidOid));
+    assert_eq!(encode_oid(&[1, 40]), Err(DerError::InvalidOid));
+    assert_eq!(encode_oid(&[3, 1]), Err(DerError::InvalidOid));
+    // Padded arc and an arc cut off in the middle.
+    assert_eq!(DerReader::new(&[0x06, 0x02, 0x80, 0x01]).read_oid(), Err(DerError::NonCanonical));
+    assert_eq!(DerReader::new(&[0x06, 0x02, 0x2a, 0x86]).read_oid(), Err(DerError::InvalidOid));
+}
+
+#[test]
+fn test_der_strings(){
+    let octets = encode_octet_string(&[1, 2, 3]);
+    assert_eq!(octets, vec![0x04, 0x03, 1, 2, 3]);
+    assert_eq!(DerReader::new(&octets).read_octet_string(), Ok(&[1u8, 2, 3][..]));
+
+    let bits = encode_bit_string(&[0xff]);
+    assert_eq!(bits, vec![0x03, 0x02, 0x00, 0xff]);
+    assert_eq!(DerReader::new(&bits).read_bit_string(), Ok(&[0xffu8][..]));
+    assert_eq!(DerReader::new(&[0x03, 0x02, 0x01, 0xfe]).read_bit_string(), Err(DerError::InvalidBitString));
+
+    assert_eq!(encode_null(), vec![0x05, 0x00]);
+    assert_eq!(DerReader::new(&[0x05, 0x00]).read_null(), Ok(()));
+    assert_eq!(DerReader::new(&[0x05, 0x01, 0x00]).read_null(), Err(DerError::NonCanonical));
+}
+
+#[test]
+fn test_der_time(){
+    // 2024-05-01 13:45:00 UTC and the last second of 2049 are UTCTime, 2050 on is GeneralizedTime.
+    assert_eq!(encode_time(1714571100), encode_tlv(TAG_UTC_TIME, b"240501134500Z"));
+    assert_eq!(encode_time(2524607999), encode_tlv(TAG_UTC_TIME, b"491231235959Z"));
+    assert_eq!(encode_time(2524608000), encode_tlv(TAG_GENERALIZED_TIME, b"20500101000000Z"));
+    for seconds in [0u64, 1714571100, 2524607999, 2524608000, 253402300799]{
+        assert_eq!(DerReader::new(&encode_time(seconds)).read_time(), Ok(seconds));
+    }
+    assert_eq!(DerReader::new(&encode_tlv(TAG_UTC_TIME, b"700101000000Z")).read_time(), Ok(0));
+    assert_eq!(DerReader::new(&encode_tlv(TAG_UTC_TIME, b"2405011345Z")).read_time(), Err(DerError::InvalidTime));
+    assert_eq!(DerReader::new(&encode_tlv(TAG_UTC_TIME, b"240501134500+0100")).read_time(), Err(DerError::InvalidTime));
+    assert_eq!(DerReader::new(&encode_tlv(TAG_UTC_TIME, b"240231000000Z")).read_time(), Err(DerError::InvalidTime));
+    assert!(DerReader::new(&encode_integer(1)).read_time().is_err());
+}
+
+#[test]
+fn test_der_set_and_strings(){
+    assert_eq!(encode_set(&[encode_integer(5), encode_integer(3)]), encode_tlv(TAG_SET, &[0x02, 0x01, 0x03, 0x02, 0x01, 0x05]));
+    assert_eq!(encode_boolean(true), vec![0x01, 0x01, 0xff]);
+    assert_eq!(DerReader::new(&encode_boolean(false)).read_boolean(), Ok(false));
+    assert_eq!(DerReader::new(&[0x01, 0x01, 0x01]).read_boolean(), Err(DerError::NonCanonical));
+
+    assert_eq!(DerReader::new(&encode_tlv(TAG_PRINTABLE_STRING, b"GB")).read_string(), Ok(String::from("GB")));
+    assert_eq!(DerReader::new(&encode_tlv(TAG_UTF8_STRING, "Zürich".as_bytes())).read_string(), Ok(String::from("Zürich")));
+    assert_eq!(DerReader::new(&encode_tlv(TAG_BMP_STRING, &[0, b'h', 0, b'i'])).read_string(), Ok(String::from("hi")));
+    assert_eq!(DerReader::new(&encode_tlv(TAG_UTF8_STRING, &[0xff])).read_string(), Err(DerError::InvalidString));
+
+    let encoded = encode_sequence(&[encode_integer(1), encode_null()]);
+    let mut reader = DerReader::new(&encoded);
+    assert_eq!(reader.read_raw(), Ok(&encoded[..]));
+    assert!(reader.finish().is_ok());
+}
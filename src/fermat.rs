@@ -0,0 +1,66 @@
+// Fermat's factoring method. An odd n = p * q is a difference of two squares,
+// a^2 - b^2 with a = (p + q) / 2 and b = (q - p) / 2, so trying a from
+// ceil(sqrt(n)) upwards until a^2 - n is a square finds p = a - b and
+// q = a + b. The search takes about (q - p)^2 / (8 * sqrt(n)) steps, so
+// primes found close together, as find_prime does when it starts near
+// sqrt(u64::MAX), are found almost at once.
+
+// Steps given to factor when no budget is chosen.
+pub const DEFAULT_BUDGET:u64 = 1 << 20;
+
+// One value of a tried, with b^2 = a^2 - n and b when that is a square.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step{
+    pub a:u128,
+    pub b_squared:u128,
+    pub b:Option<u128>,
+}
+
+// The values of a in order from ceil(sqrt(n)), as far as a^2 fits in a u128.
+pub fn steps(n:u64) -> impl Iterator<Item = Step>{
+    let mut a = n.isqrt() as u128;
+    if a * a < n as u128{
+        a += 1;
+    }
+    (a..=u64::MAX as u128).map(move |a| {
+        let b_squared = a * a - n as u128;
+        let root = b_squared.isqrt();
+        Step{ a, b_squared, b: if root * root == b_squared {Some(root)} else {None} }
+    })
+}
+
+// p, q and the number of steps it took, within budget steps. None for even
+// n, where a^2 - n is never a square, and when the only square found gives
+// the factors 1 and n.
+pub fn factor(n:u64, budget:u64) -> Option<(u64, u64, u64)>{
+    if n.is_multiple_of(2){
+        return None;
+    }
+    for (index, step) in steps(n).take(budget as usize).enumerate(){
+        if let Some(b) = step.b{
+            let (p, q) = ((step.a - b) as u64, (step.a + b) as u64);
+            return if p > 1 {Some((p, q, index as u64 + 1))} else {None};
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+#[test]
+fn test_fermat_factor(){
+    assert_eq!(factor(4294967291u64 * 4294967279, 10), Some((4294967279, 4294967291, 1)));
+    // find_prime from 4294000000, 109 steps apart.
+    assert_eq!(factor(4293999991u64 * 4295934809, 108), None);
+    assert_eq!(factor(4293999991u64 * 4295934809, 109), Some((4293999991, 4295934809, 109)));
+    assert_eq!(factor(50000000021u64 * 368934871, 1000), None);
+    assert_eq!(factor(4294967291u64 * 4294967291, 1), Some((4294967291, 4294967291, 1)));
+    assert_eq!(factor(1000, 10), None);
+    // 7 = 4^2 - 3^2 only as 1 * 7.
+    assert_eq!(factor(7, 10), None);
+
+    let first = steps(21).next().unwrap();
+    assert_eq!(first, Step{ a: 5, b_squared: 4, b: Some(2) });
+    assert_eq!(steps(15).next().unwrap(), Step{ a: 4, b_squared: 1, b: Some(1) });
+    assert_eq!(steps(35).next().unwrap(), Step{ a: 6, b_squared: 1, b: Some(1) });
+    assert_eq!(steps(33).next().unwrap(), Step{ a: 6, b_squared: 3, b: None });
+}
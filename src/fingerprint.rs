@@ -0,0 +1,164 @@
+// Key fingerprints, the SHA-256 digest of the public key in the SSH wire
+// format (RFC 4253 section 6.6) so they match what ssh-keygen -lf shows, and
+// the ways of showing them: hex, base64, a short ID and OpenSSH's randomart.
+
+use crate::base64;
+use crate::keys::RSAPublicKey;
+use crate::sha256::{self, DIGEST_LENGTH};
+use crate::ssh;
+use std::fmt;
+
+// Size of the randomart field, as in OpenSSH.
+const FIELD_WIDTH: usize = 17;
+const FIELD_HEIGHT: usize = 9;
+
+// Characters for how often the bishop visited a square, the last two mark
+// where it started and ended.
+const ART_SYMBOLS: &[u8] = b" .o+=*BOX@%&#/^SE";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint{
+    digest:[u8; DIGEST_LENGTH],
+}
+
+impl Fingerprint{
+    pub fn of(key:&RSAPublicKey) -> Fingerprint{
+        Fingerprint::of_blob(&ssh::encode_public_key_blob(key))
+    }
+
+    // Fingerprint of an already encoded key of any type or size.
+    pub fn of_blob(blob:&[u8]) -> Fingerprint{
+        Fingerprint{ digest: sha256::digest(blob) }
+    }
+
+    pub fn as_bytes(&self) -> &[u8; DIGEST_LENGTH]{
+        &self.digest
+    }
+
+    // Lower case hex, 64 characters.
+    pub fn to_hex(&self) -> String{
+        sha256::to_hex(&self.digest)
+    }
+
+    // "SHA256:" and the unpadded base64 digest, as OpenSSH shows it.
+    pub fn to_base64(&self) -> String{
+        format!("SHA256:{}", base64::encode_unpadded(&self.digest))
+    }
+
+    // First 8 bytes as upper case hex in groups of four, short enough to read
+    // out and compare by eye, like "E336 2992 DCC3 9169".
+    pub fn short_id(&self) -> String{
+        let groups:Vec<String> = self.digest[..8].chunks(2).map(|g| format!("{:02X}{:02X}", g[0], g[1])).collect();
+        groups.join(" ")
+    }
+
+    // OpenSSH's "drunken bishop" picture (ssh-keygen -lv). The bishop starts in
+    // the middle of the field and each byte, two bits at a time from the lowest,
+    // moves it one square diagonally, bit 0 picks left or right and bit 1 up or
+    // down. Each square shows how often it was visited. The key type and size
+    // go in the top border.
+    pub fn randomart(&self, key_type:&str, bits:u32) -> String{
+        let mut field = [[0usize; FIELD_WIDTH]; FIELD_HEIGHT];
+        let most = ART_SYMBOLS.len() - 1;
+        let mut x = FIELD_WIDTH / 2;
+        let mut y = FIELD_HEIGHT / 2;
+
+        for byte in self.digest{
+            let mut input = byte;
+            for _ in 0..4{
+                x = if input & 0x1 != 0 {(x + 1).min(FIELD_WIDTH - 1)} else {x.saturating_sub(1)};
+                y = if input & 0x2 != 0 {(y + 1).min(FIELD_HEIGHT - 1)} else {y.saturating_sub(1)};
+                // The two highest symbols are kept for the start and end.
+                if field[y][x] < most - 2{
+                    field[y][x] += 1;
+                }
+                input >>= 2;
+            }
+        }
+        field[FIELD_HEIGHT / 2][FIELD_WIDTH / 2] = most - 1;
+        field[y][x] = most;
+
+        // A title too long for the border loses the size, as OpenSSH does.
+        let mut title = format!("[{} {}]", key_type, bits);
+        if title.chars().count() > FIELD_WIDTH{
+            title = format!("[{}]", key_type);
+        }
+        let mut result = border(&title);
+        for row in field{
+            result.push('|');
+            result.extend(row.iter().map(|v| ART_SYMBOLS[*v] as char));
+            result.push_str("|\n");
+        }
+        result.push_str(&border("[SHA256]"));
+        result
+    }
+}
+
+// Shown the same way as OpenSSH, "SHA256:" and the base64 digest.
+impl fmt::Display for Fingerprint{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "{}", self.to_base64())
+    }
+}
+
+// "+---[text]----+" with the text centred, rounding left.
+fn border(text:&str) -> String{
+    let text:String = text.chars().take(FIELD_WIDTH).collect();
+    let width = text.chars().count();
+    let left = (FIELD_WIDTH - width) / 2;
+    let right = FIELD_WIDTH - width - left;
+    format!("+{}{}{}+\n", "-".repeat(left), text, "-".repeat(right))
+}
+
+#[cfg(test)]
+use crate::keys::test_key;
+
+#[test]
+fn test_fingerprint_renderings(){
+    let fingerprint = Fingerprint::of(&test_key().public_key());
+    assert_eq!(fingerprint.to_base64(), "SHA256:4zYpktzDkWm2qrmfDAX6cg2E5sBUihWCvHDT1clJi3k");
+    assert_eq!(fingerprint.to_string(), fingerprint.to_base64());
+    assert_eq!(fingerprint.to_hex(), "e3362992dcc39169b6aab99f0c05fa720d84e6c0548a1582bc70d3d5c9498b79");
+    assert_eq!(fingerprint.short_id(), "E336 2992 DCC3 9169");
+    assert_ne!(Fingerprint::of(&RSAPublicKey::new(test_key().get_n(), 65537)), fingerprint);
+}
+
+#[test]
+fn test_fingerprint_randomart(){
+    // Key and picture from ssh-keygen -lv -f fixtures/ssh_rsa_1024.pub.
+    let text = include_str!("../fixtures/ssh_rsa_1024.pub");
+    let blob = base64::decode(text.split(' ').nth(1).unwrap()).unwrap();
+    let fingerprint = Fingerprint::of_blob(&blob);
+    assert_eq!(fingerprint.to_base64(), "SHA256:tfTHsNhXZBNNdmPPy9dSAHBreaWJxPAWlrPTBZgqTlA");
+    let expected = "\
++---[RSA 1024]----+
+|       .E o+*=oBX|
+|      .    =*=.XB|
+|       .  o.O++.=|
+|        oo.Bo=ooo|
+|       oS.o +.=oo|
+|        .    o ..|
+|                 |
+|                 |
+|                 |
++----[SHA256]-----+
+";
+    assert_eq!(fingerprint.randomart("RSA", 1024), expected);
+}
+
+#[test]
+fn test_fingerprint_randomart_title(){
+    let art = Fingerprint::of(&test_key().public_key()).randomart("RSA", 64);
+    assert!(art.starts_with("+----[RSA 64]-----+\n"));
+    assert_eq!(art.lines().count(), FIELD_HEIGHT + 2);
+    assert!(art.lines().all(|l| l.len() == FIELD_WIDTH + 2));
+    // The start is always the centre square, unless the walk ended there too.
+    let middle = art.lines().nth(1 + FIELD_HEIGHT / 2).unwrap();
+    assert!(matches!(middle.as_bytes()[1 + FIELD_WIDTH / 2], b'S' | b'E'));
+    let art = Fingerprint::of(&test_key().public_key()).randomart("ED25519-SK-CERT", 256);
+    assert!(art.starts_with("+[ED25519-SK-CERT]+\n"));
+    // The border counts characters, not bytes.
+    let art = Fingerprint::of(&test_key().public_key()).randomart("ÉÉÉÉÉÉÉÉÉÉ", 1);
+    assert!(art.starts_with("+-[ÉÉÉÉÉÉÉÉÉÉ 1]--+\n"));
+    assert!(art.lines().all(|l| l.chars().count() == FIELD_WIDTH + 2));
+}
@@ -0,0 +1,300 @@
+// Key generation following FIPS 186-5, the way a certified module makes RSA
+// keys, scaled down to the tutorial's 64 bit modulus (nlen = 64).
+//
+// The tutorial's own primes can be any size as long as their product fits,
+// one is picked near the user's number and the other is whatever is left.
+// Very different sizes make the small prime easy to find, and very close
+// ones let Fermat's method find both. FIPS 186-5 instead picks p and q at
+// random from the same range, [sqrt(2) * 2^(nlen/2 - 1), 2^(nlen/2) - 1], so
+// both have nlen/2 bits and n has exactly nlen, and asks that
+// |p - q| > 2^(nlen/2 - 100) and 2^(nlen/2) < d < lambda(n). Candidates are
+// tested with its number of Miller-Rabin rounds, and after 5 * nlen/2 failed
+// candidates for a prime the generation gives up.
+//
+// The bounds are written for keys of 2048 bits and more. At 64 bits
+// 2^(nlen/2 - 100) is below 1, so any two different primes meet it, and the
+// round counts are taken from the smallest size the table covers. The
+// primes are also confirmed with fixed bases, which is exact at 32 bits.
+
+use crate::keys::{KeyError, RSAPrivateKey};
+use crate::multiprime;
+use crate::random;
+use crate::utility;
+use std::fmt;
+
+// Bits in n, the most a u64 holds.
+pub const NLEN:u32 = 64;
+
+// sqrt(2) * 2^31 rounded up, the smallest prime allowed, so that n has 64 bits.
+pub const MIN_PRIME:u64 = 3037000500;
+
+// Miller-Rabin with these bases finds every composite below 4,759,123,141
+// (Jaeschke, 1993), which covers every 32 bit candidate.
+const CONFIRM_BASES: &[u64] = &[2, 7, 61];
+
+// Errors returned when a key can't be generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FipsError{
+    // e is even or outside 2^16 < e < 2^256.
+    InvalidExponent(u64),
+    // The operating system random generator couldn't be read.
+    Random(String),
+    // 5 * nlen/2 candidates in a row weren't usable, names the prime.
+    TooManyCandidates(&'static str),
+    // The key made from p and q failed its own checks.
+    Key(KeyError),
+}
+
+impl fmt::Display for FipsError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            FipsError::InvalidExponent(e) => write!(f, "FIPS 186-5 needs an odd e with 2^16 < e < 2^256, not {}", e),
+            FipsError::Random(s) => write!(f, "unable to get random numbers: {}", s),
+            FipsError::TooManyCandidates(s) => write!(f, "no prime {} found in {} candidates", s, 5 * NLEN / 2),
+            FipsError::Key(e) => write!(f, "generated key is not consistent: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FipsError{}
+
+impl From<KeyError> for FipsError{
+    fn from(e:KeyError) -> FipsError{
+        FipsError::Key(e)
+    }
+}
+
+impl From<std::io::Error> for FipsError{
+    fn from(e:std::io::Error) -> FipsError{
+        FipsError::Random(e.to_string())
+    }
+}
+
+// One constraint and the values showing the key meets it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check{
+    pub constraint:String,
+    pub result:String,
+}
+
+// What was checked while making a key, and how much searching it took.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FipsReport{
+    pub nlen:u32,
+    pub rounds:u32,
+    // Candidates in the prime range tested for p and q, over all attempts.
+    pub candidates:u32,
+    // Times p and q were thrown away because d was too small.
+    pub restarts:u32,
+    pub checks:Vec<Check>,
+}
+
+impl fmt::Display for FipsReport{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        writeln!(f, "FIPS 186-5 key generation, nlen = {} bits", self.nlen)?;
+        writeln!(f, "{} prime candidates tested, {} restarts for a small d", self.candidates, self.restarts)?;
+        for check in &self.checks{
+            writeln!(f, "  checked {}: {}", check.constraint, check.result)?;
+        }
+        Ok(())
+    }
+}
+
+// Miller-Rabin rounds with no Lucas test that FIPS 186-5 asks for when p and
+// q are nlen/2 bits, 5 up to nlen = 2048 and 4 above. Its table starts at
+// 2048, smaller keys get the first row.
+pub fn miller_rabin_rounds(nlen:u32) -> u32{
+    if nlen <= 2048 {5} else {4}
+}
+
+// Makes a key with the given e, d worked out modulo lambda(n) as FIPS 186-5
+// asks, and a report of the constraints it meets.
+pub fn generate(e:u64) -> Result<(RSAPrivateKey, FipsReport), FipsError>{
+    if e <= 1 << 16 || e.is_multiple_of(2){
+        return Err(FipsError::InvalidExponent(e));
+    }
+    let rounds = miller_rabin_rounds(NLEN);
+    let mut candidates = 0u32;
+    let mut restarts = 0u32;
+    loop{
+        let p = find_prime(e, rounds, None, &mut candidates, "p")?;
+        let q = find_prime(e, rounds, Some(p), &mut candidates, "q")?;
+        let lambda = multiprime::carmichael(&[p, q]);
+        // gcd(p - 1, e) = gcd(q - 1, e) = 1, so this only fails if find_prime is wrong.
+        let d = match utility::mod_inverse(e, lambda){
+            Some(s) => s,
+            None => return Err(FipsError::Key(KeyError::Inconsistent("e has no inverse modulo lambda(n)"))),
+        };
+        if d <= 1 << (NLEN / 2){
+            restarts += 1;
+            continue;
+        }
+        let key = RSAPrivateKey::from_parts(p, q, e, d)?;
+        key.validate()?;
+        let report = FipsReport{ nlen: NLEN, rounds, candidates, restarts, checks: checks(&key, lambda, rounds) };
+        return Ok((key, report));
+    }
+}
+
+// Random odd candidates of nlen/2 bits, those below MIN_PRIME are drawn again
+// without counting. A candidate is usable when gcd(r - 1, e) = 1, it is far
+// enough from the other prime and it passes the Miller-Rabin rounds, then
+// the fixed bases.
+fn find_prime(e:u64, rounds:u32, other:Option<u64>, candidates:&mut u32, name:&'static str) -> Result<u64, FipsError>{
+    let mut tried = 0;
+    while tried < 5 * NLEN / 2{
+        let candidate = random_below(1 << (NLEN / 2))? | 1;
+        if candidate < MIN_PRIME{
+            continue;
+        }
+        tried += 1;
+        *candidates += 1;
+        if let Some(p) = other{
+            // |p - q| > 2^(nlen/2 - 100), which at 64 bits only rules out p itself.
+            if candidate == p{
+                continue;
+            }
+        }
+        if utility::gcd(candidate - 1, e) != 1{
+            continue;
+        }
+        let mut bases:Vec<u64> = Vec::with_capacity(rounds as usize);
+        for _ in 0..rounds{
+            bases.push(2 + random_below(candidate - 3)?);
+        }
+        if miller_rabin(candidate, &bases) && miller_rabin(candidate, CONFIRM_BASES){
+            return Ok(candidate);
+        }
+    }
+    Err(FipsError::TooManyCandidates(name))
+}
+
+// Works out the report from the finished key, so every line is a check of
+// the key itself rather than a record of the search.
+fn checks(key:&RSAPrivateKey, lambda:u64, rounds:u32) -> Vec<Check>{
+    let (p, q, e, d) = (key.get_p(), key.get_q(), key.get_e(), key.get_d());
+    let bits = |x:u64| 64 - x.leading_zeros();
+    let check = |constraint:String, result:String| Check{ constraint, result };
+    vec![
+        check(String::from("e is odd and 2^16 < e < 2^256"), format!("e = {}", e)),
+        check(format!("p and q have nlen/2 = {} bits and are at least sqrt(2) * 2^{}", NLEN / 2, NLEN / 2 - 1),
+            format!("p = {} ({} bits), q = {} ({} bits)", p, bits(p), q, bits(q))),
+        check(format!("n has nlen = {} bits", NLEN), format!("n = {} ({} bits)", key.get_n(), bits(key.get_n()))),
+        check(String::from("gcd(p - 1, e) = gcd(q - 1, e) = 1"), format!("{} and {}", utility::gcd(p - 1, e), utility::gcd(q - 1, e))),
+        check(format!("|p - q| > 2^(nlen/2 - 100) = 2^{}", NLEN as i32 / 2 - 100), format!("|p - q| = {}", p.abs_diff(q))),
+        check(format!("p and q pass {} rounds of Miller-Rabin with random bases", rounds),
+            format!("p {} and q {} with bases {:?}, exact below 2^32", prime_word(p), prime_word(q), CONFIRM_BASES)),
+        check(format!("2^{} < d < lambda(n)", NLEN / 2), format!("d = {}, lambda(n) = {}", d, lambda)),
+        check(String::from("e * d = 1 mod lambda(n)"), format!("e * d mod lambda(n) = {}", (e as u128 * d as u128) % lambda as u128)),
+    ]
+}
+
+// Exact primality test for any u64, Miller-Rabin with the first twelve
+// primes as bases finds every composite below 3.3 * 10^24.
+pub fn is_prime(w:u64) -> bool{
+    const BASES: &[u64] = &[2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if w < 2{
+        return false;
+    }
+    if let Some(base) = BASES.iter().find(|b| w.is_multiple_of(**b)){
+        return w == *base;
+    }
+    miller_rabin(w, BASES)
+}
+
+fn prime_word(r:u64) -> &'static str{
+    if miller_rabin(r, CONFIRM_BASES) {"prime"} else {"composite"}
+}
+
+// Miller-Rabin test of an odd w > 3 with each of the bases. A composite
+// passes a random base at most a quarter of the time.
+pub fn miller_rabin(w:u64, bases:&[u64]) -> bool{
+    let mut m = w - 1;
+    let mut a = 0;
+    while m.is_multiple_of(2){
+        m /= 2;
+        a += 1;
+    }
+    'bases: for base in bases{
+        let mut z = crate::modded_exponent(*base % w, m, w);
+        if z == 1 || z == w - 1 || *base % w == 0{
+            continue;
+        }
+        for _ in 1..a{
+            z = crate::modded_exponent(z, 2, w);
+            if z == w - 1{
+                continue 'bases;
+            }
+            if z == 1{
+                return false;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+// Random number below the limit, from 8 random bytes so the bias is tiny.
+fn random_below(limit:u64) -> Result<u64, FipsError>{
+    let mut bytes = [0u8; 8];
+    random::fill_random(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes) % limit)
+}
+
+#[cfg(test)]
+#[test]
+fn test_fips_generate(){
+    let (key, report) = generate(65537).unwrap();
+    let (p, q) = (key.get_p(), key.get_q());
+    assert!((MIN_PRIME..1 << 32).contains(&p) && (MIN_PRIME..1 << 32).contains(&q));
+    assert_ne!(p, q);
+    assert!(key.get_n() >= 1 << 63);
+    assert!(miller_rabin(p, CONFIRM_BASES) && miller_rabin(q, CONFIRM_BASES));
+    let lambda = multiprime::carmichael(&[p, q]);
+    assert!(key.get_d() > 1 << 32 && key.get_d() < lambda);
+    assert_eq!(crate::keys::check_public_exponent(key.get_e()), Ok(()));
+
+    assert_eq!(report.nlen, 64);
+    assert_eq!(report.rounds, 5);
+    assert!(report.candidates >= 2);
+    assert_eq!(report.checks.len(), 8);
+    let text = report.to_string();
+    assert!(text.starts_with("FIPS 186-5 key generation, nlen = 64 bits\n"));
+    assert!(text.contains("checked |p - q| > 2^(nlen/2 - 100) = 2^-68: "));
+    assert!(text.contains("e * d mod lambda(n) = 1\n"));
+
+    let message = b"standard primes";
+    let encrypted = crate::encrypt_message(&key.public_key(), message).unwrap();
+    assert_eq!(crate::decrypt_message(&key, &encrypted), Some(message.to_vec()));
+}
+
+#[test]
+fn test_fips_exponents(){
+    for e in [3u64, 65535, 65536, 65538]{
+        assert_eq!(generate(e).map(|_| ()), Err(FipsError::InvalidExponent(e)));
+    }
+    assert_eq!(generate(65539).unwrap().0.get_e(), 65539);
+    assert_eq!(miller_rabin_rounds(64), 5);
+    assert_eq!(miller_rabin_rounds(3072), 4);
+    assert_eq!(FipsError::from(KeyError::Inconsistent("p is not prime")).to_string(), "generated key is not consistent: inconsistent key: p is not prime");
+}
+
+#[test]
+fn test_fips_miller_rabin(){
+    // 3215031751 = 151 * 751 * 28351 passes bases 2, 3, 5 and 7 but not 61.
+    assert!(miller_rabin(3215031751, &[2, 3, 5, 7]));
+    assert!(!miller_rabin(3215031751, CONFIRM_BASES));
+    assert!(miller_rabin(4294967291, CONFIRM_BASES));
+    assert!(!miller_rabin(4294967291 * 3, &[2]));
+    for w in (5u64..2000).step_by(2){
+        let prime = (3..w).step_by(2).take_while(|i| i * i <= w).all(|i| w % i != 0);
+        assert_eq!(miller_rabin(w, CONFIRM_BASES), prime, "{}", w);
+        assert_eq!(is_prime(w), prime, "{}", w);
+    }
+
+    assert!(!is_prime(0) && !is_prime(1) && is_prime(2) && is_prime(3) && !is_prime(4));
+    assert!(is_prime(50000000021) && is_prime(18446744073709551557));
+    // 3825123056546413051 = 149491 * 747451 * 34233211 passes bases 2 to 23.
+    assert!(miller_rabin(3825123056546413051, &[2, 3, 5, 7, 11, 13, 17, 19, 23]));
+    assert!(!is_prime(3825123056546413051));
+}
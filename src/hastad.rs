@@ -0,0 +1,89 @@
+// Håstad's broadcast attack. When the same message m is encrypted with e
+// keys that all use the same small e, the attacker has m^e mod n1, m^e mod
+// n2 and so on. The Chinese remainder theorem joins them into m^e mod
+// n1 * n2 * ..., and as m is below every n, m^e is below the product, so that
+// is m^e itself and an ordinary e-th root gives m. No private key is needed.
+//
+// Real padding puts fresh random bytes in every encryption, so each key is
+// sent a different number. pad_the_bytes only adds a length byte, so the
+// blocks are the same whoever the message is for.
+
+use crate::biguint::BigUint;
+use crate::utility;
+
+// The x with x = residue mod modulus for each pair, below the product of the
+// moduli, or None if a modulus is below 2 or two moduli share a factor. Each
+// residue is multiplied by the product of the other moduli and by that
+// product's inverse mod its own modulus, which is 1 mod its own modulus and 0
+// mod the others, and the results are added up.
+pub fn crt(residues:&[u64], moduli:&[u64]) -> Option<BigUint>{
+    if moduli.iter().any(|n| *n < 2){
+        return None;
+    }
+    let product = moduli.iter().fold(BigUint::from_u64(1), |p, n| p.mul(&BigUint::from_u64(*n)));
+    let mut sum = BigUint::zero();
+    for (residue, modulus) in residues.iter().zip(moduli){
+        let (others, remainder) = product.div_rem_u64(*modulus);
+        if remainder != 0{
+            return None;
+        }
+        let (_, others_mod) = others.div_rem_u64(*modulus);
+        let inverse = utility::mod_inverse(others_mod, *modulus)?;
+        let coefficient = ((*residue as u128 * inverse as u128) % *modulus as u128) as u64;
+        sum = sum.add(&others.mul(&BigUint::from_u64(coefficient)));
+    }
+    Some(sum.rem(&product))
+}
+
+// The whole number r with r^k = value, if there is one below 2^64, found by
+// halving the range r can be in.
+pub fn root(value:&BigUint, k:u32) -> Option<u64>{
+    let power = |r:u64| (0..k).fold(BigUint::from_u64(1), |p, _| p.mul(&BigUint::from_u64(r)));
+    let (mut low, mut high) = (0u64, u64::MAX);
+    while low < high{
+        let middle = low + (high - low) / 2 + 1;
+        if power(middle) <= *value{
+            low = middle;
+        }else{
+            high = middle - 1;
+        }
+    }
+    if power(low) == *value {Some(low)} else {None}
+}
+
+// The message block from its encryptions under e keys with the same e, one
+// cypher per modulus. None when there are fewer than e keys, the moduli
+// share a factor or the result isn't an e-th power.
+pub fn attack(cyphers:&[u64], moduli:&[u64], e:u64) -> Option<u64>{
+    if cyphers.len() != moduli.len() || (moduli.len() as u64) < e || e > u32::MAX as u64{
+        return None;
+    }
+    root(&crt(cyphers, moduli)?, e as u32)
+}
+
+#[cfg(test)]
+#[test]
+fn test_crt_and_root(){
+    assert_eq!(crt(&[2, 3, 2], &[3, 5, 7]), Some(BigUint::from_u64(23)));
+    assert_eq!(crt(&[1, 2], &[6, 9]), None);
+    assert_eq!(crt(&[0, 1], &[0, 5]), None);
+    assert_eq!(crt(&[0, 1], &[1, 5]), None);
+    let cube = BigUint::from_u64(u64::MAX).mul(&BigUint::from_u64(u64::MAX)).mul(&BigUint::from_u64(u64::MAX));
+    assert_eq!(root(&cube, 3), Some(u64::MAX));
+    assert_eq!(root(&BigUint::from_u64(27), 3), Some(3));
+    assert_eq!(root(&BigUint::from_u64(28), 3), None);
+    assert_eq!(root(&BigUint::zero(), 3), Some(0));
+}
+
+#[test]
+fn test_hastad_attack(){
+    let moduli = [4294967291u64 * 4294967279, 50000000021 * 368934871, 3999999979 * 4611686041];
+    // A padded block, 7 message bytes and the length header.
+    let m = 0x0765_6c6c_6f48_6c6cu64;
+    let cyphers:Vec<u64> = moduli.iter().map(|n| crate::modded_exponent(m, 3, *n)).collect();
+    assert_eq!(attack(&cyphers, &moduli, 3), Some(m));
+    // With two keys m^3 is larger than their product and the CRT only gives it mod the product.
+    assert_eq!(root(&crt(&cyphers[..2], &moduli[..2]).unwrap(), 3), None);
+    assert_eq!(attack(&cyphers[..2], &moduli[..2], 3), None);
+    assert_eq!(attack(&cyphers, &moduli, 5), None);
+}
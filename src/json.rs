@@ -0,0 +1,387 @@
+// Minimal JSON (RFC 8259) reader and writer, enough for JSON Web Keys.
+// Numbers are kept as their original text since keys never do arithmetic on them,
+// and object members keep their order.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonValue{
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+// Error with the byte position in the text where parsing stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError{
+    pub position:usize,
+    pub message:&'static str,
+}
+
+impl fmt::Display for JsonError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "invalid JSON at byte {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for JsonError{}
+
+impl JsonValue{
+    // Looks up a member of an object, None for missing members or other values.
+    pub fn get(&self, name:&str) -> Option<&JsonValue>{
+        match self{
+            JsonValue::Object(members) => members.iter().find(|(k, _)| k == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str>{
+        match self{
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]>{
+        match self{
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    // Compact JSON text with no whitespace, members in their stored order.
+    pub fn to_json(&self) -> String{
+        let mut out = String::new();
+        self.write(&mut out);
+        out
+    }
+
+    fn write(&self, out:&mut String){
+        match self{
+            JsonValue::Null => out.push_str("null"),
+            JsonValue::Bool(b) => out.push_str(if *b {"true"} else {"false"}),
+            JsonValue::Number(n) => out.push_str(n),
+            JsonValue::String(s) => write_string(out, s),
+            JsonValue::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate(){
+                    if i > 0{
+                        out.push(',');
+                    }
+                    item.write(out);
+                }
+                out.push(']');
+            },
+            JsonValue::Object(members) => {
+                out.push('{');
+                for (i, (name, value)) in members.iter().enumerate(){
+                    if i > 0{
+                        out.push(',');
+                    }
+                    write_string(out, name);
+                    out.push(':');
+                    value.write(out);
+                }
+                out.push('}');
+            },
+        }
+    }
+}
+
+fn write_string(out:&mut String, s:&str){
+    out.push('"');
+    for c in s.chars(){
+        match c{
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// Parses a complete JSON document, nothing but whitespace may follow the value.
+pub fn parse(text:&str) -> Result<JsonValue, JsonError>{
+    let mut parser = Parser{ data: text.as_bytes(), position: 0, depth: 0 };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.position != parser.data.len(){
+        return Err(parser.error("unexpected data after the value"));
+    }
+    Ok(value)
+}
+
+// Nesting limit so hostile input can't overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+struct Parser<'a>{
+    data:&'a [u8],
+    position:usize,
+    depth:usize,
+}
+
+impl<'a> Parser<'a>{
+    fn error(&self, message:&'static str) -> JsonError{
+        JsonError{ position: self.position, message }
+    }
+
+    fn skip_whitespace(&mut self){
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.data.get(self.position){
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, word:&'static str) -> Result<(), JsonError>{
+        if self.data[self.position..].starts_with(word.as_bytes()){
+            self.position += word.len();
+            Ok(())
+        }else{
+            Err(self.error("unknown literal"))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, JsonError>{
+        self.skip_whitespace();
+        match self.data.get(self.position){
+            Some(b'n') => self.expect("null").map(|_| JsonValue::Null),
+            Some(b't') => self.expect("true").map(|_| JsonValue::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| JsonValue::Bool(false)),
+            Some(b'"') => self.parse_string().map(JsonValue::String),
+            Some(b'[') => self.parse_array(),
+            Some(b'{') => self.parse_object(),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of text")),
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), JsonError>{
+        self.depth += 1;
+        if self.depth > MAX_DEPTH{
+            return Err(self.error("nested too deeply"));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue, JsonError>{
+        self.enter()?;
+        let mut items:Vec<JsonValue> = Vec::new();
+        self.skip_whitespace();
+        if self.data.get(self.position) == Some(&b']'){
+            self.position += 1;
+            self.depth -= 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop{
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.data.get(self.position){
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    self.depth -= 1;
+                    return Ok(JsonValue::Array(items));
+                },
+                _ => return Err(self.error("expected , or ]")),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue, JsonError>{
+        self.enter()?;
+        let mut members:Vec<(String, JsonValue)> = Vec::new();
+        self.skip_whitespace();
+        if self.data.get(self.position) == Some(&b'}'){
+            self.position += 1;
+            self.depth -= 1;
+            return Ok(JsonValue::Object(members));
+        }
+        loop{
+            self.skip_whitespace();
+            if self.data.get(self.position) != Some(&b'"'){
+                return Err(self.error("expected a member name"));
+            }
+            let name = self.parse_string()?;
+            if members.iter().any(|(k, _)| *k == name){
+                return Err(self.error("duplicate member name"));
+            }
+            self.skip_whitespace();
+            if self.data.get(self.position) != Some(&b':'){
+                return Err(self.error("expected :"));
+            }
+            self.position += 1;
+            let value = self.parse_value()?;
+            members.push((name, value));
+            self.skip_whitespace();
+            match self.data.get(self.position){
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    self.depth -= 1;
+                    return Ok(JsonValue::Object(members));
+                },
+                _ => return Err(self.error("expected , or }")),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, JsonError>{
+        let start = self.position;
+        if self.data.get(self.position) == Some(&b'-'){
+            self.position += 1;
+        }
+        // No leading zeros, a lone 0 is fine.
+        match self.data.get(self.position){
+            Some(b'0') => self.position += 1,
+            Some(b'1'..=b'9') => self.skip_digits(),
+            _ => return Err(self.error("expected a digit")),
+        }
+        if self.data.get(self.position) == Some(&b'.'){
+            self.position += 1;
+            if !matches!(self.data.get(self.position), Some(b'0'..=b'9')){
+                return Err(self.error("expected a digit"));
+            }
+            self.skip_digits();
+        }
+        if let Some(b'e' | b'E') = self.data.get(self.position){
+            self.position += 1;
+            if let Some(b'+' | b'-') = self.data.get(self.position){
+                self.position += 1;
+            }
+            if !matches!(self.data.get(self.position), Some(b'0'..=b'9')){
+                return Err(self.error("expected a digit"));
+            }
+            self.skip_digits();
+        }
+        // Only ascii was consumed, so this is always valid utf8.
+        Ok(JsonValue::Number(String::from_utf8_lossy(&self.data[start..self.position]).to_string()))
+    }
+
+    fn skip_digits(&mut self){
+        while let Some(b'0'..=b'9') = self.data.get(self.position){
+            self.position += 1;
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError>{
+        // Skip the opening quote.
+        self.position += 1;
+        let mut result:Vec<u8> = Vec::new();
+        loop{
+            let b = match self.data.get(self.position){
+                Some(b) => *b,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.position += 1;
+            match b{
+                b'"' => break,
+                b'\\' => {
+                    let escape = match self.data.get(self.position){
+                        Some(e) => *e,
+                        None => return Err(self.error("unterminated string")),
+                    };
+                    self.position += 1;
+                    match escape{
+                        b'"' => result.push(b'"'),
+                        b'\\' => result.push(b'\\'),
+                        b'/' => result.push(b'/'),
+                        b'b' => result.push(0x08),
+                        b'f' => result.push(0x0c),
+                        b'n' => result.push(b'\n'),
+                        b'r' => result.push(b'\r'),
+                        b't' => result.push(b'\t'),
+                        b'u' => {
+                            let c = self.parse_unicode_escape()?;
+                            let mut buffer = [0u8; 4];
+                            result.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                        },
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                },
+                b if b < 0x20 => return Err(self.error("control character in string")),
+                b => result.push(b),
+            }
+        }
+        // The input was a &str and escapes add valid utf8, so this can't fail.
+        String::from_utf8(result).map_err(|_| self.error("invalid utf8"))
+    }
+
+    // Reads the hex digits after \u, joining surrogate pairs.
+    fn parse_unicode_escape(&mut self) -> Result<char, JsonError>{
+        let first = self.parse_hex4()?;
+        let code = if (0xd800..0xdc00).contains(&first){
+            if !self.data[self.position..].starts_with(b"\\u"){
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.position += 2;
+            let second = self.parse_hex4()?;
+            if !(0xdc00..0xe000).contains(&second){
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00)
+        }else{
+            first
+        };
+        char::from_u32(code).ok_or_else(|| self.error("unpaired surrogate"))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, JsonError>{
+        let mut value = 0u32;
+        for _ in 0..4{
+            let digit = match self.data.get(self.position).and_then(|b| (*b as char).to_digit(16)){
+                Some(d) => d,
+                None => return Err(self.error("invalid unicode escape")),
+            };
+            value = (value << 4) | digit;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_json_parse(){
+    let text = r#" { "kty" : "RSA", "n": [1, -2.5e3, 0], "ok": true, "no": false, "none": null, "s": "a\"b\\c\u00e9\ud83d\ude00" } "#;
+    let value = parse(text).unwrap();
+    assert_eq!(value.get("kty").and_then(|v| v.as_str()), Some("RSA"));
+    assert_eq!(value.get("n").and_then(|v| v.as_array()).map(|a| a.len()), Some(3));
+    assert_eq!(value.get("n").unwrap().as_array().unwrap()[1], JsonValue::Number(String::from("-2.5e3")));
+    assert_eq!(value.get("ok"), Some(&JsonValue::Bool(true)));
+    assert_eq!(value.get("none"), Some(&JsonValue::Null));
+    assert_eq!(value.get("s").and_then(|v| v.as_str()), Some("a\"b\\c\u{e9}\u{1f600}"));
+    assert_eq!(value.get("missing"), None);
+}
+
+#[test]
+fn test_json_write(){
+    let value = JsonValue::Object(vec![
+        (String::from("e"), JsonValue::String(String::from("AQAB"))),
+        (String::from("list"), JsonValue::Array(vec![JsonValue::Number(String::from("1")), JsonValue::Null])),
+        (String::from("text"), JsonValue::String(String::from("line\n\"quoted\"\u{1}"))),
+    ]);
+    let text = value.to_json();
+    assert_eq!(text, r#"{"e":"AQAB","list":[1,null],"text":"line\n\"quoted\"\u0001"}"#);
+    assert_eq!(parse(&text), Ok(value));
+}
+
+#[test]
+fn test_json_errors(){
+    assert_eq!(parse("").unwrap_err().message, "unexpected end of text");
+    assert_eq!(parse("{\"a\":1,}").unwrap_err().message, "expected a member name");
+    assert_eq!(parse("[1 2]").unwrap_err().message, "expected , or ]");
+    assert_eq!(parse("01").unwrap_err().message, "unexpected data after the value");
+    assert_eq!(parse("\"abc").unwrap_err().message, "unterminated string");
+    assert_eq!(parse("\"\\ud800\"").unwrap_err().message, "unpaired surrogate");
+    assert_eq!(parse("{\"a\":1,\"a\":2}").unwrap_err().message, "duplicate member name");
+    assert_eq!(parse("tru").unwrap_err().message, "unknown literal");
+    assert_eq!(parse(&"[".repeat(100)).unwrap_err().message, "nested too deeply");
+}
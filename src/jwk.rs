@@ -0,0 +1,280 @@
+// JSON Web Keys (RFC 7517, RSA members from RFC 7518 section 6.3), JWK Sets
+// and JWK thumbprints (RFC 7638) for use as key IDs.
+
+use crate::base64::{self, Base64Error};
+use crate::json::{self, JsonError, JsonValue};
+use crate::keys::{KeyError, RSAPrivateKey, RSAPublicKey};
+use crate::sha256;
+use std::fmt;
+
+// Errors returned when a JSON document isn't a usable RSA JWK.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JwkError{
+    Json(JsonError),
+    // Document is not a JSON object.
+    NotAnObject,
+    // Required member missing, with its name.
+    MissingMember(&'static str),
+    // Member is not a base64url string, or has the wrong type.
+    InvalidMember(&'static str, Option<Base64Error>),
+    // kty other than RSA.
+    UnsupportedKeyType(String),
+    // Member the tutorial keys can't represent, like "oth" for multi prime keys.
+    UnsupportedMember(&'static str),
+    // Integer does not fit in 64 bits.
+    IntegerTooLarge(&'static str),
+    Key(KeyError),
+}
+
+impl fmt::Display for JwkError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            JwkError::Json(e) => write!(f, "{}", e),
+            JwkError::NotAnObject => write!(f, "JWK must be a JSON object"),
+            JwkError::MissingMember(m) => write!(f, "JWK is missing \"{}\"", m),
+            JwkError::InvalidMember(m, Some(e)) => write!(f, "JWK member \"{}\" is invalid: {}", m, e),
+            JwkError::InvalidMember(m, None) => write!(f, "JWK member \"{}\" is invalid", m),
+            JwkError::UnsupportedKeyType(t) => write!(f, "unsupported JWK key type {}", t),
+            JwkError::UnsupportedMember(m) => write!(f, "JWK member \"{}\" is not supported", m),
+            JwkError::IntegerTooLarge(m) => write!(f, "JWK member \"{}\" is larger than 64 bits", m),
+            JwkError::Key(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for JwkError{}
+
+impl From<JsonError> for JwkError{
+    fn from(e:JsonError) -> JwkError{
+        JwkError::Json(e)
+    }
+}
+
+impl From<KeyError> for JwkError{
+    fn from(e:KeyError) -> JwkError{
+        JwkError::Key(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JwkKey{
+    Public(RSAPublicKey),
+    Private(RSAPrivateKey),
+}
+
+// A key with its optional key ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jwk{
+    pub key:JwkKey,
+    pub kid:Option<String>,
+}
+
+impl Jwk{
+    pub fn public_key(&self) -> RSAPublicKey{
+        match &self.key{
+            JwkKey::Public(k) => *k,
+            JwkKey::Private(k) => k.public_key(),
+        }
+    }
+
+    pub fn to_json_value(&self) -> JsonValue{
+        let mut members:Vec<(String, JsonValue)> = vec![(String::from("kty"), JsonValue::String(String::from("RSA")))];
+        if let Some(kid) = &self.kid{
+            members.push((String::from("kid"), JsonValue::String(kid.clone())));
+        }
+        let public = self.public_key();
+        members.push(integer_member("n", public.get_n()));
+        members.push(integer_member("e", public.get_e()));
+        if let JwkKey::Private(k) = &self.key{
+            members.push(integer_member("d", k.get_d()));
+            members.push(integer_member("p", k.get_p()));
+            members.push(integer_member("q", k.get_q()));
+            members.push(integer_member("dp", k.get_dp()));
+            members.push(integer_member("dq", k.get_dq()));
+            members.push(integer_member("qi", k.get_qinv()));
+        }
+        JsonValue::Object(members)
+    }
+
+    pub fn to_json(&self) -> String{
+        self.to_json_value().to_json()
+    }
+
+    // Reads an RSA JWK, it is a private key when "d" is present. Private keys
+    // need the primes, the CRT members are checked when they are given.
+    pub fn from_json_value(value:&JsonValue) -> Result<Jwk, JwkError>{
+        if !matches!(value, JsonValue::Object(_)){
+            return Err(JwkError::NotAnObject);
+        }
+        match value.get("kty"){
+            Some(JsonValue::String(kty)) if kty == "RSA" => {},
+            Some(JsonValue::String(kty)) => return Err(JwkError::UnsupportedKeyType(kty.clone())),
+            Some(_) => return Err(JwkError::InvalidMember("kty", None)),
+            None => return Err(JwkError::MissingMember("kty")),
+        }
+        let kid = match value.get("kid"){
+            Some(JsonValue::String(s)) => Some(s.clone()),
+            Some(_) => return Err(JwkError::InvalidMember("kid", None)),
+            None => None,
+        };
+        let n = read_integer(value, "n")?;
+        let e = read_integer(value, "e")?;
+
+        if value.get("d").is_none(){
+            return Ok(Jwk{ key: JwkKey::Public(RSAPublicKey::new(n, e)), kid });
+        }
+        if value.get("oth").is_some(){
+            return Err(JwkError::UnsupportedMember("oth"));
+        }
+        let d = read_integer(value, "d")?;
+        let p = read_integer(value, "p")?;
+        let q = read_integer(value, "q")?;
+        let key = RSAPrivateKey::from_parts(p, q, e, d)?;
+        if key.get_n() != n{
+            return Err(JwkError::Key(KeyError::Inconsistent("modulus is not p * q")));
+        }
+        for (name, expected) in [("dp", key.get_dp()), ("dq", key.get_dq()), ("qi", key.get_qinv())]{
+            if value.get(name).is_some() && read_integer(value, name)? != expected{
+                return Err(JwkError::Key(KeyError::Inconsistent("CRT values do not match p, q and d")));
+            }
+        }
+        key.validate()?;
+        Ok(Jwk{ key: JwkKey::Private(key), kid })
+    }
+
+    pub fn from_json(text:&str) -> Result<Jwk, JwkError>{
+        Jwk::from_json_value(&json::parse(text)?)
+    }
+}
+
+// {"keys":[...]} document holding several keys.
+pub fn to_jwk_set(keys:&[Jwk]) -> String{
+    let items:Vec<JsonValue> = keys.iter().map(|k| k.to_json_value()).collect();
+    JsonValue::Object(vec![(String::from("keys"), JsonValue::Array(items))]).to_json()
+}
+
+// Reads a JWK Set. As RFC 7517 section 5 asks, keys of other types (EC, oct)
+// are skipped rather than failing the whole set.
+pub fn parse_jwk_set(text:&str) -> Result<Vec<Jwk>, JwkError>{
+    let value = json::parse(text)?;
+    let items = match value.get("keys"){
+        Some(JsonValue::Array(items)) => items,
+        Some(_) => return Err(JwkError::InvalidMember("keys", None)),
+        None => return Err(JwkError::MissingMember("keys")),
+    };
+    let mut keys:Vec<Jwk> = Vec::new();
+    for item in items{
+        match Jwk::from_json_value(item){
+            Ok(k) => keys.push(k),
+            Err(JwkError::UnsupportedKeyType(_)) => {},
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(keys)
+}
+
+// RFC 7638 thumbprint, the base64url SHA-256 of the required members in
+// lexicographic order with no whitespace. Stable for the same key, so it
+// works as a "kid".
+pub fn thumbprint(key:&RSAPublicKey) -> String{
+    thumbprint_members(&encode_integer(key.get_e()), &encode_integer(key.get_n()))
+}
+
+fn thumbprint_members(e:&str, n:&str) -> String{
+    let canonical = JsonValue::Object(vec![
+        (String::from("e"), JsonValue::String(e.to_string())),
+        (String::from("kty"), JsonValue::String(String::from("RSA"))),
+        (String::from("n"), JsonValue::String(n.to_string())),
+    ]).to_json();
+    base64::encode_url(&sha256::digest(canonical.as_bytes()))
+}
+
+// Integers are big endian base64url with no leading zero bytes, zero is one zero byte.
+fn encode_integer(value:u64) -> String{
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take(7).take_while(|b| **b == 0).count();
+    base64::encode_url(&bytes[skip..])
+}
+
+fn integer_member(name:&str, value:u64) -> (String, JsonValue){
+    (name.to_string(), JsonValue::String(encode_integer(value)))
+}
+
+fn read_integer(value:&JsonValue, name:&'static str) -> Result<u64, JwkError>{
+    let text = match value.get(name){
+        Some(JsonValue::String(s)) => s,
+        Some(_) => return Err(JwkError::InvalidMember(name, None)),
+        None => return Err(JwkError::MissingMember(name)),
+    };
+    let bytes = match base64::decode_url(text){
+        Ok(s) => s,
+        Err(e) => return Err(JwkError::InvalidMember(name, Some(e))),
+    };
+    // RFC 7518 section 6.3.1, the shortest big endian bytes, so only zero
+    // itself may start with a zero byte.
+    if bytes.is_empty() || (bytes.len() > 1 && bytes[0] == 0){
+        return Err(JwkError::InvalidMember(name, None));
+    }
+    if bytes.len() > 8{
+        return Err(JwkError::IntegerTooLarge(name));
+    }
+    Ok(bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+}
+
+#[cfg(test)]
+#[test]
+fn test_jwk_private_round_trip(){
+    let key = crate::keys::test_key();
+    let jwk = Jwk{ key: JwkKey::Private(key), kid: Some(String::from("tutorial")) };
+    let text = jwk.to_json();
+    assert_eq!(text, r#"{"kty":"RSA","kid":"tutorial","n":"____h95F6KM","e":"BYh4Fw","d":"grcPi-JVuq8","p":"C6Q7dBU","q":"Ff1_1w","dp":"CDxocP8","dq":"E3VFMQ","qi":"AUkwKQg"}"#);
+    assert_eq!(Jwk::from_json(&text), Ok(jwk));
+}
+
+#[test]
+fn test_jwk_public(){
+    let text = r#"{ "kty": "RSA", "use": "sig", "n": "____h95F6KM", "e": "AQAB" }"#;
+    let jwk = Jwk::from_json(text).unwrap();
+    assert_eq!(jwk.key, JwkKey::Public(RSAPublicKey::new(18446743557747632291, 65537)));
+    assert_eq!(jwk.kid, None);
+    assert_eq!(jwk.to_json(), r#"{"kty":"RSA","n":"____h95F6KM","e":"AQAB"}"#);
+}
+
+#[test]
+fn test_jwk_errors(){
+    assert_eq!(Jwk::from_json("[]"), Err(JwkError::NotAnObject));
+    assert_eq!(Jwk::from_json(r#"{"n":"AQAB","e":"AQAB"}"#), Err(JwkError::MissingMember("kty")));
+    assert_eq!(Jwk::from_json(r#"{"kty":"EC","crv":"P-256"}"#), Err(JwkError::UnsupportedKeyType(String::from("EC"))));
+    assert_eq!(Jwk::from_json(r#"{"kty":"RSA","e":"AQAB"}"#), Err(JwkError::MissingMember("n")));
+    assert_eq!(Jwk::from_json(r#"{"kty":"RSA","n":"AQ==","e":"AQAB"}"#), Err(JwkError::InvalidMember("n", Some(Base64Error::InvalidCharacter(2)))));
+    assert_eq!(Jwk::from_json(r#"{"kty":"RSA","n":"AQEBAQEBAQEB","e":"AQAB"}"#), Err(JwkError::IntegerTooLarge("n")));
+    // 65537 with a leading zero byte isn't the minimal encoding.
+    assert_eq!(Jwk::from_json(r#"{"kty":"RSA","n":"____h95F6KM","e":"AAEAAQ"}"#), Err(JwkError::InvalidMember("e", None)));
+    // Wrong qi for this key.
+    let text = r#"{"kty":"RSA","n":"____h95F6KM","e":"BYh4Fw","d":"grcPi-JVuq8","p":"C6Q7dBU","q":"Ff1_1w","qi":"AQ"}"#;
+    assert_eq!(Jwk::from_json(text), Err(JwkError::Key(KeyError::Inconsistent("CRT values do not match p, q and d"))));
+}
+
+#[test]
+fn test_jwk_set(){
+    let public = RSAPublicKey::new(18446743557747632291, 65537);
+    let keys = vec![Jwk{ key: JwkKey::Public(public), kid: Some(thumbprint(&public)) }];
+    let text = to_jwk_set(&keys);
+    assert_eq!(parse_jwk_set(&text), Ok(keys.clone()));
+
+    // Keys of other types are skipped.
+    let mixed = format!(r#"{{"keys":[{{"kty":"EC","crv":"P-256","x":"AA","y":"AA"}},{}]}}"#, keys[0].to_json());
+    assert_eq!(parse_jwk_set(&mixed), Ok(keys));
+    assert_eq!(parse_jwk_set("{}"), Err(JwkError::MissingMember("keys")));
+}
+
+#[test]
+fn test_thumbprint(){
+    // Example from RFC 7638 section 3.1.
+    let n = "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw";
+    assert_eq!(thumbprint_members("AQAB", n), "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs");
+
+    let key = RSAPublicKey::new(18446743557747632291, 65537);
+    assert_eq!(thumbprint(&key), thumbprint_members("AQAB", "____h95F6KM"));
+    assert_ne!(thumbprint(&key), thumbprint(&RSAPublicKey::new(18446743557747632291, 3)));
+}
@@ -0,0 +1,764 @@
+// Directory of named keypairs. Each key is kept as three files:
+//
+//     name.pem   the private key, PKCS#1 PEM or passphrase protected
+//     name.pub   the public key as an OpenSSH line
+//     name.json  metadata, creation time, size, fingerprint, comment and
+//                the lifetime and status of the key
+//
+// and revocations.txt lists revoked keys by fingerprint, one a line:
+//
+//     SHA256:4zYpktzDkWm2qrmfDAX6cg2E5sBUihWCvHDT1clJi3k 1714571100 reason
+//
+// The directory comes from RSA_TUTORIAL_KEYSTORE, or ~/.rsaencryptiontutorial/keys.
+
+use crate::json::{self, JsonError, JsonValue};
+use crate::keyfile::{self, KeyFileError};
+use crate::keys::{KeyError, RSAPrivateKey, RSAPublicKey};
+use crate::signature;
+use crate::ssh::{self, SshError};
+use crate::timestamp;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub const KEYSTORE_ENV: &str = "RSA_TUTORIAL_KEYSTORE";
+
+// Revocation list, the extension keeps it apart from the key files.
+const REVOCATIONS_FILE: &str = "revocations.txt";
+
+// Longest key name allowed, names become file names.
+const MAX_NAME_LENGTH: usize = 64;
+
+// Errors returned by keystore operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeystoreError{
+    // File system error, with the path and the message.
+    Io(String, String),
+    // Name that can't be used as a file name.
+    InvalidName(String),
+    NotFound(String),
+    AlreadyExists(String),
+    // Neither RSA_TUTORIAL_KEYSTORE nor HOME is set.
+    NoDirectory,
+    // Metadata file is missing a member or has the wrong type, with the key name.
+    InvalidMetadata(String),
+    // Private key is encrypted and no passphrase was given.
+    PassphraseRequired(String),
+    // Key can't be used now, with the key name and the time involved.
+    NotYetValid(String, u64),
+    Expired(String, u64),
+    Revoked(String),
+    // Key was rotated and is kept for decrypting old messages, with the replacement.
+    DecryptOnly(String, Option<String>),
+    // Key leaves a block of the message unchanged, with the key name.
+    CantEncrypt(String),
+    // Message wasn't encrypted with the key, with the key name.
+    CantDecrypt(String),
+    // Line of the revocation list that can't be read, with its number.
+    InvalidRevocation(usize),
+    Json(JsonError),
+    Key(KeyError),
+    KeyFile(KeyFileError),
+    Ssh(SshError),
+}
+
+impl fmt::Display for KeystoreError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            KeystoreError::Io(path, e) => write!(f, "{}: {}", path, e),
+            KeystoreError::InvalidName(n) => write!(f, "invalid key name \"{}\", use letters, digits, '.', '-' and '_'", n),
+            KeystoreError::NotFound(n) => write!(f, "no key named \"{}\" in the keystore", n),
+            KeystoreError::AlreadyExists(n) => write!(f, "a key named \"{}\" is already in the keystore", n),
+            KeystoreError::NoDirectory => write!(f, "set {} or HOME to choose the keystore directory", KEYSTORE_ENV),
+            KeystoreError::InvalidMetadata(n) => write!(f, "metadata for key \"{}\" is invalid", n),
+            KeystoreError::PassphraseRequired(n) => write!(f, "key \"{}\" is protected by a passphrase", n),
+            KeystoreError::NotYetValid(n, t) => write!(f, "key \"{}\" is not valid until {}", n, timestamp::format_utc(*t)),
+            KeystoreError::Expired(n, t) => write!(f, "key \"{}\" expired at {}", n, timestamp::format_utc(*t)),
+            KeystoreError::Revoked(n) => write!(f, "key \"{}\" has been revoked", n),
+            KeystoreError::DecryptOnly(n, Some(r)) => write!(f, "key \"{}\" was replaced by \"{}\" and can only decrypt", n, r),
+            KeystoreError::DecryptOnly(n, None) => write!(f, "key \"{}\" can only decrypt", n),
+            KeystoreError::CantEncrypt(n) => write!(f, "key \"{}\" leaves part of this message unchanged, use a different key", n),
+            KeystoreError::CantDecrypt(n) => write!(f, "unable to decrypt, the message was not encrypted with key \"{}\"", n),
+            KeystoreError::InvalidRevocation(l) => write!(f, "line {} of the revocation list is invalid", l),
+            KeystoreError::Json(e) => write!(f, "{}", e),
+            KeystoreError::Key(e) => write!(f, "{}", e),
+            KeystoreError::KeyFile(e) => write!(f, "{}", e),
+            KeystoreError::Ssh(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for KeystoreError{}
+
+impl From<JsonError> for KeystoreError{
+    fn from(e:JsonError) -> KeystoreError{
+        KeystoreError::Json(e)
+    }
+}
+
+impl From<KeyError> for KeystoreError{
+    fn from(e:KeyError) -> KeystoreError{
+        KeystoreError::Key(e)
+    }
+}
+
+impl From<KeyFileError> for KeystoreError{
+    fn from(e:KeyFileError) -> KeystoreError{
+        KeystoreError::KeyFile(e)
+    }
+}
+
+impl From<SshError> for KeystoreError{
+    fn from(e:SshError) -> KeystoreError{
+        KeystoreError::Ssh(e)
+    }
+}
+
+// What a key is being used for, some uses are still allowed for rotated keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyOperation{
+    Encrypt,
+    Decrypt,
+    Sign,
+    Verify,
+}
+
+// What is known about a stored key without reading the private key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMetadata{
+    pub name:String,
+    // Times are seconds since the Unix epoch.
+    pub created:u64,
+    pub bits:u32,
+    // OpenSSH style SHA256 fingerprint of the public key.
+    pub fingerprint:String,
+    pub comment:String,
+    pub encrypted:bool,
+    // Key can't be used before not_before or after not_after.
+    pub not_before:u64,
+    pub not_after:Option<u64>,
+    pub revoked:bool,
+    // Set when the key is rotated, it can still decrypt and verify.
+    pub decrypt_only:bool,
+    pub replaced_by:Option<String>,
+}
+
+impl KeyMetadata{
+    // Checks the key can be used for the operation at the time given. Revoked
+    // and expired keys can't be used at all, rotated keys can still decrypt
+    // old messages and verify old signatures.
+    pub fn check(&self, operation:KeyOperation, now:u64) -> Result<(), KeystoreError>{
+        if self.revoked{
+            return Err(KeystoreError::Revoked(self.name.clone()));
+        }
+        if now < self.not_before{
+            return Err(KeystoreError::NotYetValid(self.name.clone(), self.not_before));
+        }
+        if let Some(t) = self.not_after{
+            if now > t{
+                return Err(KeystoreError::Expired(self.name.clone(), t));
+            }
+        }
+        if self.decrypt_only && matches!(operation, KeyOperation::Encrypt | KeyOperation::Sign){
+            return Err(KeystoreError::DecryptOnly(self.name.clone(), self.replaced_by.clone()));
+        }
+        Ok(())
+    }
+
+    // One word summary for listings.
+    pub fn status(&self, now:u64) -> &'static str{
+        match self.check(KeyOperation::Encrypt, now){
+            Ok(()) => "valid",
+            Err(KeystoreError::Revoked(_)) => "revoked",
+            Err(KeystoreError::NotYetValid(..)) => "not yet valid",
+            Err(KeystoreError::Expired(..)) => "expired",
+            Err(_) => "decrypt only",
+        }
+    }
+
+    pub fn to_json(&self) -> String{
+        let optional_number = |v:Option<u64>| match v{
+            Some(n) => JsonValue::Number(n.to_string()),
+            None => JsonValue::Null,
+        };
+        let optional_string = |v:&Option<String>| match v{
+            Some(s) => JsonValue::String(s.clone()),
+            None => JsonValue::Null,
+        };
+        JsonValue::Object(vec![
+            (String::from("name"), JsonValue::String(self.name.clone())),
+            (String::from("created"), JsonValue::Number(self.created.to_string())),
+            (String::from("bits"), JsonValue::Number(self.bits.to_string())),
+            (String::from("fingerprint"), JsonValue::String(self.fingerprint.clone())),
+            (String::from("comment"), JsonValue::String(self.comment.clone())),
+            (String::from("encrypted"), JsonValue::Bool(self.encrypted)),
+            (String::from("not_before"), JsonValue::Number(self.not_before.to_string())),
+            (String::from("not_after"), optional_number(self.not_after)),
+            (String::from("revoked"), JsonValue::Bool(self.revoked)),
+            (String::from("decrypt_only"), JsonValue::Bool(self.decrypt_only)),
+            (String::from("replaced_by"), optional_string(&self.replaced_by)),
+        ]).to_json()
+    }
+
+    // Reads metadata, the lifetime and status members are optional so files
+    // written before they existed still load as valid keys without an expiry.
+    pub fn from_json(text:&str) -> Result<KeyMetadata, KeystoreError>{
+        let value = json::parse(text)?;
+        let name = match value.get("name").and_then(|v| v.as_str()){
+            Some(s) => s.to_string(),
+            None => return Err(KeystoreError::InvalidMetadata(String::new())),
+        };
+        let invalid = || KeystoreError::InvalidMetadata(name.clone());
+        let number = |member:&str| match value.get(member){
+            Some(JsonValue::Number(n)) => n.parse::<u64>().ok(),
+            _ => None,
+        };
+        let string = |member:&str| value.get(member).and_then(|v| v.as_str()).map(|s| s.to_string());
+        let boolean = |member:&str| match value.get(member){
+            Some(JsonValue::Bool(b)) => Some(*b),
+            _ => None,
+        };
+        let present = |member:&str| !matches!(value.get(member), None | Some(JsonValue::Null));
+
+        let created = number("created").ok_or_else(invalid)?;
+        let bits = match number("bits"){
+            Some(b) if b <= 64 => b as u32,
+            _ => return Err(invalid()),
+        };
+        let fingerprint = string("fingerprint").ok_or_else(invalid)?;
+        let comment = string("comment").ok_or_else(invalid)?;
+        let encrypted = boolean("encrypted").ok_or_else(invalid)?;
+
+        let not_before = if present("not_before") {number("not_before").ok_or_else(invalid)?} else {created};
+        let not_after = if present("not_after") {Some(number("not_after").ok_or_else(invalid)?)} else {None};
+        let revoked = if present("revoked") {boolean("revoked").ok_or_else(invalid)?} else {false};
+        let decrypt_only = if present("decrypt_only") {boolean("decrypt_only").ok_or_else(invalid)?} else {false};
+        let replaced_by = if present("replaced_by") {Some(string("replaced_by").ok_or_else(invalid)?)} else {None};
+        Ok(KeyMetadata{ name, created, bits, fingerprint, comment, encrypted, not_before, not_after, revoked, decrypt_only, replaced_by })
+    }
+}
+
+// Entry of the revocation list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revocation{
+    pub fingerprint:String,
+    pub revoked:u64,
+    pub reason:String,
+}
+
+// Reads the revocation list, blank lines and lines starting with # are skipped.
+pub fn parse_revocations(text:&str) -> Result<Vec<Revocation>, KeystoreError>{
+    let mut result:Vec<Revocation> = Vec::new();
+    for (i, line) in text.lines().enumerate(){
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#'){
+            continue;
+        }
+        let mut fields = line.splitn(3, char::is_whitespace);
+        let fingerprint = fields.next().unwrap_or("");
+        let revoked = fields.next().and_then(|t| t.parse::<u64>().ok());
+        match revoked{
+            Some(t) if fingerprint.starts_with("SHA256:") => result.push(Revocation{
+                fingerprint: fingerprint.to_string(),
+                revoked: t,
+                reason: fields.next().unwrap_or("").trim().to_string(),
+            }),
+            _ => return Err(KeystoreError::InvalidRevocation(i + 1)),
+        }
+    }
+    Ok(result)
+}
+
+// A keystore directory, it is created when the first key is saved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keystore{
+    dir:PathBuf,
+}
+
+impl Keystore{
+    pub fn new<P:Into<PathBuf>>(dir:P) -> Keystore{
+        Keystore{ dir: dir.into() }
+    }
+
+    // The directory from RSA_TUTORIAL_KEYSTORE, or .rsaencryptiontutorial/keys in the home directory.
+    pub fn open_default() -> Result<Keystore, KeystoreError>{
+        if let Some(dir) = std::env::var_os(KEYSTORE_ENV){
+            if !dir.is_empty(){
+                return Ok(Keystore::new(dir));
+            }
+        }
+        match std::env::var_os("HOME"){
+            Some(home) if !home.is_empty() => Ok(Keystore::new(Path::new(&home).join(".rsaencryptiontutorial").join("keys"))),
+            _ => Err(KeystoreError::NoDirectory),
+        }
+    }
+
+    pub fn dir(&self) -> &Path{
+        &self.dir
+    }
+
+    pub fn contains(&self, name:&str) -> bool{
+        check_name(name).is_ok() && self.path(name, "json").exists()
+    }
+
+    // Saves a new key, encrypted when a passphrase is given. An existing key
+    // with the same name is never overwritten.
+    pub fn save(&self, name:&str, key:&RSAPrivateKey, comment:&str, passphrase:Option<&str>) -> Result<KeyMetadata, KeystoreError>{
+        check_name(name)?;
+        if self.contains(name){
+            return Err(KeystoreError::AlreadyExists(name.to_string()));
+        }
+        if let Err(e) = std::fs::create_dir_all(&self.dir){
+            return Err(io_error(&self.dir, e));
+        }
+
+        let privatetext = match passphrase{
+            Some(p) => keyfile::encrypt_private_key(key, p, keyfile::DEFAULT_ITERATIONS)?,
+            None => key.to_pkcs1_pem(),
+        };
+        let public = key.public_key();
+        let created = timestamp::now();
+        let metadata = KeyMetadata{
+            name: name.to_string(),
+            created,
+            bits: 64 - public.get_n().leading_zeros(),
+            fingerprint: ssh::fingerprint_sha256(&public),
+            comment: comment.to_string(),
+            encrypted: passphrase.is_some(),
+            not_before: created,
+            not_after: None,
+            revoked: false,
+            decrypt_only: false,
+            replaced_by: None,
+        };
+
+        write_private(&self.path(name, "pem"), &privatetext)?;
+        write_file(&self.path(name, "pub"), &format!("{}\n", ssh::to_openssh_line(&public, comment)))?;
+        // Metadata goes last, a key only counts as stored once it is there.
+        write_file(&self.path(name, "json"), &format!("{}\n", metadata.to_json()))?;
+        Ok(metadata)
+    }
+
+    // Metadata of every key, sorted by name. Metadata files that can't be read
+    // are skipped so one damaged key doesn't hide the rest, but a damaged
+    // revocation list is an error since it could hide that a key is revoked.
+    pub fn list(&self) -> Result<Vec<KeyMetadata>, KeystoreError>{
+        let revocations = self.revocations()?;
+        let entries = match std::fs::read_dir(&self.dir){
+            Ok(s) => s,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(io_error(&self.dir, e)),
+        };
+        let mut result:Vec<KeyMetadata> = Vec::new();
+        for entry in entries.flatten(){
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json"){
+                continue;
+            }
+            let name = match path.file_stem().and_then(|s| s.to_str()){
+                Some(s) => s.to_string(),
+                None => continue,
+            };
+            if let Ok(mut metadata) = self.read_metadata(&name){
+                apply_revocations(&mut metadata, &revocations);
+                result.push(metadata);
+            }
+        }
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(result)
+    }
+
+    // Metadata of the key, marked revoked when its fingerprint is on the
+    // revocation list even if the metadata file doesn't say so.
+    pub fn metadata(&self, name:&str) -> Result<KeyMetadata, KeystoreError>{
+        let mut metadata = self.read_metadata(name)?;
+        apply_revocations(&mut metadata, &self.revocations()?);
+        Ok(metadata)
+    }
+
+    // Replaces the metadata of a stored key, used to change its lifetime or status.
+    pub fn update_metadata(&self, metadata:&KeyMetadata) -> Result<(), KeystoreError>{
+        if !self.contains(&metadata.name){
+            return Err(KeystoreError::NotFound(metadata.name.clone()));
+        }
+        write_file(&self.path(&metadata.name, "json"), &format!("{}\n", metadata.to_json()))
+    }
+
+    // Metadata of a key that can be used for the operation now.
+    pub fn check(&self, name:&str, operation:KeyOperation) -> Result<KeyMetadata, KeystoreError>{
+        let metadata = self.metadata(name)?;
+        metadata.check(operation, timestamp::now())?;
+        Ok(metadata)
+    }
+
+    // Encrypts with the stored key, as crate::encrypt_message does. Revoked,
+    // expired and rotated keys are refused unless ignore_validity is set.
+    pub fn encrypt(&self, name:&str, message:&[u8], ignore_validity:bool) -> Result<Vec<u8>, KeystoreError>{
+        self.check_use(name, KeyOperation::Encrypt, ignore_validity)?;
+        match crate::encrypt_message(&self.public_key(name)?, message){
+            Some(s) => Ok(s),
+            None => Err(KeystoreError::CantEncrypt(name.to_string())),
+        }
+    }
+
+    // Decrypts with the stored key, the passphrase is needed when it is encrypted.
+    pub fn decrypt(&self, name:&str, data:&[u8], passphrase:Option<&str>, ignore_validity:bool) -> Result<Vec<u8>, KeystoreError>{
+        self.check_use(name, KeyOperation::Decrypt, ignore_validity)?;
+        match crate::decrypt_message(&self.private_key(name, passphrase)?, data){
+            Some(s) => Ok(s),
+            None => Err(KeystoreError::CantDecrypt(name.to_string())),
+        }
+    }
+
+    pub fn sign(&self, name:&str, message:&[u8], passphrase:Option<&str>, ignore_validity:bool) -> Result<u64, KeystoreError>{
+        self.check_use(name, KeyOperation::Sign, ignore_validity)?;
+        Ok(signature::sign(&self.private_key(name, passphrase)?, message))
+    }
+
+    pub fn verify(&self, name:&str, message:&[u8], value:u64, ignore_validity:bool) -> Result<bool, KeystoreError>{
+        self.check_use(name, KeyOperation::Verify, ignore_validity)?;
+        Ok(signature::verify(&self.public_key(name)?, message, value))
+    }
+
+    // check, but with ignore_validity only a missing key or bad metadata fails.
+    fn check_use(&self, name:&str, operation:KeyOperation, ignore_validity:bool) -> Result<(), KeystoreError>{
+        match self.check(name, operation){
+            Ok(_) => Ok(()),
+            Err(KeystoreError::Revoked(_) | KeystoreError::Expired(..) | KeystoreError::NotYetValid(..) | KeystoreError::DecryptOnly(..)) if ignore_validity => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn revocations(&self) -> Result<Vec<Revocation>, KeystoreError>{
+        let path = self.dir.join(REVOCATIONS_FILE);
+        match std::fs::read_to_string(&path){
+            Ok(s) => parse_revocations(&s),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(io_error(&path, e)),
+        }
+    }
+
+    // Marks the key revoked in its metadata and adds it to the revocation list.
+    pub fn revoke(&self, name:&str, reason:&str) -> Result<KeyMetadata, KeystoreError>{
+        let mut metadata = self.metadata(name)?;
+        let listed = self.revocations()?.iter().any(|r| r.fingerprint == metadata.fingerprint);
+        if !listed{
+            let path = self.dir.join(REVOCATIONS_FILE);
+            let reason:String = reason.chars().map(|c| if c.is_control() {' '} else {c}).collect();
+            let line = format!("{} {} {}\n", metadata.fingerprint, timestamp::now(), reason.trim());
+            let file = std::fs::OpenOptions::new().append(true).create(true).open(&path);
+            let result = match file{
+                Ok(mut f) => std::io::Write::write_all(&mut f, line.as_bytes()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result{
+                return Err(io_error(&path, e));
+            }
+        }
+        metadata.revoked = true;
+        self.update_metadata(&metadata)?;
+        Ok(metadata)
+    }
+
+    // Saves the new key under new_name with the comment and lifetime of the old
+    // one, and marks the old key decrypt only so messages sent to it can still
+    // be read.
+    pub fn rotate(&self, name:&str, new_name:&str, key:&RSAPrivateKey, passphrase:Option<&str>) -> Result<KeyMetadata, KeystoreError>{
+        let mut old = self.metadata(name)?;
+        check_name(new_name)?;
+        if self.contains(new_name){
+            return Err(KeystoreError::AlreadyExists(new_name.to_string()));
+        }
+        let mut new = self.save(new_name, key, &old.comment, passphrase)?;
+        if let Some(t) = old.not_after{
+            new.not_after = Some(new.not_before + t.saturating_sub(old.not_before));
+            self.update_metadata(&new)?;
+        }
+        old.decrypt_only = true;
+        old.replaced_by = Some(new_name.to_string());
+        self.update_metadata(&old)?;
+        Ok(new)
+    }
+
+    pub fn public_key(&self, name:&str) -> Result<RSAPublicKey, KeystoreError>{
+        let text = self.read(name, "pub")?;
+        Ok(ssh::parse_public_key_line(text.trim())?.key)
+    }
+
+    // Reads the private key, the passphrase is needed when it is encrypted.
+    pub fn private_key(&self, name:&str, passphrase:Option<&str>) -> Result<RSAPrivateKey, KeystoreError>{
+        let text = self.read(name, "pem")?;
+        if keyfile::is_encrypted(&text){
+            match passphrase{
+                Some(p) => Ok(keyfile::decrypt_private_key(&text, p)?),
+                None => Err(KeystoreError::PassphraseRequired(name.to_string())),
+            }
+        }else{
+            Ok(RSAPrivateKey::from_pem(&text)?)
+        }
+    }
+
+    // Removes all three files of the key.
+    pub fn delete(&self, name:&str) -> Result<(), KeystoreError>{
+        if !self.contains(name){
+            return Err(KeystoreError::NotFound(name.to_string()));
+        }
+        for extension in ["json", "pem", "pub"]{
+            let path = self.path(name, extension);
+            match std::fs::remove_file(&path){
+                Ok(()) => {},
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {},
+                Err(e) => return Err(io_error(&path, e)),
+            }
+        }
+        Ok(())
+    }
+
+    fn read_metadata(&self, name:&str) -> Result<KeyMetadata, KeystoreError>{
+        let metadata = KeyMetadata::from_json(&self.read(name, "json")?)?;
+        if metadata.name != name{
+            return Err(KeystoreError::InvalidMetadata(name.to_string()));
+        }
+        Ok(metadata)
+    }
+
+    fn path(&self, name:&str, extension:&str) -> PathBuf{
+        self.dir.join(format!("{}.{}", name, extension))
+    }
+
+    fn read(&self, name:&str, extension:&str) -> Result<String, KeystoreError>{
+        check_name(name)?;
+        let path = self.path(name, extension);
+        match std::fs::read_to_string(&path){
+            Ok(s) => Ok(s),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(KeystoreError::NotFound(name.to_string())),
+            Err(e) => Err(io_error(&path, e)),
+        }
+    }
+}
+
+// Names are used as file names, so only a safe set of characters is allowed
+// and they can't start with a dot.
+fn check_name(name:&str) -> Result<(), KeystoreError>{
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && !name.starts_with('.')
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'-' || b == b'_');
+    if valid{
+        Ok(())
+    }else{
+        Err(KeystoreError::InvalidName(name.to_string()))
+    }
+}
+
+// A key on the revocation list is revoked even if its metadata doesn't say so.
+fn apply_revocations(metadata:&mut KeyMetadata, revocations:&[Revocation]){
+    if revocations.iter().any(|r| r.fingerprint == metadata.fingerprint){
+        metadata.revoked = true;
+    }
+}
+
+fn io_error(path:&Path, e:std::io::Error) -> KeystoreError{
+    KeystoreError::Io(path.display().to_string(), e.to_string())
+}
+
+fn write_file(path:&Path, text:&str) -> Result<(), KeystoreError>{
+    std::fs::write(path, text).map_err(|e| io_error(path, e))
+}
+
+// Private keys are only readable by their owner on Unix.
+#[cfg(unix)]
+fn write_private(path:&Path, text:&str) -> Result<(), KeystoreError>{
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    let file = std::fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path);
+    match file{
+        Ok(mut f) => f.write_all(text.as_bytes()).map_err(|e| io_error(path, e)),
+        Err(e) => Err(io_error(path, e)),
+    }
+}
+
+#[cfg(not(unix))]
+fn write_private(path:&Path, text:&str) -> Result<(), KeystoreError>{
+    write_file(path, text)
+}
+
+// An empty keystore in the temporary directory, label keeps tests that run
+// at the same time apart. Tests remove it again when they finish.
+#[cfg(test)]
+pub fn test_keystore(label:&str) -> Keystore{
+    let dir = std::env::temp_dir().join(format!("rsatutorial-keystore-{}-{}", label, std::process::id()));
+    _ = std::fs::remove_dir_all(&dir);
+    Keystore::new(dir)
+}
+
+#[cfg(test)]
+use crate::keys::{other_test_key, test_key};
+
+#[test]
+fn test_keystore_save_and_load(){
+    let store = test_keystore("save");
+    let key = test_key();
+    assert_eq!(store.list(), Ok(Vec::new()));
+
+    let metadata = store.save("work", &key, "me@example", None).unwrap();
+    assert_eq!(metadata.bits, 64);
+    assert_eq!(metadata.fingerprint, "SHA256:4zYpktzDkWm2qrmfDAX6cg2E5sBUihWCvHDT1clJi3k");
+    assert!(!metadata.encrypted);
+    assert_eq!(store.metadata("work"), Ok(metadata.clone()));
+    assert_eq!(store.public_key("work"), Ok(key.public_key()));
+    assert_eq!(store.private_key("work", None), Ok(key));
+
+    assert_eq!(store.save("work", &key, "", None), Err(KeystoreError::AlreadyExists(String::from("work"))));
+    store.save("another", &key, "", None).unwrap();
+    let names:Vec<String> = store.list().unwrap().into_iter().map(|m| m.name).collect();
+    assert_eq!(names, vec!["another", "work"]);
+
+    assert_eq!(store.delete("work"), Ok(()));
+    assert!(!store.contains("work"));
+    assert_eq!(store.delete("work"), Err(KeystoreError::NotFound(String::from("work"))));
+    assert_eq!(store.private_key("work", None), Err(KeystoreError::NotFound(String::from("work"))));
+    _ = std::fs::remove_dir_all(store.dir());
+}
+
+#[test]
+fn test_keystore_encrypted(){
+    let store = test_keystore("encrypted");
+    let key = test_key();
+    let metadata = store.save("secret", &key, "", Some("pw")).unwrap();
+    assert!(metadata.encrypted);
+    assert_eq!(store.public_key("secret"), Ok(key.public_key()));
+    assert_eq!(store.private_key("secret", None), Err(KeystoreError::PassphraseRequired(String::from("secret"))));
+    assert_eq!(store.private_key("secret", Some("wrong")), Err(KeystoreError::KeyFile(KeyFileError::WrongPassphrase)));
+    assert_eq!(store.private_key("secret", Some("pw")), Ok(key));
+    _ = std::fs::remove_dir_all(store.dir());
+}
+
+#[test]
+fn test_keystore_names(){
+    for name in ["", ".hidden", "../escape", "a/b", "with space", &"x".repeat(65)]{
+        assert_eq!(check_name(name), Err(KeystoreError::InvalidName(name.to_string())));
+    }
+    for name in ["a", "work-2024", "id_rsa", "key.v2"]{
+        assert_eq!(check_name(name), Ok(()));
+    }
+}
+
+#[test]
+fn test_keystore_metadata_json(){
+    let mut metadata = test_metadata();
+    metadata.comment = String::from("a \"quoted\" comment");
+    assert_eq!(KeyMetadata::from_json(&metadata.to_json()), Ok(metadata.clone()));
+    metadata.not_after = Some(1714571200);
+    metadata.replaced_by = Some(String::from("work-2"));
+    assert_eq!(KeyMetadata::from_json(&metadata.to_json()), Ok(metadata));
+    assert_eq!(KeyMetadata::from_json("{\"name\":\"work\",\"created\":\"soon\"}"), Err(KeystoreError::InvalidMetadata(String::from("work"))));
+
+    // Metadata from before keys had a lifetime.
+    let old = "{\"name\":\"work\",\"created\":1714571100,\"bits\":64,\"fingerprint\":\"SHA256:abc\",\"comment\":\"\",\"encrypted\":false}";
+    let metadata = KeyMetadata::from_json(old).unwrap();
+    assert_eq!(metadata.not_before, 1714571100);
+    assert_eq!(metadata.not_after, None);
+    assert!(!metadata.revoked && !metadata.decrypt_only);
+}
+
+#[cfg(test)]
+fn test_metadata() -> KeyMetadata{
+    KeyMetadata{
+        name: String::from("work"),
+        created: 1714571100,
+        bits: 64,
+        fingerprint: String::from("SHA256:abc"),
+        comment: String::new(),
+        encrypted: true,
+        not_before: 1714571100,
+        not_after: None,
+        revoked: false,
+        decrypt_only: false,
+        replaced_by: None,
+    }
+}
+
+#[test]
+fn test_keystore_metadata_check(){
+    let mut metadata = test_metadata();
+    metadata.not_after = Some(1714571100 + 86400);
+    assert_eq!(metadata.check(KeyOperation::Encrypt, 1714571100), Ok(()));
+    assert_eq!(metadata.check(KeyOperation::Sign, 1714571099), Err(KeystoreError::NotYetValid(String::from("work"), 1714571100)));
+    assert_eq!(metadata.check(KeyOperation::Decrypt, 1714571100 + 86401), Err(KeystoreError::Expired(String::from("work"), 1714571100 + 86400)));
+    assert_eq!(metadata.status(1714571100 + 86401), "expired");
+
+    metadata.decrypt_only = true;
+    assert_eq!(metadata.check(KeyOperation::Encrypt, 1714571100), Err(KeystoreError::DecryptOnly(String::from("work"), None)));
+    assert_eq!(metadata.check(KeyOperation::Sign, 1714571100), Err(KeystoreError::DecryptOnly(String::from("work"), None)));
+    assert_eq!(metadata.check(KeyOperation::Decrypt, 1714571100), Ok(()));
+    assert_eq!(metadata.check(KeyOperation::Verify, 1714571100), Ok(()));
+    assert_eq!(metadata.status(1714571100), "decrypt only");
+
+    metadata.revoked = true;
+    assert_eq!(metadata.check(KeyOperation::Verify, 1714571100), Err(KeystoreError::Revoked(String::from("work"))));
+}
+
+#[test]
+fn test_keystore_revocations(){
+    let text = "# revoked keys\n\nSHA256:abc 1714571100 laptop stolen\nSHA256:def 1714571200\n";
+    let list = parse_revocations(text).unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0], Revocation{ fingerprint: String::from("SHA256:abc"), revoked: 1714571100, reason: String::from("laptop stolen") });
+    assert_eq!(list[1].reason, "");
+    assert_eq!(parse_revocations("SHA256:abc yesterday\n"), Err(KeystoreError::InvalidRevocation(1)));
+    assert_eq!(parse_revocations("\nabc 1714571100\n"), Err(KeystoreError::InvalidRevocation(2)));
+
+    let store = test_keystore("revoke");
+    let key = test_key();
+    store.save("work", &key, "", None).unwrap();
+    assert!(store.check("work", KeyOperation::Encrypt).is_ok());
+    let cypher = store.encrypt("work", b"hello", false).unwrap();
+    let value = store.sign("work", b"hello", None, false).unwrap();
+    store.revoke("work", "lost\nlaptop").unwrap();
+    assert_eq!(store.check("work", KeyOperation::Decrypt), Err(KeystoreError::Revoked(String::from("work"))));
+    let revoked = KeystoreError::Revoked(String::from("work"));
+    assert_eq!(store.encrypt("work", b"hello", false), Err(revoked.clone()));
+    assert_eq!(store.decrypt("work", &cypher, None, false), Err(revoked.clone()));
+    assert_eq!(store.sign("work", b"hello", None, false), Err(revoked.clone()));
+    assert_eq!(store.verify("work", b"hello", value, false), Err(revoked));
+    assert_eq!(store.decrypt("work", &cypher, None, true), Ok(b"hello".to_vec()));
+    assert_eq!(store.verify("work", b"hello", value, true), Ok(true));
+    assert_eq!(store.decrypt("work", &cypher[..4], None, true), Err(KeystoreError::CantDecrypt(String::from("work"))));
+    assert_eq!(store.encrypt("missing", b"hello", true), Err(KeystoreError::NotFound(String::from("missing"))));
+    let list = store.revocations().unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].reason, "lost laptop");
+
+    // The list alone is enough, even if the metadata is put back.
+    let mut metadata = store.metadata("work").unwrap();
+    metadata.revoked = false;
+    store.update_metadata(&metadata).unwrap();
+    assert!(store.metadata("work").unwrap().revoked);
+    _ = std::fs::remove_dir_all(store.dir());
+}
+
+#[test]
+fn test_keystore_rotate(){
+    let store = test_keystore("rotate");
+    let key = test_key();
+    let mut metadata = store.save("work", &key, "me@example", None).unwrap();
+    metadata.not_after = Some(metadata.not_before + 30 * 86400);
+    store.update_metadata(&metadata).unwrap();
+
+    let newkey = other_test_key();
+    let new = store.rotate("work", "work-2", &newkey, None).unwrap();
+    assert_eq!(new.comment, "me@example");
+    assert_eq!(new.not_after, Some(new.not_before + 30 * 86400));
+    assert!(store.check("work-2", KeyOperation::Encrypt).is_ok());
+
+    let old = store.metadata("work").unwrap();
+    assert!(old.decrypt_only);
+    assert_eq!(old.replaced_by.as_deref(), Some("work-2"));
+    assert_eq!(store.check("work", KeyOperation::Encrypt), Err(KeystoreError::DecryptOnly(String::from("work"), Some(String::from("work-2")))));
+    assert!(store.check("work", KeyOperation::Decrypt).is_ok());
+    assert!(matches!(store.encrypt("work", b"hello", false), Err(KeystoreError::DecryptOnly(..))));
+    let cypher = store.encrypt("work", b"hello", true).unwrap();
+    assert_eq!(store.decrypt("work", &cypher, None, false), Ok(b"hello".to_vec()));
+    assert_eq!(store.rotate("work", "work-2", &newkey, None), Err(KeystoreError::AlreadyExists(String::from("work-2"))));
+    _ = std::fs::remove_dir_all(store.dir());
+}
@@ -1,8 +1,38 @@
 // Author: Shannon Musgrave
 
-use std::{panic, sync::{mpsc, Arc}, thread};
+use std::{panic, sync::{mpsc, Arc}, thread, time::Instant};
 mod utility;
 mod structures;
+mod chapters;
+pub mod codec;
+pub mod base64;
+pub mod der;
+pub mod pem;
+pub mod keys;
+pub mod sha256;
+pub mod ssh;
+pub mod json;
+pub mod jwk;
+pub mod random;
+pub mod pbkdf2;
+pub mod keyfile;
+pub mod timestamp;
+pub mod keystore;
+pub mod cli;
+pub mod fingerprint;
+pub mod signature;
+pub mod x509;
+pub mod csr;
+pub mod multiprime;
+pub mod fips186;
+pub mod audit;
+pub mod biguint;
+pub mod batchgcd;
+pub mod pollard;
+pub mod fermat;
+pub mod wiener;
+pub mod hastad;
+pub mod commonmodulus;
 
 // Main function called from main.rs. Will run through complete
 // encryption and decryption process.
@@ -12,10 +42,11 @@ pub fn run(){
     // multiply to get modulus N must be higher than 
     // u32max
     // get Phi (p-1)*(q-1);
+    // get lambda lcm(p-1, q-1), which divides phi
     // get public exponent such that 1 < e < phi
     // and is coprime with phi
     // get private exponent which is the 
-    // multiplicative inverse d * e = 1 modn
+    // multiplicative inverse d * e = 1 mod phi or lambda
     // to encrypt c = m ^ e modn
     // to decrypt m = c ^ d modn
 
@@ -24,32 +55,80 @@ pub fn run(){
         // Create structure to hold all encryption information.
         let mut myrsainfo = structures::RSAInfo::new();
 
-        // Helper method to retrieve primes.
-        utility::get_primes(&mut myrsainfo);
-        println!("");
-        println!("-----------------------------------------------------------------");
-        println!("");
-
-        // Helper method to get N (modulus).
-        utility::get_modulus(&mut myrsainfo);
-        println!("");
-        println!("-----------------------------------------------------------------");
-        println!("");
-
-        // Get euler totient.
-        utility::get_phi(&mut myrsainfo);
-        println!("");
-        println!("-----------------------------------------------------------------");
-        println!("");
-
-        // Get exponent e (for encryption).
-        utility::get_e(&mut myrsainfo);  
-        println!("");
-        println!("-----------------------------------------------------------------");
-        println!("");
+        // A saved key can be loaded instead of working through key generation.
+        println!("Press enter to create a new key, or type the name of a key in the keystore or the path of a PEM private key file to load one.");
+        let keypath = get_user_string();
+        let loaded = !keypath.is_empty() && load_key(&keypath, &mut myrsainfo);
+
+        if !loaded{
+            // e is only asked for once, the primes are picked again until it works with them.
+            let mut first = true;
+            loop{
+                // Helper method to retrieve primes.
+                utility::get_primes(&mut myrsainfo);
+                println!("");
+                println!("-----------------------------------------------------------------");
+                println!("");
+
+                // Helper method to get N (modulus).
+                utility::get_modulus(&mut myrsainfo);
+                println!("");
+                println!("-----------------------------------------------------------------");
+                println!("");
+
+                // Get euler totient.
+                utility::get_phi(&mut myrsainfo);
+                println!("");
+                println!("-----------------------------------------------------------------");
+                println!("");
+
+                // Get carmichaels function, the smallest modulus d can be worked out with.
+                utility::get_lambda(&mut myrsainfo);
+                println!("");
+                println!("-----------------------------------------------------------------");
+                println!("");
+
+                // Get exponent e (for encryption).
+                if first{
+                    utility::get_e(&mut myrsainfo);
+                    first = false;
+                }
+                let usable = utility::check_e(&myrsainfo);
+                println!("");
+                println!("-----------------------------------------------------------------");
+                println!("");
+                if usable{
+                    break;
+                }
+                println!("We'll keep e and pick new primes, generated keys do the same. Start looking somewhere else this time.");
+            }
+
+            // Get exponent d (for decryption), modulo phi or lambda as the user picks.
+            let modulus = utility::get_exponent_modulus();
+            utility::get_d(&mut myrsainfo, modulus);
+            println!("");
+            println!("-----------------------------------------------------------------");
+            println!("");
+
+            // Check the values agree with each other before the key is used or saved.
+            println!("Before using the key, lets check it. n must be p * q with p and q prime, e * d = 1 mod lambda,");
+            println!("and a random number must come back after encrypting and decrypting it, and signing and verifying it.");
+            if let Err(e) = myrsainfo.validate(){
+                println!("The key failed a check, {}. Lets start again with different primes.", e);
+                continue;
+            }
+            println!("The key passed every check.");
+            println!("");
+            println!("-----------------------------------------------------------------");
+            println!("");
+
+            // The key is lost when this loop ends unless it is saved.
+            save_key(&myrsainfo);
+        }
 
-        // Get exponent d (for decryption).
-        utility::get_d(&mut myrsainfo);
+        // Show the public key as OpenSSH writes it, with the same fingerprint
+        // line as ssh-keygen -lf so the two can be compared.
+        show_public_key(&myrsainfo);
         println!("");
         println!("Everything is in place to perform encryption.");
         println!("");
@@ -78,10 +157,11 @@ pub fn run(){
         println!("");
 
         println!("Now we should pad the bytes, we need to create a multiple of 8 bytes since we are performing 64 bit encryption chunks.");
-        println!("We also do this to make sure the bytes are smaller, by inserting a small byte into the most significant byte.");
+        println!("Each chunk holds 7 bytes of the message and a header byte in the most significant position, which says how many of the 7 are used.");
+        println!("The header keeps the integers smaller than the modulus and lets us get back every byte, even zeros at the end of the message.");
 
-        // Common practice is to pad bytes, simply adding a byte of low value to most significant position.
-        // every 8 bytes. Also making total array length a multiple of 8, since these will be converted to u64 integers.
+        // Split the message into 7 byte pieces, each one gets a length header as its most significant byte.
+        // This makes the total array length a multiple of 8, since these will be converted to u64 integers.
         let paddedbytes:Vec<u8> = pad_the_bytes(rawbytes);
         println!("");
         println!("{:?}", paddedbytes);
@@ -95,7 +175,13 @@ pub fn run(){
         println!("Now we need to convert these bytes into 64bit integers, remember 8 bytes is one 64bit integer.");
 
         // Now to take padded vector of bytes and convert them 8 to 1 to a vector of u64 integers.
-        let info:Vec<u64> = get_integers(&paddedbytes);
+        let info:Vec<u64> = match get_integers(&paddedbytes){
+            Ok(s) => s,
+            Err(e) => {
+                println!("Unable to convert the padded bytes. Error: {}", e);
+                Vec::new()
+            },
+        };
         println!("");
         println!("{:?}", info);
        
@@ -105,6 +191,27 @@ pub fn run(){
         println!("-----------------------------------------------------------------");
         println!("");
 
+        println!("Some integers come out of RSA unchanged (x ^ e modulus n = x), which would show the message in the encrypted data.");
+        println!("The header byte is never 0, so the integers are never 0 or 1. For any other integer that would stay the same,");
+        println!("we change two spare bits in its header byte, which are ignored when we depad.");
+
+        // Make sure no block is a fixed point of the encryption.
+        let info:Vec<u64> = match avoid_fixed_points(info, myrsainfo.get_e(), myrsainfo.get_n()){
+            Some(s) => s,
+            None => {
+                println!("This key leaves the message unchanged, lets start again with different primes.");
+                continue;
+            },
+        };
+        println!("");
+        println!("{:?}", info);
+
+        println!("Press enter to continue.");
+        _ = get_user_string();
+        println!("");
+        println!("-----------------------------------------------------------------");
+        println!("");
+
         println!("We can start encrypting these with the information above, to encrypt, it is information ^ e modulus n.");
         println!("This is the data taken to the {} power modulus {}", myrsainfo.get_e(), myrsainfo.get_n());
 
@@ -125,6 +232,10 @@ pub fn run(){
         let encrypted:Vec<u8> = getbytes(mutatedvec);
         println!("");
         println!("{:?}", encrypted);
+        println!("");
+        println!("To send it, the bytes are written as text with the fingerprint of the key it was encrypted for:");
+        let fingerprint = fingerprint::Fingerprint::of(&keys::RSAPublicKey::new(myrsainfo.get_n(), myrsainfo.get_e())).to_string();
+        print!("{}", pem::encode_with_headers(cli::MESSAGE_LABEL, &[(cli::FINGERPRINT_HEADER, &fingerprint)], &encrypted));
        
         println!("Press enter to continue.");
         _ = get_user_string();
@@ -136,7 +247,13 @@ pub fn run(){
 
         // Now that bytes have been encrypted and displayed to user, now lets reverse process and decrypt.
         // Reverse steps, convert back to u64 ints.
-        let encryptedintegers = get_integers(&encrypted);
+        let encryptedintegers = match get_integers(&encrypted){
+            Ok(s) => s,
+            Err(e) => {
+                println!("The encrypted bytes are malformed. Error: {}", e);
+                Vec::new()
+            },
+        };
         println!("");
         println!("{:?}", encryptedintegers);
         
@@ -161,6 +278,20 @@ pub fn run(){
         println!("-----------------------------------------------------------------");
         println!("");
 
+        println!("Decryption can be made faster with the Chinese remainder theorem. Instead of one exponent mod n,");
+        println!("it works out cypher ^ (d mod (r - 1)) mod r for each prime r, which are smaller numbers, and joins the results.");
+        println!("A key can also have more than two primes, each one is then smaller still and cheaper to work with.");
+        println!("Here are keys with 2 to {} primes decrypting the same integers, each encrypted with that key first.", multiprime::MAX_PRIMES);
+        compare_prime_counts(&decrypted, myrsainfo.get_n());
+        println!("With real keys of thousands of bits k primes make decryption about k^2 times faster. With 64 bits it depends");
+        println!("on the word size, primes below 2^32 fit 64 bit arithmetic. More primes also make each one easier to find, so few are used.");
+
+        println!("Press enter to continue.");
+        _ = get_user_string();
+        println!("");
+        println!("-----------------------------------------------------------------");
+        println!("");
+
         println!("Convert these decrypted integers back to the vector of bytes.");
 
         // Get bytes from integers.
@@ -176,8 +307,14 @@ pub fn run(){
 
         println!("We need to depad this string to get to our original string.");
 
-        // Reverse the padding process by removing each 8th byte.
-        let depadded = depad_the_bytes(getdecryptedbytes);
+        // Reverse the padding process by reading each header byte and keeping that many bytes.
+        let depadded = match depad_the_bytes(getdecryptedbytes){
+            Some(s) => s,
+            None => {
+                println!("The header bytes do not describe a padded message, something went wrong during decryption.");
+                Vec::new()
+            },
+        };
         println!("");
         println!("{:?}", depadded);
         
@@ -202,6 +339,9 @@ pub fn run(){
 
         println!("Congratulations, you have encrypted and decrypted a message using RSA assymetric encryption.");
 
+        // Optional chapters on how keys like this one are broken.
+        chapters::attack_chapters(&myrsainfo);
+
 
         // Simple match for checking with user, if they type a variation of y in, process restarts.
 
@@ -221,17 +361,41 @@ pub fn run(){
     println!("Thanks for using the RSA encryption process app.");
 }
 
+// Runs the same steps as the tutorial without printing them: pad, convert to
+// integers, avoid fixed points and encrypt with the public key. None if the
+// key leaves some block of the message unchanged. Stored keys should go
+// through Keystore::encrypt and Keystore::decrypt, which refuse revoked and
+// expired keys.
+pub fn encrypt_message(key:&keys::RSAPublicKey, message:&[u8]) -> Option<Vec<u8>>{
+    let blocks = match get_integers(&pad_the_bytes(message.to_vec())){
+        Ok(s) => s,
+        Err(_) => return None,
+    };
+    let blocks = avoid_fixed_points(blocks, key.get_e(), key.get_n())?;
+    Some(getbytes(encryption_process(blocks, key.get_e(), key.get_n())))
+}
+
+// Reverses encrypt_message with the private key. None if the data is not a
+// whole number of blocks, a block is not below the modulus or the padding is
+// wrong, which is what a different key gives.
+pub fn decrypt_message(key:&keys::RSAPrivateKey, data:&[u8]) -> Option<Vec<u8>>{
+    let blocks = match get_integers(data){
+        Ok(s) => s,
+        Err(_) => return None,
+    };
+    if blocks.iter().any(|b| *b >= key.get_n()){
+        return None;
+    }
+    depad_the_bytes(getbytes(encryption_process(blocks, key.get_d(), key.get_n())))
+}
+
 // Get bytes turns vector of u64 (8 bytes) into byte vector.
 fn getbytes(input:Vec<u64>) -> Vec<u8> {
-    let mut result:Vec<u8> = Vec::new();
-    for i in input.into_iter(){
-        let temp = i.to_le_bytes();
-        for j in temp{
-            result.push(j);
-        }
+    // 8 byte blocks can hold any u64, so this never fails.
+    match codec::BlockCodec::default().to_bytes(&input){
+        Ok(s) => s,
+        Err(e) => panic!("Error converting integers to bytes. {}", e),
     }
-
-    result
 }
 
 // Main function which takes a vector of u64 and applies exponent and modulus then returns.
@@ -248,7 +412,7 @@ fn encryption_process(message:Vec<u64>, exp:u64, modulus:u64) -> Vec<u64> {
             let atom1 = atomicinfo.clone();
             thread::spawn(move ||{
                 let ret = modded_exponent(atom1[place], exp, modulus);
-                tx1.send((place, ret)).unwrap();
+                _ = tx1.send((place, ret));
             });
             place += 1;
         }
@@ -258,7 +422,7 @@ fn encryption_process(message:Vec<u64>, exp:u64, modulus:u64) -> Vec<u64> {
             let atom2 = atomicinfo.clone();
             thread::spawn(move ||{
                 let ret = modded_exponent(atom2[place], exp, modulus);
-                tx2.send((place, ret)).unwrap();
+                _ = tx2.send((place, ret));
             });
             place += 1;
         }
@@ -268,7 +432,7 @@ fn encryption_process(message:Vec<u64>, exp:u64, modulus:u64) -> Vec<u64> {
             let atom3 = atomicinfo.clone();
             thread::spawn(move ||{
                 let ret = modded_exponent(atom3[place], exp, modulus);
-                tx3.send((place, ret)).unwrap();
+                _ = tx3.send((place, ret));
             });
             place += 1;
         }
@@ -278,7 +442,7 @@ fn encryption_process(message:Vec<u64>, exp:u64, modulus:u64) -> Vec<u64> {
             let atom4 = atomicinfo.clone();
             thread::spawn(move ||{
                 let ret = modded_exponent(atom4[place], exp, modulus);
-                tx4.send((place, ret)).unwrap();
+                _ = tx4.send((place, ret));
             });
             place += 1;
         }
@@ -295,68 +459,105 @@ fn encryption_process(message:Vec<u64>, exp:u64, modulus:u64) -> Vec<u64> {
     result
 }
 
-// Converts bytes into vector of u64 integers.
-fn get_integers(message: &[u8]) -> Vec<u64> {
-    let mut list:Vec<u64> = Vec::new();
+// Converts bytes into vector of u64 integers, 8 little endian bytes
+// per integer. Returns an error if the length isn't a multiple of 8.
+fn get_integers(message: &[u8]) -> Result<Vec<u64>, codec::CodecError> {
+    codec::BlockCodec::default().to_blocks(message)
+}
 
-    let len = message.len();
-    let mut start = 0;
-    let mut end = 8;
-    loop{
-        let mut eight:[u8;8] = [0;8];
-        let section:Vec<&u8> = message.iter().skip(start).take(end).collect();
-        for i in 0..8{
-            eight[i] = *section[i];
+// Number of message bytes carried in each 8 byte block, the last
+// (most significant) byte of the block is the length header.
+const BLOCK_DATA_BYTES: usize = 7;
+
+// Header byte layout, the low 3 bits hold the length and the next 2 bits
+// are a tweak used to move a block off a fixed point. The top 3 bits are
+// always 0 so every block stays below 2^61.
+const HEADER_LENGTH_MASK: u8 = 0b0000_0111;
+const HEADER_TWEAK_MASK: u8 = 0b0001_1000;
+const HEADER_TWEAK_SHIFT: u32 = 3;
+
+// Removes the padding by reading the header byte of every block and keeping
+// that many message bytes. Returns None if the bytes were not produced by
+// pad_the_bytes, for example after decrypting with the wrong key.
+fn depad_the_bytes(paddedbytes:Vec<u8>) -> Option<Vec<u8>>{
+    if !paddedbytes.len().is_multiple_of(BLOCK_DATA_BYTES + 1){
+        return None;
+    }
+    let blocks = paddedbytes.len() / (BLOCK_DATA_BYTES + 1);
+    let mut result:Vec<u8> = Vec::with_capacity(blocks * BLOCK_DATA_BYTES);
+
+    for (index, block) in paddedbytes.chunks(BLOCK_DATA_BYTES + 1).enumerate(){
+        let header = block[BLOCK_DATA_BYTES];
+        if header & !(HEADER_LENGTH_MASK | HEADER_TWEAK_MASK) != 0{
+            return None;
         }
-        list.push(u64::from_le_bytes(eight));
-        start +=8;
-        end +=8;
-        if end > len{
-            break;
+        // The tweak bits are ignored, only the length matters.
+        let used = (header & HEADER_LENGTH_MASK) as usize;
+        // Every block but the last must be full, and no block is ever empty.
+        let last = index + 1 == blocks;
+        if used == 0 || used > BLOCK_DATA_BYTES || (!last && used != BLOCK_DATA_BYTES){
+            return None;
+        }
+        // Unused bytes in the last block are always zero.
+        if block[used..BLOCK_DATA_BYTES].iter().any(|b| *b != 0){
+            return None;
         }
+        result.extend_from_slice(&block[..used]);
     }
-    list
-}
 
-// Removes the added bytes throughout the message and those at end.
-// Bytes at end are added to make vector a multiple of 8.
-fn depad_the_bytes(rawbytes:Vec<u8>) -> Vec<u8>{
-    let mut result:Vec<u8> = Vec::new();
+    Some(result)
+}
 
-    for i in 0..rawbytes.len(){
-        if i == 0 || (i + 1) % 8 != 0{
-            result.push(rawbytes[i]);
-        }
-    }
+// Splits the message into 7 byte pieces and stores each in an 8 byte block,
+// the most significant byte of the block holds how many message bytes the block
+// carries (1 to 7) and unused bytes in the last block are zero. Since every block
+// records its own length, zero bytes at the end of the message survive depadding,
+// and the small header keeps each integer well below the modulus.
+fn pad_the_bytes(rawbytes: Vec<u8>) -> Vec<u8> {
+    let mut result: Vec<u8> = Vec::with_capacity(rawbytes.len().div_ceil(BLOCK_DATA_BYTES) * (BLOCK_DATA_BYTES + 1));
 
-    while Some(&0) == result.last(){
-        result.pop();
+    for chunk in rawbytes.chunks(BLOCK_DATA_BYTES){
+        let mut block = [0u8; BLOCK_DATA_BYTES + 1];
+        block[..chunk.len()].copy_from_slice(chunk);
+        block[BLOCK_DATA_BYTES] = chunk.len() as u8;
+        result.extend_from_slice(&block);
     }
 
     result
 }
-// Adds a byte everry 7 bytes to add randomness and keep
-// integer value lower (most sig byte is rotated between 0 and 3).
-// Bytes added to end to create multiple of 8. So that the message
-// can be converted into array of u64 integers.
-fn pad_the_bytes(rawbytes: Vec<u8>) -> Vec<u8> {
-    let mut result: Vec<u8> = Vec::new();
 
-    for i in 0..rawbytes.len(){
-       
-        if i != 0 && i % 7 == 0{
-            let byte:u8 = (i % 4) as u8;
-            result.push(byte);
+// RSA leaves some integers unchanged (x ^ e mod n = x), 0, 1 and n - 1 always,
+// plus a few others that depend on p and q. An unchanged block would show the
+// plaintext in the ciphertext. The non zero header byte already rules out 0 and 1,
+// any other fixed point has its tweak bits changed until it encrypts to something
+// else. Returns None if a block is too large for the modulus or every tweak is a
+// fixed point, which only happens for keys that leave everything unchanged.
+fn avoid_fixed_points(blocks:Vec<u64>, exp:u64, modulus:u64) -> Option<Vec<u64>>{
+    let tweak_bits = (HEADER_TWEAK_MASK as u64) << (BLOCK_DATA_BYTES * 8);
+    let tweak_one = 1u64 << (BLOCK_DATA_BYTES * 8 + HEADER_TWEAK_SHIFT as usize);
+    let mut result:Vec<u64> = Vec::with_capacity(blocks.len());
+
+    for block in blocks{
+        let mut candidate = block;
+        let mut tries = 0;
+        loop{
+            if candidate < 2 || candidate >= modulus.saturating_sub(1){
+                return None;
+            }
+            if modded_exponent(candidate, exp, modulus) != candidate{
+                break;
+            }
+            tries += 1;
+            if tries > HEADER_TWEAK_MASK >> HEADER_TWEAK_SHIFT{
+                return None;
+            }
+            // Step the tweak, wrapping within its two bits.
+            candidate = (candidate & !tweak_bits) | (candidate.wrapping_add(tweak_one) & tweak_bits);
         }
-        
-        result.push(*rawbytes.get(i).unwrap());
+        result.push(candidate);
     }
 
-    while result.len() % 8 != 0{
-        result.push(0);
-    }
-
-    result
+    Some(result)
 }
 
 // Convert utf8 string into vector of bytes.
@@ -365,6 +566,161 @@ fn convert_raw_bytes(input: &str) -> Vec<u8>{
     bytes
 }
 
+// Loads a key from the keystore by name, or a PKCS#1, PKCS#8 or passphrase
+// protected PEM private key file by path, into the structure. Prints the
+// problem and returns false if the key can't be used.
+fn load_key(nameorpath:&str, info:&mut structures::RSAInfo) -> bool{
+    let result = match keystore::Keystore::open_default(){
+        Ok(store) if store.contains(nameorpath) => load_stored_key(&store, nameorpath),
+        _ => load_key_file(nameorpath),
+    };
+    match result{
+        Ok(key) => {
+            *info = key.to_rsainfo();
+            println!("Loaded the key with modulus {}, e is {} and d is {}.", info.get_n(), info.get_e(), info.get_d());
+            true
+        },
+        Err(e) => {
+            println!("Unable to load the key {}. Error: {}", nameorpath, e);
+            println!("Lets create a new key instead.");
+            false
+        },
+    }
+}
+
+// The tutorial encrypts with the key, so expired, revoked and rotated keys are refused.
+fn load_stored_key(store:&keystore::Keystore, name:&str) -> Result<keys::RSAPrivateKey, keystore::KeystoreError>{
+    if store.check(name, keystore::KeyOperation::Encrypt)?.encrypted{
+        ask_passphrase(|p| store.private_key(name, Some(p)))
+    }else{
+        store.private_key(name, None)
+    }
+}
+
+fn load_key_file(path:&str) -> Result<keys::RSAPrivateKey, keystore::KeystoreError>{
+    let text = match std::fs::read_to_string(path){
+        Ok(s) => s,
+        Err(e) => return Err(keystore::KeystoreError::Io(path.to_string(), e.to_string())),
+    };
+    if keyfile::is_encrypted(&text){
+        ask_passphrase(|p| Ok(keyfile::decrypt_private_key(&text, p)?))
+    }else{
+        Ok(keys::RSAPrivateKey::from_pem(&text)?)
+    }
+}
+
+// Number of times the passphrase can be typed before loading gives up.
+const PASSPHRASE_ATTEMPTS: usize = 3;
+
+// Asks for the passphrase of an encrypted key until it is right or the
+// attempts run out.
+fn ask_passphrase<F>(decrypt:F) -> Result<keys::RSAPrivateKey, keystore::KeystoreError>
+where F: Fn(&str) -> Result<keys::RSAPrivateKey, keystore::KeystoreError>{
+    println!("This key is protected by a passphrase.");
+    let mut attempt = 1;
+    loop{
+        println!("Type the passphrase.");
+        let passphrase = get_user_string();
+        println!("Deriving the key from the passphrase, this takes a moment.");
+        match decrypt(&passphrase){
+            Err(keystore::KeystoreError::KeyFile(keyfile::KeyFileError::WrongPassphrase)) if attempt < PASSPHRASE_ATTEMPTS => {
+                println!("That passphrase is not right, try again.");
+                attempt += 1;
+            },
+            result => return result,
+        }
+    }
+}
+
+// Offers to save the key in the keystore under a name, so it can be loaded
+// again here or used with the keys, encrypt and decrypt commands. With a
+// passphrase the private key is encrypted.
+fn save_key(info:&structures::RSAInfo){
+    let store = match keystore::Keystore::open_default(){
+        Ok(s) => s,
+        Err(e) => {
+            println!("This key can't be saved. Error: {}", e);
+            return;
+        },
+    };
+    println!("Type a name to save this key in the keystore at {}, or press enter to skip.", store.dir().display());
+    let name = get_user_string();
+    if name.is_empty(){
+        return;
+    }
+    let key = match keys::RSAPrivateKey::from_rsainfo(info){
+        Ok(s) => s,
+        Err(e) => {
+            println!("Unable to save this key. Error: {}", e);
+            return;
+        },
+    };
+    println!("Type a passphrase to protect the private key, or press enter to save it unencrypted.");
+    println!("Without a passphrase anyone who can read the file can read d, p and q.");
+    let passphrase = get_user_string();
+    if !passphrase.is_empty(){
+        println!("Type the passphrase again.");
+        if get_user_string() != passphrase{
+            println!("The passphrases don't match, the key was not saved.");
+            return;
+        }
+        println!("Deriving the key from the passphrase, this takes a moment.");
+    }
+    let passphrase = if passphrase.is_empty() {None} else {Some(passphrase.as_str())};
+    match store.save(&name, &key, KEY_COMMENT, passphrase){
+        Ok(metadata) => println!("Saved the key as {}, its fingerprint is {}.", metadata.name, metadata.fingerprint),
+        Err(e) => println!("Unable to save this key. Error: {}", e),
+    }
+}
+
+// Comment put on OpenSSH public key lines and saved keys.
+const KEY_COMMENT: &str = "rsaencryptiontutorial";
+
+// Prints the public key in OpenSSH format and its fingerprint in the ways
+// it can be compared, so two keys can be told apart without reading n and e.
+fn show_public_key(info:&structures::RSAInfo){
+    let key = keys::RSAPublicKey::new(info.get_n(), info.get_e());
+    let fingerprint = fingerprint::Fingerprint::of(&key);
+    println!("");
+    println!("Here is the public key in OpenSSH format:");
+    println!("{}", ssh::to_openssh_line(&key, KEY_COMMENT));
+    println!("and its fingerprint, the same way ssh-keygen -lf shows it:");
+    println!("{}", ssh::fingerprint_line(&key, KEY_COMMENT));
+    println!("");
+    println!("The fingerprint is the SHA-256 hash of the public key, any change to n or e gives a different one.");
+    println!("In hex it is {}", fingerprint.to_hex());
+    println!("and the short ID, the first 8 bytes, is {}.", fingerprint.short_id());
+    println!("ssh-keygen -lv draws it as a picture, which is easier to remember than the digits:");
+    print!("{}", fingerprint.randomart("RSA", 64 - key.get_n().leading_zeros()));
+}
+
+// Encrypts the blocks with a new key for each number of primes, and times
+// decrypting them with c ^ d mod n and with the CRT.
+fn compare_prime_counts(blocks:&[u64], offset:u64){
+    for count in 2..=multiprime::MAX_PRIMES{
+        let key = match multiprime::RSAMultiPrimeKey::generate(count, offset){
+            Ok(s) => s,
+            Err(e) => {
+                println!("Unable to make a key with {} primes. Error: {}", count, e);
+                continue;
+            },
+        };
+        let encrypted = encryption_process(blocks.to_vec(), key.get_e(), key.get_n());
+
+        let start = Instant::now();
+        let plain = encryption_process(encrypted.clone(), key.get_d(), key.get_n());
+        let direct = start.elapsed();
+        let start = Instant::now();
+        let crt:Vec<u64> = encrypted.iter().map(|c| key.decrypt_block(*c)).collect();
+        let chinese = start.elapsed();
+
+        println!("");
+        println!("{} primes {:?}", count, key.get_primes());
+        println!("c ^ d mod n took {:?}, the CRT took {:?}, same result: {}", direct, chinese, plain == crt && crt == blocks);
+    }
+    println!("");
+}
+
 // Ask user for string to process.
 fn get_user_string() -> String  {
     let mut input = String::new();
@@ -376,15 +732,16 @@ fn get_user_string() -> String  {
 }
 
 // Modded exponentiation, keeps values low by applying modulus each cycle.
-// Also uses logic to increase speed.
+// Also uses logic to increase speed. A base of 0 gives 0, an exponent of 0
+// gives 1 and a modulus of 1 gives 0, only a modulus of 0 is not allowed.
 fn modded_exponent(base:u64, exp:u64, modulus:u64) -> u64{
-    if base == 0u64 || exp == 0u64 || modulus == 0u64{
-        panic!("Cannot perform exponents on 0.");
+    if modulus == 0u64{
+        panic!("Cannot perform modulus by 0.");
     }
     let  base1 = base as u128;
-    let mut result = 1u128;
     let exp = exp as u128;
     let modulus = modulus as u128;
+    let mut result = 1u128 % modulus;
     let mut base1 = base1 % modulus;
     let mut exp = exp;
 
@@ -441,23 +798,125 @@ fn test_modded_exponent(){
 
 #[test]
 fn test_modded_exponent_panic(){
-    let result = panic::catch_unwind(|| modded_exponent(0, 5, 66));
+    let result = panic::catch_unwind(|| modded_exponent(5, 5, 0));
     assert!(result.is_err());
 }
 
+#[test]
+fn test_modded_exponent_zero_and_one(){
+    assert_eq!(0, modded_exponent(0, 5, 66));
+    assert_eq!(1, modded_exponent(1, 5, 66));
+    assert_eq!(1, modded_exponent(0, 0, 66));
+    assert_eq!(1, modded_exponent(7, 0, 66));
+    assert_eq!(0, modded_exponent(7, 5, 1));
+    assert_eq!(0, modded_exponent(7, 0, 1));
+
+    // A block of all zeros encrypts and decrypts without panicking.
+    let zeros = get_integers(&[0u8; 8]).unwrap();
+    let encrypted = encryption_process(zeros.clone(), 92829719, 18446743557747632291);
+    assert_eq!(zeros, encrypted);
+}
+
+#[test]
+fn test_avoid_fixed_points(){
+    let e = 92829719u64;
+    let n = 18446743557747632291u64;
+    let d = 9419014239140821679u64;
+
+    // 1 mod p and -1 mod q, a fixed point below 2^61.
+    let fixed = 2037575750855781815u64;
+    assert_eq!(fixed, modded_exponent(fixed, e, n));
+
+    let moved = avoid_fixed_points(vec![fixed], e, n).unwrap();
+    assert_ne!(fixed, moved[0]);
+    assert_ne!(moved[0], modded_exponent(moved[0], e, n));
+    // Only the tweak bits changed, so depadding gives the same bytes.
+    assert_eq!(fixed >> 61, moved[0] >> 61);
+    assert_eq!(fixed & ((1 << 59) - 1), moved[0] & ((1 << 59) - 1));
+
+    // Padded blocks pass through untouched and decrypt to the same message.
+    let message = b"no fixed points here".to_vec();
+    let blocks = get_integers(&pad_the_bytes(message.clone())).unwrap();
+    let checked = avoid_fixed_points(blocks.clone(), e, n).unwrap();
+    assert_eq!(blocks, checked);
+    let decrypted = encryption_process(encryption_process(checked, e, n), d, n);
+    assert_eq!(Some(message), depad_the_bytes(getbytes(decrypted)));
+
+    // A tweaked header still depads to the original bytes.
+    let mut tweaked = pad_the_bytes(vec![1, 2, 3]);
+    tweaked[7] |= 0b0001_0000;
+    assert_eq!(Some(vec![1, 2, 3]), depad_the_bytes(tweaked));
+
+    // An exponent of 1 leaves every block unchanged.
+    assert_eq!(None, avoid_fixed_points(blocks, 1, n));
+}
+
 
 #[test]
 fn test_pad_the_bytes(){
    
     let bytes = [211,29,99,21,94,74,10,7,92,19,49,182,29,99,21,94,74,10,7,92,19,49,182,29,99,21,94,74,10,7,92,19,49,182];
     let newbytes = pad_the_bytes(bytes.to_vec());
-    let newbytes1 = vec![211, 29, 99, 21, 94, 74, 10, 3, 7, 92, 19, 49, 182, 29, 99, 2, 21, 94, 74, 10, 7, 92, 19, 1, 49, 182, 29, 99, 21, 94, 74, 0, 10, 7, 92, 19, 49, 182, 0, 0];
+    let newbytes1 = vec![211, 29, 99, 21, 94, 74, 10, 7, 7, 92, 19, 49, 182, 29, 99, 7, 21, 94, 74, 10, 7, 92, 19, 7, 49, 182, 29, 99, 21, 94, 74, 7, 10, 7, 92, 19, 49, 182, 0, 6];
     let reversed = depad_the_bytes(newbytes.clone());
     assert_eq!(newbytes, newbytes1);
-    assert_eq!(bytes.to_vec(), reversed);
+    assert_eq!(Some(bytes.to_vec()), reversed);
 
 }
 
+#[test]
+fn test_pad_the_bytes_trailing_zeros(){
+    let bytes = vec![1, 0, 0, 0, 0, 0, 0, 0, 0];
+    let padded = pad_the_bytes(bytes.clone());
+    assert_eq!(padded, vec![1, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 2]);
+    assert_eq!(Some(bytes), depad_the_bytes(padded));
+
+    assert!(pad_the_bytes(Vec::new()).is_empty());
+    assert_eq!(Some(Vec::new()), depad_the_bytes(Vec::new()));
+}
+
+#[test]
+fn test_depad_the_bytes_rejects_malformed(){
+    // Not a multiple of 8.
+    assert_eq!(None, depad_the_bytes(vec![1, 2, 3]));
+    // Header of zero.
+    assert_eq!(None, depad_the_bytes(vec![1, 0, 0, 0, 0, 0, 0, 0]));
+    // Header larger than a block can hold.
+    assert_eq!(None, depad_the_bytes(vec![1, 0, 0, 0, 0, 0, 0, 8]));
+    // Short block before the last one.
+    assert_eq!(None, depad_the_bytes(vec![1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 1]));
+    // Data after the length given in the header.
+    assert_eq!(None, depad_the_bytes(vec![1, 0, 0, 9, 0, 0, 0, 1]));
+}
+
+#[test]
+fn test_pad_the_bytes_round_trip_random(){
+    // Small xorshift generator so the inputs are random looking but repeatable.
+    let mut state = 0x2545f4914f6cdd1du64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for len in 0..=64usize{
+        for _ in 0..32{
+            let mut bytes:Vec<u8> = (0..len).map(|_| next() as u8).collect();
+            // Force some inputs to end in zero bytes, the case the old padding lost.
+            if next() % 3 == 0{
+                let zeros = (next() as usize) % (len + 1);
+                for b in bytes.iter_mut().rev().take(zeros){
+                    *b = 0;
+                }
+            }
+            let padded = pad_the_bytes(bytes.clone());
+            assert_eq!(0, padded.len() % 8);
+            assert_eq!(len.div_ceil(7) * 8, padded.len());
+            assert_eq!(Some(bytes), depad_the_bytes(padded));
+        }
+    }
+}
 
 #[test]
 fn test_getbytes(){
@@ -481,5 +940,22 @@ fn test_getintegers(){
     let m = [244,192,99,05,22,200,122,55,217,192,3,05,22,2,222,55,244,192,99,05,122,0,122,66];
     let g = get_integers(&m);
     let base:Vec<u64> = vec![3997727616149995764, 4025657410512470233, 4790141677738377460];
-    assert_eq!(g, base);
-}
\ No newline at end of file
+    assert_eq!(g, Ok(base));
+
+    assert_eq!(get_integers(&[]), Ok(Vec::new()));
+    assert!(get_integers(&m[..20]).is_err());
+}
+#[test]
+fn test_encrypt_message_round_trip(){
+    let key = keys::test_key();
+    let message = b"Hello, keystore \x00\xff";
+    let encrypted = encrypt_message(&key.public_key(), message).unwrap();
+    assert_eq!(encrypted.len(), 24);
+    assert_eq!(decrypt_message(&key, &encrypted), Some(message.to_vec()));
+
+    // A different key, a block at or above the modulus and a partial block are all rejected.
+    let other = keys::other_test_key();
+    assert_ne!(decrypt_message(&other, &encrypted), Some(message.to_vec()));
+    assert_eq!(decrypt_message(&key, &[0xff; 8]), None);
+    assert_eq!(decrypt_message(&key, &encrypted[..20]), None);
+}
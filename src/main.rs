@@ -1,6 +1,12 @@
 
-// Main entry point of application, calls library after greeting.
+// Main entry point of application, calls library after greeting. With
+// arguments it runs a single command on the keystore instead.
 fn main() {
+    let args:Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty(){
+        std::process::exit(rsaencryptiontutorial::cli::run(&args));
+    }
+
     println!("");
     println!("-----------------------------------------------------------------");
     println!("-----------------------------------------------------------------");
@@ -0,0 +1,374 @@
+// Multi-prime RSA (RFC 8017 section 3.2), a modulus made of more than two
+// primes. The public key looks the same as any other, only the private key
+// knows n has more factors.
+//
+// Decryption with the private exponent works on numbers the size of n. The
+// Chinese remainder theorem (CRT) lets it work modulo each prime instead,
+// c ^ (d mod (r - 1)) mod r for every prime r, and then joins the results
+// back into one number mod n. For real keys, made of many machine words, the
+// cost of modular exponentiation grows with the cube of the size of the
+// modulus, so with k primes each of the k exponentiations is about k^3 times
+// cheaper, k^2 times overall.
+//
+// The tutorial's n is only 64 bits, so the gain here comes from the word
+// size instead. Products of numbers below n need 128 bit arithmetic, which
+// is much slower to divide than 64 bit, while a prime below 2^32 can be
+// worked with in 64 bits. A 2 prime key usually has one prime above 2^32,
+// 3 and 4 prime keys never do. More primes also make them smaller, 16 bits
+// at 4, small enough to find by trial division, so the number of primes is
+// limited by the size of n.
+//
+// d is the inverse of e modulo the Carmichael function, the lcm of r - 1 for
+// every prime, as RFC 8017 asks. The totient, the product of r - 1, is given
+// for comparison.
+
+use crate::der::{self, DerReader};
+use crate::keys::{KeyError, RSAPublicKey, PKCS1_PRIVATE_LABEL};
+use crate::pem;
+use crate::utility;
+use std::fmt;
+
+// Largest number of primes generate makes, each of them 16 bits at 4.
+pub const MAX_PRIMES: u32 = 4;
+
+// A private key with two or more primes, r_1 = p and r_2 = q as in RFC 8017.
+// Each prime r_i has its CRT exponent d mod (r_i - 1), the coefficients are
+// q^-1 mod p for the second prime and (r_1 * ... * r_(i-1))^-1 mod r_i for
+// the rest. The first prime has no coefficient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RSAMultiPrimeKey{
+    n:u64,
+    e:u64,
+    d:u64,
+    primes:Vec<u64>,
+    exponents:Vec<u64>,
+    coefficients:Vec<u64>,
+}
+
+impl RSAMultiPrimeKey{
+    // Builds a key from its primes and e, working out d modulo the Carmichael
+    // function and the CRT values.
+    pub fn from_primes(primes:&[u64], e:u64) -> Result<RSAMultiPrimeKey, KeyError>{
+        if primes.len() < 2{
+            return Err(KeyError::Inconsistent("a key needs at least two primes"));
+        }
+        if primes.iter().any(|p| *p < 3){
+            return Err(KeyError::Inconsistent("primes must be odd"));
+        }
+        for (index, prime) in primes.iter().enumerate(){
+            if primes[..index].contains(prime){
+                return Err(KeyError::Inconsistent("primes must all be different"));
+            }
+        }
+        if primes.iter().try_fold(1u64, |n, p| n.checked_mul(*p)).is_none(){
+            return Err(KeyError::Inconsistent("the product of the primes does not fit in 64 bits"));
+        }
+        let lambda = carmichael(primes);
+        let d = match utility::mod_inverse(e, lambda){
+            Some(s) => s,
+            None => return Err(KeyError::Inconsistent("e has no inverse modulo lambda(n)")),
+        };
+        RSAMultiPrimeKey::from_parts(primes, e, d)
+    }
+
+    // Builds a key from primes, e and d, checking d works with e. d may be
+    // taken modulo either the totient or the Carmichael function.
+    pub fn from_parts(primes:&[u64], e:u64, d:u64) -> Result<RSAMultiPrimeKey, KeyError>{
+        if primes.len() < 2 || primes.iter().any(|p| *p < 3){
+            return Err(KeyError::Inconsistent("a key needs at least two odd primes"));
+        }
+        let n = match primes.iter().try_fold(1u64, |n, p| n.checked_mul(*p)){
+            Some(s) => s,
+            None => return Err(KeyError::Inconsistent("the product of the primes does not fit in 64 bits")),
+        };
+        if (e as u128 * d as u128) % carmichael(primes) as u128 != 1{
+            return Err(KeyError::Inconsistent("d is not the inverse of e"));
+        }
+        let exponents:Vec<u64> = primes.iter().map(|p| d % (p - 1)).collect();
+        let mut coefficients:Vec<u64> = Vec::with_capacity(primes.len() - 1);
+        // q^-1 mod p, then the product of the primes before each one, inverted.
+        let second = match utility::mod_inverse(primes[1], primes[0]){
+            Some(s) => s,
+            None => return Err(KeyError::Inconsistent("primes must all be different")),
+        };
+        coefficients.push(second);
+        let mut product = primes[0] as u128 * primes[1] as u128;
+        for prime in &primes[2..]{
+            match utility::mod_inverse((product % *prime as u128) as u64, *prime){
+                Some(s) => coefficients.push(s),
+                None => return Err(KeyError::Inconsistent("primes must all be different")),
+            }
+            product *= *prime as u128;
+        }
+        Ok(RSAMultiPrimeKey{ n, e, d, primes: primes.to_vec(), exponents, coefficients })
+    }
+
+    // Makes a new key with count primes and e = 65537, the primes searched for
+    // from a point picked by the offset.
+    pub fn generate(count:u32, offset:u64) -> Result<RSAMultiPrimeKey, KeyError>{
+        if !(2..=MAX_PRIMES).contains(&count){
+            return Err(KeyError::Inconsistent("the number of primes must be from 2 to 4"));
+        }
+        RSAMultiPrimeKey::from_primes(&utility::generate_primes(count, offset, utility::DEFAULT_E), utility::DEFAULT_E)
+    }
+
+    pub fn public_key(&self) -> RSAPublicKey{
+        RSAPublicKey::new(self.n, self.e)
+    }
+
+    pub fn get_n(&self) -> u64{
+        self.n
+    }
+
+    pub fn get_e(&self) -> u64{
+        self.e
+    }
+
+    pub fn get_d(&self) -> u64{
+        self.d
+    }
+
+    pub fn get_primes(&self) -> &[u64]{
+        &self.primes
+    }
+
+    // d mod (r - 1) for each prime.
+    pub fn get_exponents(&self) -> &[u64]{
+        &self.exponents
+    }
+
+    // One for each prime after the first.
+    pub fn get_coefficients(&self) -> &[u64]{
+        &self.coefficients
+    }
+
+    // Decrypts one block with the CRT, RFC 8017 section 5.1.2 step 2b.
+    // m_i = c ^ d_i mod r_i for each prime, then
+    // m = m_2 + q * ((m_1 - m_2) * qInv mod p), and for each further prime
+    // m = m + R * ((m_i - m) * t_i mod r_i) where R is the product of the
+    // primes already used.
+    pub fn decrypt_block(&self, block:u64) -> u64{
+        let (p, q) = (self.primes[0] as u128, self.primes[1] as u128);
+        let m_1 = power_mod(block, self.exponents[0], self.primes[0]) as u128;
+        let m_2 = power_mod(block, self.exponents[1], self.primes[1]) as u128;
+        let h = ((m_1 + p - m_2 % p) * self.coefficients[0] as u128) % p;
+        let mut m = m_2 + q * h;
+        let mut product = p * q;
+        for i in 2..self.primes.len(){
+            let r = self.primes[i] as u128;
+            let m_i = power_mod(block, self.exponents[i], self.primes[i]) as u128;
+            let h = ((m_i + r - m % r) * self.coefficients[i - 1] as u128) % r;
+            m += product * h;
+            product *= r;
+        }
+        m as u64
+    }
+
+    // Same as crate::decrypt_message but block by block with the CRT.
+    pub fn decrypt_message(&self, data:&[u8]) -> Option<Vec<u8>>{
+        let blocks = match crate::get_integers(data){
+            Ok(s) => s,
+            Err(_) => return None,
+        };
+        if blocks.iter().any(|b| *b >= self.n){
+            return None;
+        }
+        crate::depad_the_bytes(crate::getbytes(blocks.iter().map(|b| self.decrypt_block(*b)).collect()))
+    }
+
+    // RSAPrivateKey ::= SEQUENCE { version INTEGER, modulus, publicExponent,
+    //     privateExponent, prime1, prime2, exponent1, exponent2, coefficient,
+    //     otherPrimeInfos SEQUENCE OF SEQUENCE { prime, exponent, coefficient } OPTIONAL }
+    // The version is 1 when there are other primes and 0 for two.
+    pub fn to_pkcs1_der(&self) -> Vec<u8>{
+        let version = if self.primes.len() > 2 {1} else {0};
+        let mut fields = vec![
+            der::encode_integer(version),
+            der::encode_integer(self.n),
+            der::encode_integer(self.e),
+            der::encode_integer(self.d),
+            der::encode_integer(self.primes[0]),
+            der::encode_integer(self.primes[1]),
+            der::encode_integer(self.exponents[0]),
+            der::encode_integer(self.exponents[1]),
+            der::encode_integer(self.coefficients[0]),
+        ];
+        if self.primes.len() > 2{
+            let others:Vec<Vec<u8>> = (2..self.primes.len()).map(|i| der::encode_sequence(&[
+                der::encode_integer(self.primes[i]),
+                der::encode_integer(self.exponents[i]),
+                der::encode_integer(self.coefficients[i - 1]),
+            ])).collect();
+            fields.push(der::encode_sequence(&others));
+        }
+        der::encode_sequence(&fields)
+    }
+
+    pub fn from_pkcs1_der(data:&[u8]) -> Result<RSAMultiPrimeKey, KeyError>{
+        let mut outer = DerReader::new(data);
+        let mut seq = outer.read_sequence()?;
+        let version = seq.read_integer()?;
+        if version > 1{
+            return Err(KeyError::UnsupportedVersion(version));
+        }
+        let mut values = [0u64; 8];
+        for value in values.iter_mut(){
+            *value = seq.read_integer()?;
+        }
+        let [n, e, d, p, q, dp, dq, qinv] = values;
+        let mut primes = vec![p, q];
+        let mut exponents = vec![dp, dq];
+        let mut coefficients = vec![qinv];
+        if version == 1{
+            let mut others = seq.read_sequence()?;
+            if others.is_empty(){
+                return Err(KeyError::Inconsistent("a version 1 key must have other primes"));
+            }
+            while !others.is_empty(){
+                let mut info = others.read_sequence()?;
+                primes.push(info.read_integer()?);
+                exponents.push(info.read_integer()?);
+                coefficients.push(info.read_integer()?);
+                info.finish()?;
+            }
+        }
+        seq.finish()?;
+        outer.finish()?;
+
+        let key = RSAMultiPrimeKey::from_parts(&primes, e, d)?;
+        if key.n != n{
+            return Err(KeyError::Inconsistent("modulus is not the product of the primes"));
+        }
+        if key.exponents != exponents || key.coefficients != coefficients{
+            return Err(KeyError::Inconsistent("CRT values do not match the primes"));
+        }
+        Ok(key)
+    }
+
+    pub fn to_pkcs1_pem(&self) -> String{
+        pem::encode(PKCS1_PRIVATE_LABEL, &self.to_pkcs1_der())
+    }
+
+    pub fn from_pkcs1_pem(text:&str) -> Result<RSAMultiPrimeKey, KeyError>{
+        RSAMultiPrimeKey::from_pkcs1_der(&pem::decode_expected(text, PKCS1_PRIVATE_LABEL)?)
+    }
+}
+
+// Primes joined with " * ", the way the tutorial shows n.
+impl fmt::Display for RSAMultiPrimeKey{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        let primes:Vec<String> = self.primes.iter().map(|p| p.to_string()).collect();
+        write!(f, "{} = {}", self.n, primes.join(" * "))
+    }
+}
+
+// Euler's totient of the product of distinct primes, the product of r - 1.
+pub fn totient(primes:&[u64]) -> u64{
+    primes.iter().map(|p| p - 1).product()
+}
+
+// Carmichael's function of the product of distinct primes, the lcm of r - 1.
+// It divides the totient, so d modulo it is never larger.
+pub fn carmichael(primes:&[u64]) -> u64{
+    primes.iter().fold(1u64, |l, p| l / utility::gcd(l, p - 1) * (p - 1))
+}
+
+// base ^ exp mod modulus, in 64 bit arithmetic when the modulus is below
+// 2^32 so products of two numbers below it fit, otherwise with crate::modded_exponent.
+fn power_mod(base:u64, exp:u64, modulus:u64) -> u64{
+    if modulus > u32::MAX as u64{
+        return crate::modded_exponent(base, exp, modulus);
+    }
+    let mut result = 1 % modulus;
+    let mut base = base % modulus;
+    let mut exp = exp;
+    while exp > 0{
+        if exp & 1 == 1{
+            result = result * base % modulus;
+        }
+        exp >>= 1;
+        base = base * base % modulus;
+    }
+    result
+}
+
+#[cfg(test)]
+#[test]
+fn test_multiprime_key(){
+    // The three largest primes whose product fits in a u64.
+    let primes = [2642239u64, 2642231, 2642203];
+    let key = RSAMultiPrimeKey::from_primes(&primes, 65537).unwrap();
+    assert_eq!(key.get_n(), 18446291336318605427);
+    assert_eq!(key.get_exponents().len(), 3);
+    assert_eq!(key.get_coefficients().len(), 2);
+    assert_eq!((key.get_coefficients()[0] as u128 * 2642231) % 2642239, 1);
+    assert_eq!((key.get_coefficients()[1] as u128 * 2642239 * 2642231) % 2642203, 1);
+    assert_eq!((65537u128 * key.get_d() as u128) % carmichael(&primes) as u128, 1);
+    assert!(key.get_d() < carmichael(&primes));
+
+    for block in [2u64, 12345, 1 << 60, key.get_n() - 2]{
+        let encrypted = crate::modded_exponent(block, 65537, key.get_n());
+        assert_eq!(key.decrypt_block(encrypted), block);
+        assert_eq!(crate::modded_exponent(encrypted, key.get_d(), key.get_n()), block);
+    }
+
+    let message = b"three primes are better than two";
+    let encrypted = crate::encrypt_message(&key.public_key(), message).unwrap();
+    assert_eq!(key.decrypt_message(&encrypted), Some(message.to_vec()));
+    assert_eq!(key.decrypt_message(&encrypted[..20]), None);
+}
+
+#[test]
+fn test_multiprime_functions(){
+    assert_eq!(totient(&[3, 5, 7]), 2 * 4 * 6);
+    assert_eq!(carmichael(&[3, 5, 7]), 12);
+    assert_eq!(carmichael(&[50000000021, 368934871]), 18446743507378697400 / utility::gcd(50000000020, 368934870));
+
+    assert!(RSAMultiPrimeKey::from_primes(&[7], 5).is_err());
+    assert!(RSAMultiPrimeKey::from_primes(&[7, 7, 11], 7).is_err());
+    assert!(RSAMultiPrimeKey::from_primes(&[4294967291, 4294967279, 7], 65537).is_err());
+    // 3 divides 7 - 1, so e = 3 has no inverse.
+    assert!(RSAMultiPrimeKey::from_primes(&[7, 11, 13], 3).is_err());
+    // d taken modulo the totient is accepted too.
+    let d = utility::mod_inverse(65537, totient(&[2642239, 2642231, 2642203])).unwrap();
+    assert!(RSAMultiPrimeKey::from_parts(&[2642239, 2642231, 2642203], 65537, d).is_ok());
+    assert!(RSAMultiPrimeKey::from_parts(&[2642239, 2642231, 2642203], 65537, d + 2).is_err());
+
+    for modulus in [1u64, 7, 2642239, u32::MAX as u64, 50000000021]{
+        assert_eq!(power_mod(123456789012345, 65537, modulus), crate::modded_exponent(123456789012345, 65537, modulus));
+    }
+}
+
+#[test]
+fn test_multiprime_pkcs1(){
+    for count in 2..=MAX_PRIMES{
+        let key = RSAMultiPrimeKey::generate(count, 42).unwrap();
+        assert_eq!(key.get_primes().len(), count as usize);
+        let pem = key.to_pkcs1_pem();
+        assert_eq!(RSAMultiPrimeKey::from_pkcs1_pem(&pem), Ok(key.clone()));
+        // Two prime keys are ordinary version 0 keys.
+        assert_eq!(crate::keys::RSAPrivateKey::from_pkcs1_pem(&pem).is_ok(), count == 2);
+    }
+    assert!(RSAMultiPrimeKey::generate(5, 0).is_err());
+
+    // A coefficient that doesn't match its prime is rejected.
+    let key = RSAMultiPrimeKey::generate(3, 42).unwrap();
+    let mut bad = key.clone();
+    bad.coefficients[1] += 1;
+    assert_eq!(RSAMultiPrimeKey::from_pkcs1_der(&bad.to_pkcs1_der()), Err(KeyError::Inconsistent("CRT values do not match the primes")));
+}
+
+#[test]
+fn test_multiprime_crt(){
+    // The CRT gives the same blocks as c ^ d mod n with 2, 3 and 4 primes,
+    // benches/multiprime.rs times the two.
+    let blocks:Vec<u64> = (1..=2000u64).map(|i| i.wrapping_mul(0x9e3779b97f4a7c15) >> 4).collect();
+    for count in 2..=MAX_PRIMES{
+        let key = RSAMultiPrimeKey::generate(count, 7).unwrap();
+        let encrypted:Vec<u64> = blocks.iter().map(|b| crate::modded_exponent(*b % key.get_n(), key.get_e(), key.get_n())).collect();
+        let plain:Vec<u64> = encrypted.iter().map(|c| crate::modded_exponent(*c, key.get_d(), key.get_n())).collect();
+        let crt:Vec<u64> = encrypted.iter().map(|c| key.decrypt_block(*c)).collect();
+        assert_eq!(plain, crt);
+        assert!(plain.iter().zip(&blocks).all(|(p, b)| *p == *b % key.get_n()));
+    }
+}
@@ -0,0 +1,54 @@
+// PBKDF2 (RFC 8018 section 5.2) with HMAC-SHA256 as the pseudorandom function,
+// used to turn a passphrase into the key that protects a saved private key.
+
+use crate::sha256::{Hmac, DIGEST_LENGTH};
+
+// Derives length bytes from the password and salt. Each block is
+// U1 ^ U2 ^ ... ^ Uc where U1 = HMAC(password, salt || i) and Uj = HMAC(password, Uj-1).
+pub fn pbkdf2_hmac_sha256(password:&[u8], salt:&[u8], iterations:u32, length:usize) -> Vec<u8>{
+    // The password is the HMAC key for every round, so it is only set up once.
+    let keyed = Hmac::new(password);
+    let mut result:Vec<u8> = Vec::with_capacity(length);
+    let mut index = 1u32;
+
+    while result.len() < length{
+        let mut hmac = keyed.clone();
+        hmac.update(salt);
+        hmac.update(&index.to_be_bytes());
+        let mut u = hmac.finalize();
+        let mut block = u;
+
+        for _ in 1..iterations{
+            let mut hmac = keyed.clone();
+            hmac.update(&u);
+            u = hmac.finalize();
+            for (b, x) in block.iter_mut().zip(u.iter()){
+                *b ^= x;
+            }
+        }
+
+        let take = (length - result.len()).min(DIGEST_LENGTH);
+        result.extend_from_slice(&block[..take]);
+        index += 1;
+    }
+    result
+}
+
+#[cfg(test)]
+#[test]
+fn test_pbkdf2_vectors(){
+    // Test vectors from RFC 7914 section 11 and the widely published SHA-256 ones.
+    use crate::sha256::to_hex;
+    assert_eq!(to_hex(&pbkdf2_hmac_sha256(b"passwd", b"salt", 1, 64)),
+        "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783");
+    assert_eq!(to_hex(&pbkdf2_hmac_sha256(b"password", b"salt", 4096, 32)),
+        "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a");
+}
+
+#[test]
+fn test_pbkdf2_truncates(){
+    let long = pbkdf2_hmac_sha256(b"passwd", b"salt", 1, 64);
+    assert_eq!(pbkdf2_hmac_sha256(b"passwd", b"salt", 1, 20), long[..20]);
+    assert_eq!(pbkdf2_hmac_sha256(b"passwd", b"salt", 1, 40), long[..40]);
+    assert!(pbkdf2_hmac_sha256(b"passwd", b"salt", 1, 0).is_empty());
+}
@@ -0,0 +1,150 @@
+// PEM armoring (RFC 7468), base64 encoded DER between BEGIN and END lines.
+
+use crate::base64::{self, Base64Error};
+use std::fmt;
+
+// Errors returned when text isn't a PEM document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PemError{
+    // No -----BEGIN line.
+    MissingBegin,
+    // No matching -----END line.
+    MissingEnd,
+    // Document has a different label than expected.
+    UnexpectedLabel{ expected:String, found:String },
+    // Body is not valid base64.
+    Base64(Base64Error),
+}
+
+impl fmt::Display for PemError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            PemError::MissingBegin => write!(f, "no PEM BEGIN line found"),
+            PemError::MissingEnd => write!(f, "no matching PEM END line found"),
+            PemError::UnexpectedLabel{ expected, found } => write!(f, "expected PEM label \"{}\", found \"{}\"", expected, found),
+            PemError::Base64(e) => write!(f, "invalid PEM body: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PemError{}
+
+impl From<Base64Error> for PemError{
+    fn from(e:Base64Error) -> PemError{
+        PemError::Base64(e)
+    }
+}
+
+// Wraps DER bytes in a PEM document with the given label, 64 characters a line.
+pub fn encode(label:&str, der:&[u8]) -> String{
+    encode_with_headers(label, &[], der)
+}
+
+// Same as encode with "Name: value" header lines (RFC 1421 section 4.4) before
+// the data, separated from it by a blank line.
+pub fn encode_with_headers(label:&str, headers:&[(&str, &str)], der:&[u8]) -> String{
+    let body = base64::encode(der);
+    let mut result = format!("-----BEGIN {}-----\n", label);
+    for (name, value) in headers{
+        result.push_str(&format!("{}: {}\n", name, value));
+    }
+    if !headers.is_empty(){
+        result.push('\n');
+    }
+    for line in body.as_bytes().chunks(64){
+        // Base64 output is always ascii.
+        result.push_str(&String::from_utf8_lossy(line));
+        result.push('\n');
+    }
+    result.push_str(&format!("-----END {}-----\n", label));
+    result
+}
+
+// Decodes the first PEM document in the text, returning its label and bytes.
+// Text before the BEGIN line and any headers are ignored.
+pub fn decode(text:&str) -> Result<(String, Vec<u8>), PemError>{
+    let (label, _, der) = decode_with_headers(text)?;
+    Ok((label, der))
+}
+
+// Header lines as (name, value) pairs, in the order they appear.
+pub type PemHeaders = Vec<(String, String)>;
+
+// Decodes the first PEM document, returning its label, headers and bytes.
+// Header lines are the lines with a colon before the data starts.
+pub fn decode_with_headers(text:&str) -> Result<(String, PemHeaders, Vec<u8>), PemError>{
+    let mut lines = text.lines().map(|l| l.trim());
+
+    let label = loop{
+        match lines.next(){
+            Some(line) => {
+                if let Some(rest) = line.strip_prefix("-----BEGIN "){
+                    if let Some(label) = rest.strip_suffix("-----"){
+                        break label.to_string();
+                    }
+                }
+            },
+            None => return Err(PemError::MissingBegin),
+        }
+    };
+
+    let end = format!("-----END {}-----", label);
+    let mut headers:PemHeaders = Vec::new();
+    let mut body = String::new();
+    loop{
+        match lines.next(){
+            Some(line) if line == end => break,
+            Some(line) => {
+                // Base64 never contains a colon, so it can't be mistaken for a header.
+                match line.split_once(':'){
+                    Some((name, value)) if body.is_empty() => headers.push((name.trim().to_string(), value.trim().to_string())),
+                    _ => body.extend(line.chars().filter(|c| !c.is_whitespace())),
+                }
+            },
+            None => return Err(PemError::MissingEnd),
+        }
+    }
+
+    let der = base64::decode(&body)?;
+    Ok((label, headers, der))
+}
+
+// Decodes the first PEM document and checks it has the expected label.
+pub fn decode_expected(text:&str, expected:&str) -> Result<Vec<u8>, PemError>{
+    let (label, der) = decode(text)?;
+    if label != expected{
+        return Err(PemError::UnexpectedLabel{ expected: expected.to_string(), found: label });
+    }
+    Ok(der)
+}
+
+#[cfg(test)]
+#[test]
+fn test_pem_round_trip(){
+    let der:Vec<u8> = (0..=255).collect();
+    let text = encode("TEST DATA", &der);
+    assert!(text.starts_with("-----BEGIN TEST DATA-----\n"));
+    assert!(text.ends_with("-----END TEST DATA-----\n"));
+    assert!(text.lines().all(|l| l.len() <= 64));
+
+    assert_eq!(decode(&text), Ok((String::from("TEST DATA"), der.clone())));
+    assert_eq!(decode_expected(&format!("explanatory text\r\n{}", text), "TEST DATA"), Ok(der));
+}
+
+#[test]
+fn test_pem_errors(){
+    assert_eq!(decode("no armor here"), Err(PemError::MissingBegin));
+    assert_eq!(decode("-----BEGIN A-----\nAAAA\n-----END B-----\n"), Err(PemError::MissingEnd));
+    assert_eq!(decode_expected("-----BEGIN A-----\nAAAA\n-----END A-----\n", "B"), Err(PemError::UnexpectedLabel{ expected: String::from("B"), found: String::from("A") }));
+    assert!(matches!(decode("-----BEGIN A-----\nA!AA\n-----END A-----\n"), Err(PemError::Base64(_))));
+}
+
+#[test]
+fn test_pem_headers(){
+    let text = encode_with_headers("TEST DATA", &[("Key-Fingerprint", "SHA256:abc"), ("Comment", "a: b")], &[1, 2, 3]);
+    assert_eq!(text, "-----BEGIN TEST DATA-----\nKey-Fingerprint: SHA256:abc\nComment: a: b\n\nAQID\n-----END TEST DATA-----\n");
+    let headers = vec![(String::from("Key-Fingerprint"), String::from("SHA256:abc")), (String::from("Comment"), String::from("a: b"))];
+    assert_eq!(decode_with_headers(&text), Ok((String::from("TEST DATA"), headers, vec![1, 2, 3])));
+    assert_eq!(decode(&text), Ok((String::from("TEST DATA"), vec![1, 2, 3])));
+    assert_eq!(decode_with_headers(&encode("TEST DATA", &[1, 2, 3])), Ok((String::from("TEST DATA"), Vec::new(), vec![1, 2, 3])));
+}
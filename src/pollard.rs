@@ -0,0 +1,186 @@
+// Pollard's two factoring methods, which break the 64 bit moduli the
+// tutorial makes in well under a second.
+//
+// Rho walks x -> x^2 + c mod n. Mod a prime factor p the walk has to repeat
+// within about sqrt(p) steps, and when two points x and y meet mod p but not
+// mod n, gcd(|x - y|, n) is p. Brent's cycle finding compares each point
+// with one saved at the last power of two, which needs one step of the walk
+// per point instead of Floyd's three, and the differences are multiplied
+// together so a GCD is only taken every RHO_BATCH steps.
+//
+// p - 1 works when p - 1 has only small prime factors. Then p - 1 divides
+// the exponent M, the product of every prime up to the first bound to the
+// highest power below n, and 2 ^ M = 1 mod p by Fermat's little theorem, so
+// gcd(2 ^ M - 1, n) is p. The second stage also allows p - 1 one prime
+// factor between the two bounds.
+
+use crate::fips186;
+use crate::utility;
+
+// Differences multiplied together before each GCD in rho.
+pub const RHO_BATCH:u64 = 128;
+
+// Bounds p - 1 uses when none are given.
+pub const DEFAULT_B1:u64 = 10000;
+pub const DEFAULT_B2:u64 = 1000000;
+
+// Pollard's rho with Brent's cycle finding. The factor and the number of
+// steps of the walk it took, or None when budget steps weren't enough. A
+// walk that finds n itself starts again with the next c.
+pub fn rho(n:u64, budget:u64) -> Option<(u64, u64)>{
+    if n < 4 || fips186::is_prime(n){
+        return None;
+    }
+    if n.is_multiple_of(2){
+        return Some((2, 1));
+    }
+    let mut steps = 0;
+    let mut c = 1;
+    while steps < budget{
+        let step = |x:u64| ((x as u128 * x as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut saved) = (2u64, 2u64, 2u64);
+        let mut product = 1u64;
+        let mut g = 1;
+        // The walk goes r steps from x, x is saved at each power of two.
+        let mut r = 1;
+        while g == 1 && steps < budget{
+            x = y;
+            for _ in 0..r{
+                y = step(y);
+            }
+            steps += r;
+            let mut k = 0;
+            while k < r && g == 1{
+                saved = y;
+                for _ in 0..RHO_BATCH.min(r - k){
+                    y = step(y);
+                    product = ((product as u128 * x.abs_diff(y) as u128) % n as u128) as u64;
+                }
+                steps += RHO_BATCH.min(r - k);
+                g = utility::gcd(product, n);
+                k += RHO_BATCH;
+            }
+            r *= 2;
+        }
+        if g == n{
+            // Two factors met in the same batch, go through it one step at a time.
+            loop{
+                saved = step(saved);
+                g = utility::gcd(x.abs_diff(saved), n);
+                if g > 1{
+                    break;
+                }
+            }
+        }
+        if g > 1 && g < n{
+            return Some((g, steps));
+        }
+        c += 1;
+    }
+    None
+}
+
+// Pollard's p - 1 method, the factor and the stage that found it (1 or 2).
+// Stage 1 finds p when p - 1 has no prime factor above b1, stage 2 when it
+// has one more between b1 and b2. None when neither works, or when every
+// factor of n is found at once.
+pub fn p_minus_1(n:u64, b1:u64, b2:u64) -> Option<(u64, u32)>{
+    if n < 4 || fips186::is_prime(n){
+        return None;
+    }
+    if n.is_multiple_of(2){
+        return Some((2, 1));
+    }
+    let primes = primes_up_to(b1.max(b2));
+
+    // Stage 1, a = 2 ^ M with M made one prime power at a time.
+    let mut a = 2u64;
+    for r in primes.iter().take_while(|r| **r <= b1){
+        let mut power = *r;
+        while power <= n / r{
+            power *= r;
+        }
+        a = crate::modded_exponent(a, power, n);
+        let g = utility::gcd(a.wrapping_sub(1), n);
+        if g == n{
+            return None;
+        }
+        if g > 1{
+            return Some((g, 1));
+        }
+    }
+
+    // Stage 2, a ^ r for each prime r above b1, stepping from one prime to
+    // the next by multiplying with a ^ gap.
+    let mut current = a;
+    let mut previous:Option<u64> = None;
+    let mut gaps:Vec<u64> = Vec::new();
+    for r in primes.iter().copied().filter(|r| *r > b1){
+        current = match previous{
+            None => crate::modded_exponent(a, r, n),
+            Some(s) => {
+                let gap = (r - s) as usize;
+                while gaps.len() <= gap{
+                    gaps.push(crate::modded_exponent(a, gaps.len() as u64, n));
+                }
+                ((current as u128 * gaps[gap] as u128) % n as u128) as u64
+            },
+        };
+        previous = Some(r);
+        let g = utility::gcd(current.wrapping_sub(1), n);
+        if g == n{
+            return None;
+        }
+        if g > 1{
+            return Some((g, 2));
+        }
+    }
+    None
+}
+
+// Sieve of Eratosthenes.
+pub fn primes_up_to(limit:u64) -> Vec<u64>{
+    if limit < 2{
+        return Vec::new();
+    }
+    let mut composite = vec![false; limit as usize + 1];
+    let mut primes:Vec<u64> = Vec::new();
+    for i in 2..=limit as usize{
+        if composite[i]{
+            continue;
+        }
+        primes.push(i as u64);
+        for multiple in (i * i..=limit as usize).step_by(i){
+            composite[multiple] = true;
+        }
+    }
+    primes
+}
+
+#[cfg(test)]
+#[test]
+fn test_rho(){
+    let n = 50000000021u64 * 368934871;
+    let (factor, steps) = rho(n, 1 << 20).unwrap();
+    assert!(factor == 50000000021 || factor == 368934871);
+    assert!(steps < 1 << 20);
+    assert_eq!(rho(n, 3), None);
+    let (factor, _) = rho(4294967291u64 * 4294967279, 1 << 20).unwrap();
+    assert!(factor == 4294967291 || factor == 4294967279);
+    assert_eq!(rho(4294967291, 1000), None);
+    assert_eq!(rho(2 * 4294967291, 1000), Some((2, 1)));
+    assert_eq!(rho(25, 1000).map(|(factor, _)| factor), Some(5));
+}
+
+#[test]
+fn test_p_minus_1(){
+    // 65537 - 1 = 2^16 is 100 smooth, 1000003 - 1 = 2 * 3 * 166667 and
+    // 1000033 - 1 = 2^5 * 3 * 11 * 947 aren't.
+    assert_eq!(p_minus_1(65537 * 1000003, 100, 100), Some((65537, 1)));
+    assert_eq!(p_minus_1(1000033 * 1000003, 100, 100), None);
+    // 947 is between the bounds, so stage 2 finds 1000033.
+    assert_eq!(p_minus_1(1000033 * 1000003, 100, 1000), Some((1000033, 2)));
+    assert_eq!(p_minus_1(1000033 * 1000003, 1000, 1000), Some((1000033, 1)));
+    assert_eq!(primes_up_to(30), vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+    assert!(primes_up_to(1).is_empty());
+}
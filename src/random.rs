@@ -0,0 +1,29 @@
+// Random bytes for salts and nonces. Reads the operating system generator
+// at /dev/urandom, the standard library has no other source of secure randomness.
+
+use std::io::Read;
+
+// Fills the buffer with random bytes, or returns the error if the operating
+// system generator can't be read.
+pub fn fill_random(buffer:&mut [u8]) -> std::io::Result<()>{
+    let mut file = std::fs::File::open("/dev/urandom")?;
+    file.read_exact(buffer)
+}
+
+// Returns length random bytes.
+pub fn random_bytes(length:usize) -> std::io::Result<Vec<u8>>{
+    let mut result = vec![0u8; length];
+    fill_random(&mut result)?;
+    Ok(result)
+}
+
+#[cfg(test)]
+#[test]
+fn test_random_bytes(){
+    let a = random_bytes(32).unwrap();
+    let b = random_bytes(32).unwrap();
+    assert_eq!(a.len(), 32);
+    // Two equal 32 byte draws would mean the generator isn't being read.
+    assert_ne!(a, b);
+    assert!(random_bytes(0).unwrap().is_empty());
+}
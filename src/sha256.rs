@@ -0,0 +1,207 @@
+// SHA-256 hash function (FIPS 180-4), used for key fingerprints, and
+// HMAC-SHA256 (RFC 2104) which the passphrase key derivation is built on.
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+pub const DIGEST_LENGTH: usize = 32;
+pub const BLOCK_LENGTH: usize = 64;
+
+// Incremental hasher, for data that arrives in pieces.
+#[derive(Clone)]
+pub struct Sha256{
+    state:[u32; 8],
+    buffer:[u8; BLOCK_LENGTH],
+    buffered:usize,
+    length:u64,
+}
+
+impl Sha256{
+    pub fn new() -> Sha256{
+        Sha256{ state: INITIAL_STATE, buffer: [0; BLOCK_LENGTH], buffered: 0, length: 0 }
+    }
+
+    pub fn update(&mut self, data:&[u8]){
+        self.length = self.length.wrapping_add(data.len() as u64);
+        let mut data = data;
+
+        // Finish off a partly filled block first.
+        if self.buffered > 0{
+            let take = (BLOCK_LENGTH - self.buffered).min(data.len());
+            self.buffer[self.buffered..self.buffered + take].copy_from_slice(&data[..take]);
+            self.buffered += take;
+            data = &data[take..];
+            if self.buffered < BLOCK_LENGTH{
+                return;
+            }
+            let block = self.buffer;
+            self.compress(&block);
+            self.buffered = 0;
+        }
+
+        let mut blocks = data.chunks_exact(BLOCK_LENGTH);
+        for block in &mut blocks{
+            self.compress(block);
+        }
+        let rest = blocks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffered = rest.len();
+    }
+
+    // Pads the message with a 1 bit, zeros and the bit length, then returns the digest.
+    pub fn finalize(mut self) -> [u8; DIGEST_LENGTH]{
+        let bits = self.length.wrapping_mul(8);
+        let mut padding:Vec<u8> = vec![0x80];
+        let used = (self.buffered + 1) % BLOCK_LENGTH;
+        let zeros = if used <= 56 {56 - used} else {BLOCK_LENGTH + 56 - used};
+        padding.extend(std::iter::repeat_n(0u8, zeros));
+        padding.extend_from_slice(&bits.to_be_bytes());
+        // update would count the padding in the length, which is already fixed.
+        let length = self.length;
+        self.update(&padding);
+        self.length = length;
+
+        let mut digest = [0u8; DIGEST_LENGTH];
+        for (i, word) in self.state.iter().enumerate(){
+            digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self, block:&[u8]){
+        let mut w = [0u32; 64];
+        for i in 0..16{
+            w[i] = u32::from_be_bytes([block[i * 4], block[i * 4 + 1], block[i * 4 + 2], block[i * 4 + 3]]);
+        }
+        for i in 16..64{
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64{
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]){
+            *state = state.wrapping_add(value);
+        }
+    }
+}
+
+impl Default for Sha256{
+    fn default() -> Sha256{
+        Sha256::new()
+    }
+}
+
+// Hashes a complete message.
+pub fn digest(data:&[u8]) -> [u8; DIGEST_LENGTH]{
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+// HMAC-SHA256 of the data. Keys longer than a block are hashed first, shorter
+// ones are padded with zeros, then H((key ^ opad) || H((key ^ ipad) || data)).
+pub fn hmac_sha256(key:&[u8], data:&[u8]) -> [u8; DIGEST_LENGTH]{
+    let mut hmac = Hmac::new(key);
+    hmac.update(data);
+    hmac.finalize()
+}
+
+// Incremental HMAC-SHA256, the inner and outer hashers are keyed once and can be
+// cloned to reuse the key, which PBKDF2 does for every iteration.
+#[derive(Clone)]
+pub struct Hmac{
+    inner:Sha256,
+    outer:Sha256,
+}
+
+impl Hmac{
+    pub fn new(key:&[u8]) -> Hmac{
+        let mut block = [0u8; BLOCK_LENGTH];
+        if key.len() > BLOCK_LENGTH{
+            block[..DIGEST_LENGTH].copy_from_slice(&digest(key));
+        }else{
+            block[..key.len()].copy_from_slice(key);
+        }
+
+        let mut inner = Sha256::new();
+        let mut outer = Sha256::new();
+        inner.update(&block.map(|b| b ^ 0x36));
+        outer.update(&block.map(|b| b ^ 0x5c));
+        Hmac{ inner, outer }
+    }
+
+    pub fn update(&mut self, data:&[u8]){
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> [u8; DIGEST_LENGTH]{
+        let mut outer = self.outer;
+        outer.update(&self.inner.finalize());
+        outer.finalize()
+    }
+}
+
+// Lower case hex, used to show digests.
+pub fn to_hex(data:&[u8]) -> String{
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+#[test]
+fn test_sha256_vectors(){
+    // Examples from FIPS 180-4 and the NIST test vectors.
+    assert_eq!(to_hex(&digest(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    assert_eq!(to_hex(&digest(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(to_hex(&digest(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")), "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+    assert_eq!(to_hex(&digest(&vec![b'a'; 1_000_000])), "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+}
+
+#[test]
+fn test_sha256_incremental(){
+    let data:Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+    let whole = digest(&data);
+    for split in [0, 1, 55, 56, 63, 64, 65, 128, 999, 1000]{
+        let mut hasher = Sha256::new();
+        hasher.update(&data[..split]);
+        hasher.update(&data[split..]);
+        assert_eq!(hasher.finalize(), whole);
+    }
+}
+
+#[test]
+fn test_hmac_sha256(){
+    // Test cases 1, 2 and 6 from RFC 4231.
+    assert_eq!(to_hex(&hmac_sha256(&[0x0b; 20], b"Hi There")), "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7");
+    assert_eq!(to_hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    assert_eq!(to_hex(&hmac_sha256(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First")), "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+}
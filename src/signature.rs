@@ -0,0 +1,55 @@
+// Signatures with the tutorial keys. The message is hashed with SHA-256 and
+// the first 8 bytes of the digest, reduced modulo n, are signed with the
+// private exponent: s = h ^ d mod n. Anyone with the public key checks that
+// s ^ e mod n gives h back.
+//
+// Real RSA signatures (PKCS#1 v1.5 or PSS, RFC 8017 section 8) pad the whole
+// digest to the size of the modulus. A 64 bit modulus can't hold that, so
+// only 64 bits of the hash are signed and two messages with the same first
+// 8 digest bytes can be found with about 2^32 hashes. These signatures show
+// how signing works, they are not safe to rely on.
+
+use crate::keys::{RSAPrivateKey, RSAPublicKey};
+use crate::sha256;
+
+// The value that is signed for a message, below the modulus.
+pub fn message_representative(message:&[u8], modulus:u64) -> u64{
+    let digest = sha256::digest(message);
+    let mut first = [0u8; 8];
+    first.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(first) % modulus
+}
+
+// Keystore::sign and Keystore::verify check the lifetime of stored keys first.
+pub fn sign(key:&RSAPrivateKey, message:&[u8]) -> u64{
+    let representative = message_representative(message, key.get_n());
+    crate::modded_exponent(representative, key.get_d(), key.get_n())
+}
+
+pub fn verify(key:&RSAPublicKey, message:&[u8], signature:u64) -> bool{
+    if key.get_n() < 2 || signature >= key.get_n(){
+        return false;
+    }
+    crate::modded_exponent(signature, key.get_e(), key.get_n()) == message_representative(message, key.get_n())
+}
+
+#[cfg(test)]
+#[test]
+fn test_sign_and_verify(){
+    let key = crate::keys::test_key();
+    let public = key.public_key();
+    let signature = sign(&key, b"pay 10 to alice");
+    assert!(verify(&public, b"pay 10 to alice", signature));
+    assert!(!verify(&public, b"pay 99 to alice", signature));
+    assert!(!verify(&public, b"pay 10 to alice", signature ^ 1));
+    assert!(!verify(&public, b"pay 10 to alice", public.get_n()));
+    assert!(!verify(&RSAPublicKey::new(public.get_n(), 65537), b"pay 10 to alice", signature));
+}
+
+#[test]
+fn test_message_representative(){
+    // First 8 bytes of SHA-256("abc") are ba7816bf8f01cfea.
+    assert_eq!(message_representative(b"abc", u64::MAX), 0xba7816bf8f01cfea);
+    assert_eq!(message_representative(b"abc", 18446743557747632291), 0xba7816bf8f01cfea);
+    assert_eq!(message_representative(b"abc", 4294967291), 0xba7816bf8f01cfea % 4294967291);
+}
@@ -0,0 +1,317 @@
+// OpenSSH public key format (RFC 4253 section 6.6), the "ssh-rsa AAAA... comment"
+// lines used in .pub and authorized_keys files, and ssh-keygen style fingerprints.
+
+use crate::base64::{self, Base64Error};
+use crate::fingerprint::Fingerprint;
+use crate::keys::RSAPublicKey;
+use std::fmt;
+
+pub const KEY_TYPE_RSA: &str = "ssh-rsa";
+
+// Errors returned when a line isn't a usable OpenSSH RSA public key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SshError{
+    // Line doesn't have the key type and base64 fields.
+    MissingField,
+    // Key type other than ssh-rsa.
+    UnsupportedKeyType(String),
+    // Key type in the line and in the encoded key don't match.
+    KeyTypeMismatch,
+    // Encoded key ended early or had extra data.
+    Malformed,
+    // Negative integer or one with padding bytes.
+    InvalidMpint,
+    // Integer does not fit in 64 bits.
+    IntegerTooLarge,
+    Base64(Base64Error),
+}
+
+impl fmt::Display for SshError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            SshError::MissingField => write!(f, "missing key type or key data"),
+            SshError::UnsupportedKeyType(t) => write!(f, "unsupported key type {}", t),
+            SshError::KeyTypeMismatch => write!(f, "key type does not match the encoded key"),
+            SshError::Malformed => write!(f, "malformed SSH key data"),
+            SshError::InvalidMpint => write!(f, "invalid SSH mpint"),
+            SshError::IntegerTooLarge => write!(f, "key integer is larger than 64 bits"),
+            SshError::Base64(e) => write!(f, "invalid key data: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SshError{}
+
+impl From<Base64Error> for SshError{
+    fn from(e:Base64Error) -> SshError{
+        SshError::Base64(e)
+    }
+}
+
+// One public key line, with the authorized_keys options if there were any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizedKey{
+    pub options:Option<String>,
+    pub key:RSAPublicKey,
+    pub comment:String,
+}
+
+// Encodes the key in the SSH wire format, string "ssh-rsa", mpint e, mpint n.
+pub fn encode_public_key_blob(key:&RSAPublicKey) -> Vec<u8>{
+    let mut blob:Vec<u8> = Vec::new();
+    write_string(&mut blob, KEY_TYPE_RSA.as_bytes());
+    write_mpint(&mut blob, key.get_e());
+    write_mpint(&mut blob, key.get_n());
+    blob
+}
+
+pub fn decode_public_key_blob(blob:&[u8]) -> Result<RSAPublicKey, SshError>{
+    let mut reader = WireReader{ data: blob, position: 0 };
+    let keytype = reader.read_string()?;
+    if keytype != KEY_TYPE_RSA.as_bytes(){
+        return Err(SshError::UnsupportedKeyType(String::from_utf8_lossy(keytype).to_string()));
+    }
+    let e = reader.read_mpint()?;
+    let n = reader.read_mpint()?;
+    if reader.position != blob.len(){
+        return Err(SshError::Malformed);
+    }
+    Ok(RSAPublicKey::new(n, e))
+}
+
+// "ssh-rsa AAAA... comment", the format of id_rsa.pub files.
+pub fn to_openssh_line(key:&RSAPublicKey, comment:&str) -> String{
+    let line = format!("{} {}", KEY_TYPE_RSA, base64::encode(&encode_public_key_blob(key)));
+    if comment.is_empty() {line} else {format!("{} {}", line, comment)}
+}
+
+// Parses a public key line, either from a .pub file or an authorized_keys entry
+// that starts with options such as from="10.0.0.1",no-pty.
+pub fn parse_public_key_line(line:&str) -> Result<AuthorizedKey, SshError>{
+    let line = line.trim();
+    let mut options:Option<String> = None;
+    let mut rest = line;
+    if !is_key_type(first_field(line)){
+        let (opts, after) = split_options(line);
+        options = Some(opts.to_string());
+        rest = after.trim_start();
+    }
+
+    let mut fields = rest.splitn(3, char::is_whitespace);
+    let keytype = fields.next().unwrap_or("");
+    let data = match fields.next(){
+        Some(s) if !keytype.is_empty() => s,
+        _ => return Err(SshError::MissingField),
+    };
+    let comment = fields.next().unwrap_or("").trim().to_string();
+
+    if keytype != KEY_TYPE_RSA{
+        return Err(SshError::UnsupportedKeyType(keytype.to_string()));
+    }
+    let blob = base64::decode(data)?;
+    let key = match decode_public_key_blob(&blob){
+        Ok(s) => s,
+        Err(SshError::UnsupportedKeyType(_)) => return Err(SshError::KeyTypeMismatch),
+        Err(e) => return Err(e),
+    };
+    Ok(AuthorizedKey{ options, key, comment })
+}
+
+// Parses every entry of an authorized_keys file, blank lines and # comments are
+// skipped. Each result comes with its line number (starting at 1) so bad entries
+// can be reported.
+pub fn parse_authorized_keys(text:&str) -> Vec<(usize, Result<AuthorizedKey, SshError>)>{
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let trimmed = line.trim();
+            !trimmed.is_empty() && !trimmed.starts_with('#')
+        })
+        .map(|(i, line)| (i + 1, parse_public_key_line(line)))
+        .collect()
+}
+
+// SHA256 fingerprint as OpenSSH shows it, "SHA256:" and the unpadded base64 digest
+// of the wire format key.
+pub fn fingerprint_sha256(key:&RSAPublicKey) -> String{
+    fingerprint_blob(&encode_public_key_blob(key))
+}
+
+// Fingerprint of an already encoded key of any type or size.
+pub fn fingerprint_blob(blob:&[u8]) -> String{
+    Fingerprint::of_blob(blob).to_base64()
+}
+
+// Same line ssh-keygen -lf prints, "bits fingerprint comment (RSA)".
+pub fn fingerprint_line(key:&RSAPublicKey, comment:&str) -> String{
+    let bits = 64 - key.get_n().leading_zeros();
+    let comment = if comment.is_empty() {"no comment"} else {comment};
+    format!("{} {} {} (RSA)", bits, fingerprint_sha256(key), comment)
+}
+
+fn first_field(line:&str) -> &str{
+    line.split(char::is_whitespace).next().unwrap_or("")
+}
+
+// Key types OpenSSH knows, anything else at the start of a line is an option list.
+fn is_key_type(field:&str) -> bool{
+    field.starts_with("ssh-") || field.starts_with("ecdsa-sha2-") || field.starts_with("sk-")
+}
+
+// Options run until the first space outside double quotes.
+fn split_options(line:&str) -> (&str, &str){
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices(){
+        if escaped{
+            escaped = false;
+        }else if c == '\\'{
+            escaped = true;
+        }else if c == '"'{
+            quoted = !quoted;
+        }else if c.is_whitespace() && !quoted{
+            return (&line[..i], &line[i..]);
+        }
+    }
+    (line, "")
+}
+
+fn write_string(out:&mut Vec<u8>, data:&[u8]){
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+// mpint is a big endian two's complement string, zero is the empty string and a
+// zero byte goes in front when the top bit is set.
+fn write_mpint(out:&mut Vec<u8>, value:u64){
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|b| **b == 0).count();
+    let mut digits:Vec<u8> = Vec::with_capacity(9);
+    if skip < 8 && bytes[skip] & 0x80 != 0{
+        digits.push(0);
+    }
+    digits.extend_from_slice(&bytes[skip..]);
+    write_string(out, &digits);
+}
+
+struct WireReader<'a>{
+    data:&'a [u8],
+    position:usize,
+}
+
+impl<'a> WireReader<'a>{
+    fn read_string(&mut self) -> Result<&'a [u8], SshError>{
+        if self.data.len() - self.position < 4{
+            return Err(SshError::Malformed);
+        }
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&self.data[self.position..self.position + 4]);
+        let len = u32::from_be_bytes(len) as usize;
+        self.position += 4;
+        if self.data.len() - self.position < len{
+            return Err(SshError::Malformed);
+        }
+        let result = &self.data[self.position..self.position + len];
+        self.position += len;
+        Ok(result)
+    }
+
+    fn read_mpint(&mut self) -> Result<u64, SshError>{
+        let digits = self.read_string()?;
+        if digits.is_empty(){
+            return Ok(0);
+        }
+        if digits[0] & 0x80 != 0{
+            return Err(SshError::InvalidMpint);
+        }
+        if digits[0] == 0 && (digits.len() == 1 || digits[1] & 0x80 == 0){
+            return Err(SshError::InvalidMpint);
+        }
+        let digits = if digits[0] == 0 {&digits[1..]} else {digits};
+        if digits.len() > 8{
+            return Err(SshError::IntegerTooLarge);
+        }
+        Ok(digits.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_openssh_line(){
+    let key = RSAPublicKey::new(18446743557747632291, 92829719);
+    let line = to_openssh_line(&key, "tutorial@example");
+    // Same line as ssh-keygen -i -m PKCS8 -f fixtures/rsa_public_spki.pem.
+    assert_eq!(line, "ssh-rsa AAAAB3NzaC1yc2EAAAAEBYh4FwAAAAkA////h95F6KM= tutorial@example");
+
+    let parsed = parse_public_key_line(&line).unwrap();
+    assert_eq!(parsed.key, key);
+    assert_eq!(parsed.comment, "tutorial@example");
+    assert_eq!(parsed.options, None);
+}
+
+#[test]
+fn test_fingerprint_matches_ssh_keygen(){
+    let key = RSAPublicKey::new(18446743557747632291, 92829719);
+    // ssh-keygen refuses to load keys this small, so the digest was worked out
+    // separately over the same key blob.
+    assert_eq!(fingerprint_line(&key, "tutorial@example"), "64 SHA256:4zYpktzDkWm2qrmfDAX6cg2E5sBUihWCvHDT1clJi3k tutorial@example (RSA)");
+    assert_eq!(fingerprint_line(&key, ""), "64 SHA256:4zYpktzDkWm2qrmfDAX6cg2E5sBUihWCvHDT1clJi3k no comment (RSA)");
+
+    // A key made by ssh-keygen, ssh-keygen -lf shows
+    // 1024 SHA256:tfTHsNhXZBNNdmPPy9dSAHBreaWJxPAWlrPTBZgqTlA tutorial@example (RSA)
+    let line = include_str!("../fixtures/ssh_rsa_1024.pub");
+    let data = line.split_whitespace().nth(1).unwrap();
+    assert_eq!(fingerprint_blob(&base64::decode(data).unwrap()), "SHA256:tfTHsNhXZBNNdmPPy9dSAHBreaWJxPAWlrPTBZgqTlA");
+    // Its integers are too large for the tutorial key type.
+    assert_eq!(parse_public_key_line(line), Err(SshError::IntegerTooLarge));
+}
+
+#[test]
+fn test_parse_authorized_keys(){
+    let text = "# keys for the tutorial account
+
+ssh-rsa AAAAB3NzaC1yc2EAAAAEBYh4FwAAAAkA////h95F6KM= first key
+from=\"10.0.0.1,10.0.0.2\",command=\"echo hello world\",no-pty ssh-rsa AAAAB3NzaC1yc2EAAAAEBYh4FwAAAAkA////h95F6KM= second
+ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIG6dYJfBKvmLeNuQP4oYdE7vNixrZ3M3R5DMkCj5xvMh third
+ssh-rsa AAAAC3NzaC1lZDI1NTE5AAAAIG6dYJfBKvmLeNuQP4oYdE7vNixrZ3M3R5DMkCj5xvMh fourth
+ssh-rsa
+";
+    let entries = parse_authorized_keys(text);
+    assert_eq!(entries.len(), 5);
+    let key = RSAPublicKey::new(18446743557747632291, 92829719);
+
+    let (line, first) = &entries[0];
+    assert_eq!(*line, 3);
+    assert_eq!(first.as_ref().unwrap().key, key);
+    assert_eq!(first.as_ref().unwrap().comment, "first key");
+
+    let (line, second) = &entries[1];
+    assert_eq!(*line, 4);
+    let second = second.as_ref().unwrap();
+    assert_eq!(second.options.as_deref(), Some("from=\"10.0.0.1,10.0.0.2\",command=\"echo hello world\",no-pty"));
+    assert_eq!(second.key, key);
+    assert_eq!(second.comment, "second");
+
+    assert_eq!(entries[2].1, Err(SshError::UnsupportedKeyType(String::from("ssh-ed25519"))));
+    assert_eq!(entries[3].1, Err(SshError::KeyTypeMismatch));
+    assert_eq!(entries[4].1, Err(SshError::MissingField));
+}
+
+#[test]
+fn test_mpint(){
+    let mut out:Vec<u8> = Vec::new();
+    write_mpint(&mut out, 0);
+    write_mpint(&mut out, 0x80);
+    write_mpint(&mut out, 0x7f);
+    assert_eq!(out, vec![0, 0, 0, 0, 0, 0, 0, 2, 0, 0x80, 0, 0, 0, 1, 0x7f]);
+
+    let mut reader = WireReader{ data: &out, position: 0 };
+    assert_eq!(reader.read_mpint(), Ok(0));
+    assert_eq!(reader.read_mpint(), Ok(0x80));
+    assert_eq!(reader.read_mpint(), Ok(0x7f));
+
+    let negative = [0, 0, 0, 1, 0x80];
+    assert_eq!(WireReader{ data: &negative, position: 0 }.read_mpint(), Err(SshError::InvalidMpint));
+    let padded = [0, 0, 0, 2, 0, 0x01];
+    assert_eq!(WireReader{ data: &padded, position: 0 }.read_mpint(), Err(SshError::InvalidMpint));
+}
@@ -1,3 +1,6 @@
+use crate::keys::{KeyError, RSAPrivateKey};
+use crate::multiprime;
+
 // Main structure of application, which holds all 
 // needed information for encryption and decryption.
 pub struct RSAInfo{
@@ -5,6 +8,7 @@ pub struct RSAInfo{
     q:u64,
     n:u64,
     phi:u64,
+    lambda:u64,
     d:u64,
     e:u64,
 }
@@ -17,6 +21,7 @@ impl RSAInfo{
             q : 0u64,
             n : 0u64,
             phi : 0u64,
+            lambda : 0u64,
             d : 0u64,
             e : 0u64,
         };
@@ -48,6 +53,12 @@ impl RSAInfo{
     pub fn get_phi(&self) -> u64{
         self.phi
     }
+    pub fn set_lambda(&mut self, val:u64){
+        self.lambda = val;
+    }
+    pub fn get_lambda(&self) -> u64{
+        self.lambda
+    }
     pub fn set_d(&mut self, val:u64){
         self.d = val;
     }
@@ -60,6 +71,22 @@ impl RSAInfo{
     pub fn get_e(&self) -> u64{
         self.e
     }
+
+    // Checks n, phi and lambda agree with p and q, then the key itself with
+    // RSAPrivateKey::validate.
+    pub fn validate(&self) -> Result<(), KeyError>{
+        let key = RSAPrivateKey::from_rsainfo(self)?;
+        if key.get_n() != self.n{
+            return Err(KeyError::Inconsistent("modulus is not p * q"));
+        }
+        if (self.p - 1).checked_mul(self.q - 1) != Some(self.phi){
+            return Err(KeyError::Inconsistent("phi is not (p - 1) * (q - 1)"));
+        }
+        if multiprime::carmichael(&[self.p, self.q]) != self.lambda{
+            return Err(KeyError::Inconsistent("lambda is not lcm(p - 1, q - 1)"));
+        }
+        key.validate()
+    }
 }
 
 #[cfg(test)]
@@ -72,10 +99,12 @@ impl RSAInfo{
         r.set_phi(4u64);
         r.set_p(5u64);
         r.set_q(6u64);
+        r.set_lambda(7u64);
         assert_eq!(1u64, r.get_d());
         assert_eq!(2u64, r.get_e());
         assert_eq!(3u64, r.get_n());
         assert_eq!(4u64, r.get_phi());
         assert_eq!(5u64, r.get_p());
         assert_eq!(6u64, r.get_q());
+        assert_eq!(7u64, r.get_lambda());
     }
\ No newline at end of file
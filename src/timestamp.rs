@@ -0,0 +1,95 @@
+// Times as seconds since the Unix epoch, and the UTC calendar dates used to show them.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Current time in seconds, 0 if the clock is set before 1970.
+pub fn now() -> u64{
+    match SystemTime::now().duration_since(UNIX_EPOCH){
+        Ok(s) => s.as_secs(),
+        Err(_) => 0,
+    }
+}
+
+// Splits seconds into UTC (year, month, day, hour, minute, second).
+pub fn to_civil(seconds:u64) -> (u64, u64, u64, u64, u64, u64){
+    let days = seconds / 86400;
+    let rest = seconds % 86400;
+    let (year, month, day) = civil_from_days(days);
+    (year, month, day, rest / 3600, rest % 3600 / 60, rest % 60)
+}
+
+// Seconds for a UTC date and time, None for dates before 1970 or out of range.
+pub fn from_civil(year:u64, month:u64, day:u64, hour:u64, minute:u64, second:u64) -> Option<u64>{
+    if year < 1970 || !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) || hour > 23 || minute > 59 || second > 59{
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+// Formats as "2024-05-01 13:45:00 UTC".
+pub fn format_utc(seconds:u64) -> String{
+    let (year, month, day, hour, minute, second) = to_civil(seconds);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, hour, minute, second)
+}
+
+fn is_leap_year(year:u64) -> bool{
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+fn days_in_month(year:u64, month:u64) -> u64{
+    match month{
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Howard Hinnant's days_from_civil, with years starting in March so the leap
+// day comes last. Only dates from 1970 on are needed, so everything is unsigned.
+fn days_from_civil(year:u64, month:u64, day:u64) -> u64{
+    let year = if month <= 2 {year - 1} else {year};
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days:u64) -> (u64, u64, u64){
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 {mp + 3} else {mp - 9};
+    let year = yoe + era * 400;
+    (if month <= 2 {year + 1} else {year}, month, day)
+}
+
+#[cfg(test)]
+#[test]
+fn test_timestamp_civil(){
+    assert_eq!(to_civil(0), (1970, 1, 1, 0, 0, 0));
+    // Values checked with date -u -d @seconds.
+    assert_eq!(to_civil(951782400), (2000, 2, 29, 0, 0, 0));
+    assert_eq!(to_civil(1714571100), (2024, 5, 1, 13, 45, 0));
+    assert_eq!(to_civil(4107542399), (2100, 2, 28, 23, 59, 59));
+    assert_eq!(format_utc(1714571100), "2024-05-01 13:45:00 UTC");
+}
+
+#[test]
+fn test_timestamp_round_trip(){
+    for seconds in [0u64, 86399, 86400, 951782400, 1714571100, 4107542399, 4107542400, 253402300799]{
+        let (year, month, day, hour, minute, second) = to_civil(seconds);
+        assert_eq!(from_civil(year, month, day, hour, minute, second), Some(seconds));
+    }
+    assert_eq!(from_civil(2023, 2, 29, 0, 0, 0), None);
+    assert_eq!(from_civil(2100, 2, 29, 0, 0, 0), None);
+    assert_eq!(from_civil(1969, 12, 31, 23, 59, 59), None);
+    assert_eq!(from_civil(2024, 13, 1, 0, 0, 0), None);
+}
@@ -1,4 +1,4 @@
-use crate::{modded_exponent, structures::RSAInfo};
+use crate::{fips186, keys::{self, ExponentModulus, KeyPolicy}, modded_exponent, multiprime, structures::RSAInfo};
 use std::{io::{self, Write}, sync::{Arc, Mutex}, thread::{self}};
 
 const PRIME_MAX:u64 = u64::MAX / 3;
@@ -56,10 +56,34 @@ pub fn get_phi(info: &mut RSAInfo) {
     println!("To get the euler totient (phi), we need to multiply p-1 * q-1. That equals {}.", info.get_phi());
 }
 
-// Gets e exponent, User supplies a candidate, but function will choose a correct value.
-// this exponent must be coprime with eulers totient.
+// Gets Carmichael's function (lambda), the least common multiple of p-1 and q-1.
+// It divides phi, so d worked out modulo lambda is never larger.
+pub fn get_lambda(info: &mut RSAInfo) {
+    info.set_lambda(multiprime::carmichael(&[info.get_p(), info.get_q()]));
+    println!("Carmichael's function (lambda) is the least common multiple of p-1 and q-1. That equals {}.", info.get_lambda());
+    println!("For any x, x ^ (lambda + 1) is x modulus n, so exponents only matter modulo lambda, and phi is {} times lambda.", info.get_phi() / info.get_lambda());
+    println!("A d worked out modulo lambda is smaller but decrypts the same as one worked out modulo phi. FIPS 186 asks for lambda.");
+}
+
+// Asks whether d is worked out modulo phi or lambda, phi unless the user types lambda.
+pub fn get_exponent_modulus() -> ExponentModulus {
+    println!("Type lambda to work out d modulo lambda, or press enter to use phi.");
+    let mut input:String = String::new();
+    if let Err(_e) = io::stdin().read_line(&mut input){
+        println!("Failed to get this information, phi will be used.");
+    }
+    match input.trim(){
+        "lambda" | "LAMBDA" | "l" | "L" => ExponentModulus::Lambda,
+        _ => ExponentModulus::Phi,
+    }
+}
+
+// Gets e exponent, 65537 unless the user types another odd number of at least 3.
+// this exponent must be coprime with lambda, check_e says whether it is. If it
+// isn't, the tutorial keeps e and picks new primes, as generated keys do.
 pub fn get_e(info: &mut RSAInfo)  {
-    println!("Our public exponent e must be coprime with our phi. Give me a place to start looking and I'll find you one.");
+    println!("Our public exponent e must be coprime with our phi. Most keys use {}, a prime with only two bits set, so encrypting is quick.", DEFAULT_E);
+    println!("Press enter to use it, or type another odd number of at least 3.");
     let mut input:String = String::new();
     print!("Enter a number:    ");
     if let Err(e) = io::stdout().flush(){
@@ -68,26 +92,45 @@ pub fn get_e(info: &mut RSAInfo)  {
 
     if let Err(_e) = io::stdin().read_line(&mut input){
         println!("Failed to get this information, I will supply a default value.");
-        input = String::from("500000000");
+        input = String::new();
     }
-    let number:u64;
-    match input.trim().parse::<u64>(){
-        Ok(s) => {
-            number = s;
-        },
-        Err(_) => {
-            println!("That's not gonna work, I'll pick you one.");
-            number = u64::MAX / 2;
+    let number:u64 = match input.trim(){
+        "" => DEFAULT_E,
+        s => match s.parse::<u64>().map(|e| (e, keys::check_public_exponent(e))){
+            Ok((e, Ok(()))) => e,
+            Ok((_, Err(e))) => {
+                println!("That's not gonna work, {}. I'll use {}.", e, DEFAULT_E);
+                DEFAULT_E
+            },
+            Err(_) => {
+                println!("That's not gonna work, I'll use {}.", DEFAULT_E);
+                DEFAULT_E
+            },
         },
+    };
+    info.set_e(number);
+}
+
+// Checks e has an inverse modulo lambda, so d can be found. lambda and phi
+// have the same prime factors, so this is the same as gcd(e, phi) = 1.
+pub fn check_e(info: &RSAInfo) -> bool {
+    let (g, _, _) = extended_gcd(i128::from(info.get_e()), i128::from(info.get_lambda()));
+    if g == 1{
+        println!("{} will work as e.", info.get_e());
+        return true;
     }
-    info.set_e(get_a_coprime(number, info.get_phi()));
-    
+    println!("{} shares the factor {} with lambda, so it has no inverse and these primes can't be used with it.", info.get_e(), g);
+    false
 }
 
-// Get exponent d, this exponent must be the modular inverse of eulers totient.
+// Get exponent d, this exponent must be the modular inverse of e modulo
+// eulers totient or carmichaels function.
 // must perform euclideans extended algorithm to find it.
-pub fn get_d(info: &mut RSAInfo) {
-    let eul= i128::try_from(info.get_phi());
+pub fn get_d(info: &mut RSAInfo, modulus:ExponentModulus) {
+    let eul= match modulus{
+        ExponentModulus::Phi => i128::try_from(info.get_phi()),
+        ExponentModulus::Lambda => i128::try_from(info.get_lambda()),
+    };
     let eul = match eul{
         Ok(s) => s,
         Err(_) => panic!("cant do it."),
@@ -104,14 +147,14 @@ pub fn get_d(info: &mut RSAInfo) {
     }
     let val = (data.1 % eul + eul) % eul;
     info.set_d(val as u64);
-    println!("D must be the multiplicative inverse: d * e = 1 mod N");
+    println!("D must be the multiplicative inverse: d * e = 1 mod {}", modulus);
     println!("We will find this using euclideans extended algorithm.");
     println!("{} is exponent d.", val as u64);
 }
 
 // function to find both primes. What ever value user chooses, this algorithm will find one so that
 // the multiplication of the two is slightly less than u64::MAX.
-fn find_prime(num:u64) -> (u64, u64){
+pub fn find_prime(num:u64) -> (u64, u64){
     let mid = PRIME_MAX / 2;
     let mut range = num;
    if num < 3 || num > PRIME_MAX{
@@ -419,26 +462,6 @@ fn miller_rabine_test(mut d:u64, num:u64, g:u64) -> bool{
     false
 }
 
-// Function to find coprime value, checks supplied number
-// first, then continues up, if not found, starts at 3 and continues
-// up to num. If nothing found (unlikely) panics.
-fn get_a_coprime(num:u64, phi: u64) -> u64{
-    
-    for i in num..phi{
-        if is_coprime(i, phi){
-            println!("{} will work as e.", i);
-            return i;
-        }
-    }
-    for j in 3..num{
-        if is_coprime(j, phi){
-            println!("{} will work as e.", j);
-            return j;
-        }
-    }
-    panic!("Unable to find exponent e.");
-}
-
 // Actually checks two numbers for coprime. Basically, an
 // implementation of euclideans algorithm if GCD is 1 returns
 // true.
@@ -464,7 +487,7 @@ fn is_coprime(e:u64, phi:u64) -> bool{
 }
 
 // Exctended euclideans algorithem worked recursively.
-fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
     if b == 0 {
         (a, 1, 0)
     } else {
@@ -473,8 +496,116 @@ fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
     }
 }
 
+pub fn gcd(a:u64, b:u64) -> u64{
+    if b == 0 {a} else {gcd(b, a % b)}
+}
+
+// Inverse of a modulo m from the extended euclidean algorithm, None if a and
+// m share a factor.
+pub fn mod_inverse(a:u64, m:u64) -> Option<u64>{
+    let m = i128::from(m);
+    let (g, x, _) = extended_gcd(i128::from(a), m);
+    if g != 1{
+        return None;
+    }
+    Some(((x % m + m) % m) as u64)
+}
+
+// Public exponent for keys made without the tutorial, the usual choice.
+pub const DEFAULT_E:u64 = 65537;
+
+// Smallest place the prime search for generated keys starts from, 2^32 + 2^30.
+// p is found above it and q below u64::MAX / p, which is under 2^32, so the two
+// primes are about the same size and can't be equal.
+const GENERATED_PRIME_START:u64 = (1 << 32) + (1 << 30);
+
+// Makes a whole key without asking or printing anything, for commands that
+// create keys. The prime search starts at GENERATED_PRIME_START plus the
+// offset, which the caller picks at random so every key is different.
+// The primes are searched for one number at a time with fips186::is_prime
+// rather than with find_prime, whose threads can return a prime far from
+// the start. e comes from the policy, and while it shares a factor with
+// lambda the search goes on past p for new primes. d is worked out modulo
+// phi or lambda as asked.
+pub fn generate_rsainfo(offset:u64, policy:&KeyPolicy) -> RSAInfo{
+    let e = policy.get_e();
+    let mut start = GENERATED_PRIME_START + offset % (1 << 32);
+    let (p, q) = loop{
+        let p = match (start..).find(|i| fips186::is_prime(*i)){
+            Some(s) => s,
+            None => panic!("No prime found above {}.", start),
+        };
+        let q = match (2..=u64::MAX / p).rev().find(|i| fips186::is_prime(*i)){
+            Some(s) => s,
+            None => panic!("No prime found below {}.", u64::MAX / p),
+        };
+        let lambda = multiprime::carmichael(&[p, q]);
+        if !e.is_multiple_of(lambda) && is_coprime(e % lambda, lambda){
+            break (p, q);
+        }
+        start = p + 1;
+    };
+    let mut info = RSAInfo::new();
+    info.set_p(p);
+    info.set_q(q);
+    // q <= u64::MAX / p, so p * q fits.
+    info.set_n(p * q);
+    info.set_phi((p - 1) * (q - 1));
+    info.set_lambda(multiprime::carmichael(&[p, q]));
+    info.set_e(e);
+
+    let modulus = match policy.get_modulus(){
+        ExponentModulus::Phi => info.get_phi(),
+        ExponentModulus::Lambda => info.get_lambda(),
+    };
+    // The search above only keeps primes where e has an inverse.
+    match mod_inverse(e, modulus){
+        Some(s) => info.set_d(s),
+        None => panic!("{} has no inverse modulo {}.", e, modulus),
+    }
+    info
+}
 
 
+// Rebuilds the private key once an attack has found the primes of a public
+// key, with d the inverse of e modulo lambda. None if e has no inverse,
+// which a working key can't have.
+pub fn recover_private_key(p:u64, q:u64, e:u64) -> Option<keys::RSAPrivateKey>{
+    let d = mod_inverse(e, multiprime::carmichael(&[p, q]))?;
+    keys::RSAPrivateKey::from_parts(p, q, e, d).ok()
+}
+
+// Makes the primes for a multi-prime key, count primes of about 64 / count
+// bits each with a product that fits in a u64. All but the last are searched
+// for upward from a point picked by the offset, the last is the largest prime
+// that keeps the product below u64::MAX. Primes r where r - 1 shares a factor
+// with e are skipped, so e has an inverse for any choice of primes.
+pub fn generate_primes(count:u32, offset:u64, e:u64) -> Vec<u64>{
+    if !(2..=8).contains(&count){
+        panic!("Can't make a key with {} primes.", count);
+    }
+    // Each prime starts between 3/4 and all of 2^(64 / count).
+    let size = 1u64 << (64 / count);
+    let is_usable_prime = |i:u64| fips186::is_prime(i) && is_coprime(e % (i - 1), i - 1);
+    let mut primes:Vec<u64> = Vec::new();
+    let mut product = 1u64;
+    for index in 0..count - 1{
+        let start = size / 4 * 3 + (offset.rotate_left(index * 16) % (size / 4));
+        let prime = match (start..).find(|i| is_usable_prime(*i) && !primes.contains(i)){
+            Some(s) => s,
+            None => panic!("No prime found above {}.", start),
+        };
+        primes.push(prime);
+        product *= prime;
+    }
+    let last = match (2..=u64::MAX / product).rev().find(|i| is_usable_prime(*i) && !primes.contains(i)){
+        Some(s) => s,
+        None => panic!("No prime found below {}.", u64::MAX / product),
+    };
+    primes.push(last);
+    primes
+}
+
 
 
 #[cfg(test)]
@@ -522,9 +653,14 @@ fn test_getd(){
     r.set_e(927438937u64);
     r.set_d(18411267238725607273u64);
 
-    get_d(&mut r);
+    get_d(&mut r, ExponentModulus::Phi);
 
     assert_eq!(18411267238725607273u64, r.get_d());
+
+    get_lambda(&mut r);
+    assert_eq!(r.get_lambda(), 1844674350737869740);
+    get_d(&mut r, ExponentModulus::Lambda);
+    assert_eq!(r.get_d(), 1809198082084779613);
 }
 
 #[test]
@@ -566,10 +702,16 @@ fn test_is_primesasync(){
 }
 
 #[test]
-fn test_getacoprime(){
-    let g = get_a_coprime(5000u64, 18446743613945430720u64);
-
-    assert!(g == 5003u64);
+fn test_check_e(){
+    let mut r = crate::structures::RSAInfo::new();
+    r.set_p(50000000021u64);
+    r.set_q(368934871u64);
+    r.set_lambda(1844674350737869740u64);
+    r.set_e(92829719u64);
+    assert!(check_e(&r));
+    // 5 divides p - 1.
+    r.set_e(5u64);
+    assert!(!check_e(&r));
 }
 
 #[test]
@@ -592,6 +734,14 @@ fn test_extended_euclidean(){
 
 }
 
+#[test]
+fn test_mod_inverse(){
+    assert_eq!(mod_inverse(5003, 18446743613945430720), Some(13988995656867672227));
+    assert_eq!(mod_inverse(65537, 65537 * 3), None);
+    assert_eq!(gcd(50000000020, 368934870), 10);
+    assert_eq!(gcd(0, 91), 91);
+}
+
 #[test]
 fn test_is_prime_miller_rabin(){
     assert_eq!(is_prime_miller_rabine(8865838643u64), is_prime_async(8865838643u64));
@@ -629,3 +779,48 @@ fn test_isprimespeeds(){
     assert!(two);
 
 }
+
+#[test]
+fn test_generate_rsainfo(){
+    for offset in [0u64, 123456789, u64::MAX]{
+        let info = generate_rsainfo(offset, &KeyPolicy::default());
+        assert!(is_prime_miller_rabine(info.get_p()) && is_prime_miller_rabine(info.get_q()));
+        assert!(info.get_p() > 1 << 32 && info.get_q() < 1 << 32);
+        assert_eq!(info.get_n(), info.get_p() * info.get_q());
+        assert!(info.get_n() > 1 << 63);
+        assert_eq!((info.get_e() as u128 * info.get_d() as u128) % info.get_phi() as u128, 1);
+        assert!(info.get_d() < info.get_phi());
+
+        assert_eq!(info.get_e(), DEFAULT_E);
+
+        let lambda = generate_rsainfo(offset, &KeyPolicy::new(DEFAULT_E, ExponentModulus::Lambda).unwrap());
+        assert_eq!(lambda.get_n(), info.get_n());
+        assert_eq!(lambda.get_phi() % lambda.get_lambda(), 0);
+        assert_eq!(lambda.get_d(), info.get_d() % lambda.get_lambda());
+        assert!(lambda.get_d() < lambda.get_lambda());
+
+        // With e = 5 primes where 5 divides p - 1 or q - 1 are passed over.
+        let five = generate_rsainfo(offset, &KeyPolicy::new(5, ExponentModulus::Phi).unwrap());
+        assert_eq!(five.get_e(), 5);
+        assert!(five.get_p() % 5 != 1 && five.get_q() % 5 != 1);
+        assert!(five.get_p() >= info.get_p());
+        assert_eq!((5 * five.get_d() as u128) % five.get_phi() as u128, 1);
+    }
+}
+
+#[test]
+fn test_generate_primes(){
+    for count in 2..=4u32{
+        for offset in [0u64, 987654321987654321]{
+            let primes = generate_primes(count, offset, DEFAULT_E);
+            assert_eq!(primes.len(), count as usize);
+            assert!(primes.iter().all(|p| is_prime(*p) && is_coprime(DEFAULT_E % (p - 1), p - 1)));
+            let n = primes.iter().try_fold(1u64, |n, p| n.checked_mul(*p)).unwrap();
+            assert!(n > 1 << 63);
+            let mut distinct = primes.clone();
+            distinct.sort();
+            distinct.dedup();
+            assert_eq!(distinct.len(), primes.len());
+        }
+    }
+}
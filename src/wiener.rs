@@ -0,0 +1,83 @@
+// Wiener's attack on a small private exponent. e * d = 1 + k * phi(n) for
+// some k, and phi(n) is close to n, so k / d is close to e / n. When
+// d < n^(1/4) / 3 and q < p < 2q it is close enough that k / d is one of the
+// convergents of the continued fraction of e / n, and there are only about
+// log(n) of those to try. A candidate is right when phi = (e * d - 1) / k
+// makes p and q the whole roots of x^2 - (n - phi + 1) x + n.
+
+// k / d, one of the fractions the continued fraction of e / n gives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Convergent{
+    pub k:u64,
+    pub d:u64,
+}
+
+// The quotients of the continued fraction of e / n, found the same way as
+// the steps of Euclid's algorithm.
+pub fn continued_fraction(e:u64, n:u64) -> Vec<u64>{
+    let (mut a, mut b) = (e, n);
+    let mut quotients:Vec<u64> = Vec::new();
+    while b != 0{
+        quotients.push(a / b);
+        (a, b) = (b, a % b);
+    }
+    quotients
+}
+
+// Each convergent is quotient * the last one + the one before, top and
+// bottom. The tops are at most e and the bottoms at most n.
+pub fn convergents(e:u64, n:u64) -> Vec<Convergent>{
+    let (mut k0, mut k1) = (0u64, 1u64);
+    let (mut d0, mut d1) = (1u64, 0u64);
+    continued_fraction(e, n).iter().map(|quotient| {
+        (k0, k1) = (k1, quotient * k1 + k0);
+        (d0, d1) = (d1, quotient * d1 + d0);
+        Convergent{ k: k1, d: d1 }
+    }).collect()
+}
+
+// p and q if the convergent's d is the private exponent.
+pub fn check(e:u64, n:u64, convergent:Convergent) -> Option<(u64, u64)>{
+    let (k, d) = (convergent.k as u128, convergent.d as u128);
+    let ed = e as u128 * d;
+    if k == 0 || ed == 0 || !(ed - 1).is_multiple_of(k){
+        return None;
+    }
+    let phi = (ed - 1) / k;
+    let s = (n as u128 + 1).checked_sub(phi)?;
+    let discriminant = s.checked_mul(s)?.checked_sub(4 * n as u128)?;
+    let root = discriminant.isqrt();
+    if root * root != discriminant || !(s + root).is_multiple_of(2){
+        return None;
+    }
+    let (p, q) = ((s + root) / 2, (s - root) / 2);
+    if q > 1 && p * q == n as u128 {Some((p as u64, q as u64))} else {None}
+}
+
+// d, p and q, or None when no convergent gives the key.
+pub fn attack(e:u64, n:u64) -> Option<(u64, u64, u64)>{
+    convergents(e, n).into_iter().find_map(|c| check(e, n, c).map(|(p, q)| (c.d, p, q)))
+}
+
+// The largest d Wiener's attack is sure to find, n^(1/4) / 3.
+pub fn bound(n:u64) -> u64{
+    n.isqrt().isqrt() / 3
+}
+
+#[cfg(test)]
+#[test]
+fn test_wiener(){
+    assert_eq!(continued_fraction(17, 60), vec![0, 3, 1, 1, 8]);
+    assert_eq!(convergents(17, 60).last(), Some(&Convergent{ k: 17, d: 60 }));
+    assert_eq!(convergents(17, 60)[1], Convergent{ k: 1, d: 3 });
+
+    // d = 7, far below n^(1/4) / 3, e is its inverse modulo phi(n).
+    let (p, q) = (4294967291u64, 3037000507u64);
+    let n = p * q;
+    let phi = (p - 1) as u128 * (q - 1) as u128;
+    let e = ((1..=7).map(|k:u128| k * phi + 1).find(|x| x % 7 == 0).unwrap() / 7) as u64;
+    assert_eq!(attack(e, n), Some((7, p, q)));
+    assert_eq!(attack(65537, 4294967291u64 * 4294967279), None);
+    assert_eq!(bound(n), 20032);
+    assert_eq!(check(e, n, Convergent{ k: 0, d: 1 }), None);
+}
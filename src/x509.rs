@@ -0,0 +1,650 @@
+// X.509 v3 certificates (RFC 5280). Certificates are built from a
+// CertificateBuilder, and existing certificates can be read back to get
+// their names, validity and public key.
+//
+// The structure is the same as any other certificate, so openssl and other
+// tools can show these ones, but the signature is the one from the signature
+// module, 64 bits of the SHA-256 digest without the PKCS#1 v1.5 padding a
+// 64 bit modulus has no room for. It isn't sha256WithRSAEncryption, so it is
+// labelled with an OID of its own that other tools don't know, and keys in
+// certificates from elsewhere are usually too large for the 64 bit key types.
+
+use crate::der::{self, DerError, DerReader};
+use crate::keys::{KeyError, RSAPrivateKey, RSAPublicKey};
+use crate::pem::{self, PemError};
+use crate::sha256;
+use crate::signature;
+use std::fmt;
+
+pub const CERTIFICATE_LABEL: &str = "CERTIFICATE";
+
+// The tutorial's 64 bit signature from the signature module. It sits under
+// 1.3.6.1.4.1.32473, the enterprise number IANA keeps for examples (RFC 5612),
+// so it can't clash with a real algorithm.
+pub const OID_TUTORIAL_SIGNATURE: &[u64] = &[1, 3, 6, 1, 4, 1, 32473, 1, 1];
+
+const OID_SUBJECT_KEY_IDENTIFIER: &[u64] = &[2, 5, 29, 14];
+const OID_KEY_USAGE: &[u64] = &[2, 5, 29, 15];
+const OID_SUBJECT_ALT_NAME: &[u64] = &[2, 5, 29, 17];
+const OID_BASIC_CONSTRAINTS: &[u64] = &[2, 5, 29, 19];
+const OID_AUTHORITY_KEY_IDENTIFIER: &[u64] = &[2, 5, 29, 35];
+
+// Short names of the name attributes that can be written, as used in "CN=example, O=Example".
+const NAME_ATTRIBUTES: &[(&str, &[u64])] = &[
+    ("CN", &[2, 5, 4, 3]),
+    ("C", &[2, 5, 4, 6]),
+    ("L", &[2, 5, 4, 7]),
+    ("ST", &[2, 5, 4, 8]),
+    ("O", &[2, 5, 4, 10]),
+    ("OU", &[2, 5, 4, 11]),
+    ("emailAddress", &[1, 2, 840, 113549, 1, 9, 1]),
+];
+
+// Key usage bits, RFC 5280 section 4.2.1.3.
+const USAGE_DIGITAL_SIGNATURE: usize = 0;
+const USAGE_KEY_ENCIPHERMENT: usize = 2;
+const USAGE_KEY_CERT_SIGN: usize = 5;
+const USAGE_CRL_SIGN: usize = 6;
+
+// dNSName in GeneralName, [2] IMPLICIT IA5String.
+const TAG_DNS_NAME: u8 = 0x82;
+
+// Errors returned when a certificate can't be built or read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum X509Error{
+    Der(DerError),
+    Pem(PemError),
+    Key(KeyError),
+    // Distinguished name text that can't be parsed or encoded.
+    InvalidName(String),
+    // Certificate version other than v1 to v3.
+    UnsupportedVersion(u64),
+    // Signature algorithm other than the tutorial's own, such as the
+    // sha256WithRSAEncryption of certificates from other tools.
+    UnsupportedAlgorithm(Vec<u64>),
+    // Signature that doesn't match the signed data and key.
+    BadSignature,
+}
+
+impl fmt::Display for X509Error{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            X509Error::Der(e) => write!(f, "{}", e),
+            X509Error::Pem(e) => write!(f, "{}", e),
+            X509Error::Key(e) => write!(f, "{}", e),
+            X509Error::InvalidName(s) => write!(f, "invalid name: {}", s),
+            X509Error::UnsupportedVersion(v) => write!(f, "unsupported certificate version {}", v + 1),
+            X509Error::UnsupportedAlgorithm(oid) => write!(f, "unsupported signature algorithm {}", oid_to_string(oid)),
+            X509Error::BadSignature => write!(f, "bad signature"),
+        }
+    }
+}
+
+impl std::error::Error for X509Error{}
+
+impl From<DerError> for X509Error{
+    fn from(e:DerError) -> X509Error{
+        X509Error::Der(e)
+    }
+}
+
+impl From<PemError> for X509Error{
+    fn from(e:PemError) -> X509Error{
+        X509Error::Pem(e)
+    }
+}
+
+impl From<KeyError> for X509Error{
+    fn from(e:KeyError) -> X509Error{
+        X509Error::Key(e)
+    }
+}
+
+// A distinguished name, the attributes in the order they are encoded, each
+// as its object identifier and text.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Name{
+    attributes:Vec<(Vec<u64>, String)>,
+}
+
+impl Name{
+    // Reads "CN=example.com, O=Example, C=GB". Commas and plus signs can't be
+    // escaped, so values can't contain them.
+    pub fn parse(text:&str) -> Result<Name, X509Error>{
+        let mut attributes:Vec<(Vec<u64>, String)> = Vec::new();
+        for part in text.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()){
+            let (key, value) = match part.split_once('='){
+                Some((k, v)) => (k.trim(), v.trim()),
+                None => return Err(X509Error::InvalidName(format!("\"{}\" is not NAME=value", part))),
+            };
+            let oid = match NAME_ATTRIBUTES.iter().find(|(n, _)| n.eq_ignore_ascii_case(key)){
+                Some((_, oid)) => oid.to_vec(),
+                None => return Err(X509Error::InvalidName(format!("unknown attribute {}", key))),
+            };
+            if value.is_empty() || value.contains('+'){
+                return Err(X509Error::InvalidName(format!("bad value for {}", key)));
+            }
+            if oid == [2, 5, 4, 6] && !(value.len() == 2 && value.bytes().all(|b| b.is_ascii_uppercase())){
+                return Err(X509Error::InvalidName(String::from("the country must be two upper case letters")));
+            }
+            attributes.push((oid, value.to_string()));
+        }
+        if attributes.is_empty(){
+            return Err(X509Error::InvalidName(String::from("the name is empty")));
+        }
+        Ok(Name{ attributes })
+    }
+
+    pub fn attributes(&self) -> &[(Vec<u64>, String)]{
+        &self.attributes
+    }
+
+    pub fn common_name(&self) -> Option<&str>{
+        self.attributes.iter().find(|(oid, _)| oid == &[2, 5, 4, 3]).map(|(_, v)| v.as_str())
+    }
+
+    // Name ::= SEQUENCE OF SET OF SEQUENCE { type OID, value string }, with one
+    // attribute in each set. The country is a PrintableString, email addresses
+    // IA5String and everything else UTF8String.
+    pub fn to_der(&self) -> Vec<u8>{
+        let sets:Vec<Vec<u8>> = self.attributes.iter().map(|(oid, value)| {
+            let tag = if oid == &[2, 5, 4, 6] {der::TAG_PRINTABLE_STRING}
+                else if oid == &[1, 2, 840, 113549, 1, 9, 1] {der::TAG_IA5_STRING}
+                else {der::TAG_UTF8_STRING};
+            der::encode_set(&[der::encode_sequence(&[encode_oid(oid), der::encode_tlv(tag, value.as_bytes())])])
+        }).collect();
+        der::encode_sequence(&sets)
+    }
+
+    pub fn read(reader:&mut DerReader) -> Result<Name, X509Error>{
+        let mut attributes:Vec<(Vec<u64>, String)> = Vec::new();
+        let mut sequence = reader.read_sequence()?;
+        while !sequence.is_empty(){
+            let mut set = DerReader::new(sequence.read_expected(der::TAG_SET)?);
+            while !set.is_empty(){
+                let mut attribute = set.read_sequence()?;
+                let oid = attribute.read_oid()?;
+                let value = attribute.read_string()?;
+                attribute.finish()?;
+                attributes.push((oid, value));
+            }
+        }
+        Ok(Name{ attributes })
+    }
+}
+
+// Written the same way parse reads it, attributes without a short name as dotted numbers.
+impl fmt::Display for Name{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        let parts:Vec<String> = self.attributes.iter().map(|(oid, value)| {
+            match NAME_ATTRIBUTES.iter().find(|(_, o)| o == oid){
+                Some((name, _)) => format!("{}={}", name, value),
+                None => format!("{}={}", oid_to_string(oid), value),
+            }
+        }).collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+// What goes into a new certificate. The issuer is the subject unless one is
+// given, as for a self-signed certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateBuilder{
+    pub serial:u64,
+    pub subject:Name,
+    pub issuer:Option<Name>,
+    pub not_before:u64,
+    pub not_after:u64,
+    // Marks the key as a certificate authority that can sign other certificates.
+    pub ca:bool,
+    // Host names for the subject alternative name extension, which TLS clients
+    // check instead of the common name.
+    pub dns_names:Vec<String>,
+}
+
+impl CertificateBuilder{
+    pub fn new(subject:Name, not_before:u64, not_after:u64) -> CertificateBuilder{
+        CertificateBuilder{ serial: 1, subject, issuer: None, not_before, not_after, ca: false, dns_names: Vec::new() }
+    }
+
+    // TBSCertificate ::= SEQUENCE { version [0] EXPLICIT 2 (v3), serialNumber,
+    //     signature AlgorithmIdentifier, issuer Name, validity SEQUENCE { notBefore,
+    //     notAfter }, subject Name, subjectPublicKeyInfo, extensions [3] EXPLICIT }
+    // The key identifiers are the first 160 bits of the SHA-256 digest of the
+    // key, method 1 of RFC 7093.
+    pub fn tbs_der(&self, subject_key:&RSAPublicKey, issuer_key:&RSAPublicKey) -> Vec<u8>{
+        let issuer = self.issuer.as_ref().unwrap_or(&self.subject);
+        let (usage, critical_ca) = if self.ca{
+            (vec![USAGE_DIGITAL_SIGNATURE, USAGE_KEY_CERT_SIGN, USAGE_CRL_SIGN], der::encode_sequence(&[der::encode_boolean(true)]))
+        }else{
+            (vec![USAGE_DIGITAL_SIGNATURE, USAGE_KEY_ENCIPHERMENT], der::encode_sequence(&[]))
+        };
+        let mut extensions = vec![
+            Extension::new(OID_BASIC_CONSTRAINTS, true, critical_ca),
+            Extension::new(OID_KEY_USAGE, true, encode_named_bits(&usage)),
+            Extension::new(OID_SUBJECT_KEY_IDENTIFIER, false, der::encode_octet_string(&key_identifier(subject_key))),
+            Extension::new(OID_AUTHORITY_KEY_IDENTIFIER, false, der::encode_sequence(&[der::encode_tlv(0x80, &key_identifier(issuer_key))])),
+        ];
+        if !self.dns_names.is_empty(){
+            extensions.push(Extension::subject_alt_name(&self.dns_names));
+        }
+        let extensions:Vec<Vec<u8>> = extensions.iter().map(|e| e.to_der()).collect();
+
+        der::encode_sequence(&[
+            der::encode_tlv(der::context_tag(0), &der::encode_integer(2)),
+            der::encode_integer(self.serial),
+            signature_algorithm_identifier(),
+            issuer.to_der(),
+            der::encode_sequence(&[der::encode_time(self.not_before), der::encode_time(self.not_after)]),
+            self.subject.to_der(),
+            subject_key.to_spki_der(),
+            der::encode_tlv(der::context_tag(3), &der::encode_sequence(&extensions)),
+        ])
+    }
+
+    // Certificate for the subject's key signed by the issuer's private key.
+    pub fn sign(&self, subject_key:&RSAPublicKey, issuer_key:&RSAPrivateKey) -> Certificate{
+        let der = sign_der(self.tbs_der(subject_key, &issuer_key.public_key()), issuer_key);
+        match Certificate::from_der(&der){
+            Ok(s) => s,
+            Err(e) => panic!("Built certificate can't be read back. {}", e),
+        }
+    }
+
+    pub fn self_sign(&self, key:&RSAPrivateKey) -> Certificate{
+        self.sign(&key.public_key(), key)
+    }
+}
+
+// An extension as it appears in the certificate, the value still DER encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension{
+    pub oid:Vec<u64>,
+    pub critical:bool,
+    pub value:Vec<u8>,
+}
+
+impl Extension{
+    pub fn new(oid:&[u64], critical:bool, value:Vec<u8>) -> Extension{
+        Extension{ oid: oid.to_vec(), critical, value }
+    }
+
+    // Subject alternative names made of host names.
+    pub fn subject_alt_name(dns_names:&[String]) -> Extension{
+        let names:Vec<Vec<u8>> = dns_names.iter().map(|n| der::encode_tlv(TAG_DNS_NAME, n.as_bytes())).collect();
+        Extension::new(OID_SUBJECT_ALT_NAME, false, der::encode_sequence(&names))
+    }
+
+    // Extension ::= SEQUENCE { extnID OID, critical BOOLEAN DEFAULT FALSE, extnValue OCTET STRING }
+    pub fn to_der(&self) -> Vec<u8>{
+        let mut fields = vec![encode_oid(&self.oid)];
+        if self.critical{
+            fields.push(der::encode_boolean(true));
+        }
+        fields.push(der::encode_octet_string(&self.value));
+        der::encode_sequence(&fields)
+    }
+
+    pub fn read(reader:&mut DerReader) -> Result<Extension, X509Error>{
+        let mut fields = reader.read_sequence()?;
+        let oid = fields.read_oid()?;
+        let critical = if fields.peek_tag() == Some(der::TAG_BOOLEAN) {fields.read_boolean()?} else {false};
+        let value = fields.read_octet_string()?.to_vec();
+        fields.finish()?;
+        Ok(Extension{ oid, critical, value })
+    }
+}
+
+// A certificate read from DER, keeping the signed bytes so the signature can be checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate{
+    der:Vec<u8>,
+    tbs:Vec<u8>,
+    version:u64,
+    serial:Vec<u8>,
+    issuer:Name,
+    subject:Name,
+    not_before:u64,
+    not_after:u64,
+    spki:Vec<u8>,
+    extensions:Vec<Extension>,
+    signature_algorithm:Vec<u64>,
+    signature:Vec<u8>,
+}
+
+impl Certificate{
+    pub fn from_der(data:&[u8]) -> Result<Certificate, X509Error>{
+        let (tbs, signature_algorithm, signature) = read_signed(data)?;
+        let mut reader = DerReader::new(tbs);
+        let mut fields = reader.read_sequence()?;
+        reader.finish()?;
+        // Version 1 certificates leave the version out.
+        let version = if fields.peek_tag() == Some(der::context_tag(0)){
+            let mut explicit = DerReader::new(fields.read_expected(der::context_tag(0))?);
+            let version = explicit.read_integer()?;
+            explicit.finish()?;
+            version
+        }else{
+            0
+        };
+        if version > 2{
+            return Err(X509Error::UnsupportedVersion(version));
+        }
+        // Serial numbers can be up to 20 bytes, longer than a u64.
+        let serial = fields.read_expected(der::TAG_INTEGER)?.to_vec();
+        if read_algorithm_identifier(&mut fields)? != signature_algorithm{
+            return Err(X509Error::Der(DerError::NonCanonical));
+        }
+        let issuer = Name::read(&mut fields)?;
+        let mut validity = fields.read_sequence()?;
+        let not_before = validity.read_time()?;
+        let not_after = validity.read_time()?;
+        validity.finish()?;
+        let subject = Name::read(&mut fields)?;
+        let spki = fields.read_raw()?.to_vec();
+
+        let mut extensions:Vec<Extension> = Vec::new();
+        while !fields.is_empty(){
+            // Unique identifiers, [1] and [2], are skipped.
+            let (tag, content) = fields.read_tlv()?;
+            if tag == der::context_tag(3){
+                let mut explicit = DerReader::new(content);
+                let mut list = explicit.read_sequence()?;
+                explicit.finish()?;
+                while !list.is_empty(){
+                    extensions.push(Extension::read(&mut list)?);
+                }
+            }else if tag != 0x81 && tag != 0x82 && tag != 0xa1 && tag != 0xa2{
+                return Err(X509Error::Der(DerError::UnexpectedTag{ expected: der::context_tag(3), found: tag }));
+            }
+        }
+
+        Ok(Certificate{ der: data.to_vec(), tbs: tbs.to_vec(), version, serial, issuer, subject, not_before, not_after,
+            spki, extensions, signature_algorithm, signature: signature.to_vec() })
+    }
+
+    pub fn to_der(&self) -> Vec<u8>{
+        self.der.clone()
+    }
+
+    pub fn from_pem(text:&str) -> Result<Certificate, X509Error>{
+        Certificate::from_der(&pem::decode_expected(text, CERTIFICATE_LABEL)?)
+    }
+
+    pub fn to_pem(&self) -> String{
+        pem::encode(CERTIFICATE_LABEL, &self.der)
+    }
+
+    // 1 to 3, as it is usually shown rather than as it is encoded.
+    pub fn get_version(&self) -> u64{
+        self.version + 1
+    }
+
+    // Serial number as its big endian two's complement bytes.
+    pub fn get_serial(&self) -> &[u8]{
+        &self.serial
+    }
+
+    pub fn get_issuer(&self) -> &Name{
+        &self.issuer
+    }
+
+    pub fn get_subject(&self) -> &Name{
+        &self.subject
+    }
+
+    pub fn get_not_before(&self) -> u64{
+        self.not_before
+    }
+
+    pub fn get_not_after(&self) -> u64{
+        self.not_after
+    }
+
+    pub fn get_extensions(&self) -> &[Extension]{
+        &self.extensions
+    }
+
+    pub fn get_signature_algorithm(&self) -> &[u64]{
+        &self.signature_algorithm
+    }
+
+    // The subject's key, fails for keys that aren't RSA or don't fit in 64 bits.
+    pub fn public_key(&self) -> Result<RSAPublicKey, X509Error>{
+        Ok(RSAPublicKey::from_spki_der(&self.spki)?)
+    }
+
+    // Whether basic constraints mark the subject as a certificate authority.
+    pub fn is_ca(&self) -> bool{
+        let extension = match self.extensions.iter().find(|e| e.oid == OID_BASIC_CONSTRAINTS){
+            Some(s) => s,
+            None => return false,
+        };
+        let mut outer = DerReader::new(&extension.value);
+        match outer.read_sequence(){
+            Ok(mut constraints) => constraints.peek_tag() == Some(der::TAG_BOOLEAN) && constraints.read_boolean() == Ok(true),
+            Err(_) => false,
+        }
+    }
+
+    // Host names from the subject alternative name extension.
+    pub fn dns_names(&self) -> Vec<String>{
+        dns_names(&self.extensions)
+    }
+
+    pub fn is_self_issued(&self) -> bool{
+        self.issuer == self.subject
+    }
+
+    // Checks the signature with the issuer's key, the certificate's own key
+    // for a self-signed certificate.
+    pub fn verify(&self, issuer_key:&RSAPublicKey) -> Result<bool, X509Error>{
+        verify_signature(&self.tbs, &self.signature_algorithm, &self.signature, issuer_key)
+    }
+}
+
+// The public key from a PEM certificate.
+pub fn public_key_from_pem(text:&str) -> Result<RSAPublicKey, X509Error>{
+    Certificate::from_pem(text)?.public_key()
+}
+
+// AlgorithmIdentifier for the tutorial's signature, which has no parameters.
+// Other tools can still parse the files but will report the algorithm as
+// unknown rather than check the signature as PKCS#1 v1.5.
+pub fn signature_algorithm_identifier() -> Vec<u8>{
+    der::encode_sequence(&[encode_oid(OID_TUTORIAL_SIGNATURE)])
+}
+
+// Reads an AlgorithmIdentifier and returns its OID, the parameters must be
+// missing or NULL as they are for every RSA signature algorithm but PSS.
+pub fn read_algorithm_identifier(reader:&mut DerReader) -> Result<Vec<u64>, X509Error>{
+    let mut algorithm = reader.read_sequence()?;
+    let oid = algorithm.read_oid()?;
+    if !algorithm.is_empty(){
+        algorithm.read_null()?;
+    }
+    algorithm.finish()?;
+    Ok(oid)
+}
+
+// First 20 bytes of the SHA-256 digest of the PKCS#1 public key.
+fn key_identifier(key:&RSAPublicKey) -> Vec<u8>{
+    sha256::digest(&key.to_pkcs1_der())[..20].to_vec()
+}
+
+// Host names from the subject alternative name extension in the list, if there is one.
+pub fn dns_names(extensions:&[Extension]) -> Vec<String>{
+    let mut names:Vec<String> = Vec::new();
+    if let Some(extension) = extensions.iter().find(|e| e.oid == OID_SUBJECT_ALT_NAME){
+        let mut outer = DerReader::new(&extension.value);
+        if let Ok(mut list) = outer.read_sequence(){
+            while let Ok((tag, content)) = list.read_tlv(){
+                if tag == TAG_DNS_NAME{
+                    names.push(String::from_utf8_lossy(content).to_string());
+                }
+            }
+        }
+    }
+    names
+}
+
+// Signs DER encoded data and wraps it the way certificates and certificate
+// requests are, SEQUENCE { data, signatureAlgorithm, signature BIT STRING }.
+// The signature is signature::sign, the 8 byte truncated digest, labelled
+// with OID_TUTORIAL_SIGNATURE.
+pub fn sign_der(data:Vec<u8>, key:&RSAPrivateKey) -> Vec<u8>{
+    let value = signature::sign(key, &data);
+    der::encode_sequence(&[data, signature_algorithm_identifier(), der::encode_bit_string(&value.to_be_bytes())])
+}
+
+// The signed bytes, signature algorithm and signature of a signed structure.
+pub type SignedParts<'a> = (&'a [u8], Vec<u64>, &'a [u8]);
+
+// Splits a structure made by sign_der into its parts.
+pub fn read_signed(data:&[u8]) -> Result<SignedParts<'_>, X509Error>{
+    let mut outer = DerReader::new(data);
+    let mut signed = outer.read_sequence()?;
+    let content = signed.read_raw()?;
+    let algorithm = read_algorithm_identifier(&mut signed)?;
+    let signature = signed.read_bit_string()?;
+    signed.finish()?;
+    outer.finish()?;
+    Ok((content, algorithm, signature))
+}
+
+// Checks a signature from read_signed, which must be an 8 byte tutorial one.
+pub fn verify_signature(data:&[u8], algorithm:&[u64], value:&[u8], key:&RSAPublicKey) -> Result<bool, X509Error>{
+    if algorithm != OID_TUTORIAL_SIGNATURE{
+        return Err(X509Error::UnsupportedAlgorithm(algorithm.to_vec()));
+    }
+    let value:[u8; 8] = match value.try_into(){
+        Ok(s) => s,
+        Err(_) => return Ok(false),
+    };
+    Ok(signature::verify(key, data, u64::from_be_bytes(value)))
+}
+
+// A BIT STRING of named bits, bit 0 is the top bit of the first byte and the
+// trailing zero bits are left off as DER requires.
+fn encode_named_bits(bits:&[usize]) -> Vec<u8>{
+    let length = bits.iter().max().map_or(0, |b| b / 8 + 1);
+    let mut content = vec![0u8; length + 1];
+    for bit in bits{
+        content[1 + bit / 8] |= 0x80 >> (bit % 8);
+    }
+    if let Some(last) = content.last().filter(|_| length > 0){
+        content[0] = last.trailing_zeros() as u8;
+    }
+    der::encode_tlv(der::TAG_BIT_STRING, &content)
+}
+
+// Encodes one of the OID constants, which are all known to be valid.
+pub fn encode_oid(oid:&[u64]) -> Vec<u8>{
+    match der::encode_oid(oid){
+        Ok(s) => s,
+        Err(e) => panic!("OID {} can't be encoded. {}", oid_to_string(oid), e),
+    }
+}
+
+fn oid_to_string(oid:&[u64]) -> String{
+    let arcs:Vec<String> = oid.iter().map(|a| a.to_string()).collect();
+    arcs.join(".")
+}
+
+#[cfg(test)]
+use crate::keys::{other_test_key, test_key};
+
+#[test]
+fn test_x509_name(){
+    let name = Name::parse("CN=localhost, O=Example Ltd, c=GB").unwrap();
+    assert_eq!(name.to_string(), "CN=localhost, O=Example Ltd, C=GB");
+    assert_eq!(name.common_name(), Some("localhost"));
+    assert_eq!(Name::read(&mut DerReader::new(&name.to_der())), Ok(name));
+
+    assert!(matches!(Name::parse(""), Err(X509Error::InvalidName(_))));
+    assert!(matches!(Name::parse("CN"), Err(X509Error::InvalidName(_))));
+    assert!(matches!(Name::parse("XX=1"), Err(X509Error::InvalidName(_))));
+    assert!(matches!(Name::parse("C=United Kingdom"), Err(X509Error::InvalidName(_))));
+}
+
+#[test]
+fn test_x509_named_bits(){
+    // digitalSignature and keyEncipherment is 0xa0 with 5 unused bits.
+    assert_eq!(encode_named_bits(&[USAGE_DIGITAL_SIGNATURE, USAGE_KEY_ENCIPHERMENT]), vec![0x03, 0x02, 0x05, 0xa0]);
+    assert_eq!(encode_named_bits(&[USAGE_DIGITAL_SIGNATURE, USAGE_KEY_CERT_SIGN, USAGE_CRL_SIGN]), vec![0x03, 0x02, 0x01, 0x86]);
+    assert_eq!(encode_named_bits(&[8]), vec![0x03, 0x03, 0x07, 0x00, 0x80]);
+    assert_eq!(encode_named_bits(&[]), vec![0x03, 0x01, 0x00]);
+}
+
+#[test]
+fn test_x509_self_signed(){
+    let key = test_key();
+    let mut builder = CertificateBuilder::new(Name::parse("CN=localhost, O=Example").unwrap(), 1714571100, 1714571100 + 365 * 86400);
+    builder.serial = 0x1234;
+    builder.dns_names = vec![String::from("localhost"), String::from("test.example")];
+    let certificate = builder.self_sign(&key);
+
+    let pem = certificate.to_pem();
+    assert!(pem.starts_with("-----BEGIN CERTIFICATE-----\n"));
+    let read = Certificate::from_pem(&pem).unwrap();
+    assert_eq!(read, certificate);
+    assert_eq!(read.get_version(), 3);
+    assert_eq!(read.get_serial(), &[0x12, 0x34]);
+    assert_eq!(read.get_subject().to_string(), "CN=localhost, O=Example");
+    assert!(read.is_self_issued());
+    assert_eq!(read.get_not_before(), 1714571100);
+    assert_eq!(read.get_not_after(), 1714571100 + 365 * 86400);
+    assert_eq!(read.public_key(), Ok(key.public_key()));
+    assert_eq!(public_key_from_pem(&pem), Ok(key.public_key()));
+    assert!(!read.is_ca());
+    assert_eq!(read.dns_names(), vec![String::from("localhost"), String::from("test.example")]);
+    assert_eq!(read.get_extensions().iter().filter(|e| e.critical).count(), 2);
+    assert_eq!(read.get_signature_algorithm(), OID_TUTORIAL_SIGNATURE);
+    assert_eq!(read.verify(&key.public_key()), Ok(true));
+    assert_eq!(read.verify(&RSAPublicKey::new(key.get_n(), 65537)), Ok(false));
+
+    // Changing a signed byte, here the last byte of the serial, breaks the signature.
+    let mut der = read.to_der();
+    let position = der.windows(2).position(|w| w == [0x12, 0x34]).unwrap();
+    der[position + 1] = 0x35;
+    assert_eq!(Certificate::from_der(&der).unwrap().verify(&key.public_key()), Ok(false));
+}
+
+#[test]
+fn test_x509_issued(){
+    let ca_key = test_key();
+    let leaf_key = other_test_key();
+    let mut ca = CertificateBuilder::new(Name::parse("CN=Test CA").unwrap(), 0, 2524608000);
+    ca.ca = true;
+    let ca_certificate = ca.self_sign(&ca_key);
+    assert!(ca_certificate.is_ca());
+    assert_eq!(ca_certificate.get_not_after(), 2524608000);
+
+    let mut leaf = CertificateBuilder::new(Name::parse("CN=server").unwrap(), 0, 86400);
+    leaf.issuer = Some(ca_certificate.get_subject().clone());
+    let certificate = leaf.sign(&leaf_key.public_key(), &ca_key);
+    assert!(!certificate.is_self_issued());
+    assert_eq!(certificate.get_issuer().to_string(), "CN=Test CA");
+    assert_eq!(certificate.public_key(), Ok(leaf_key.public_key()));
+    assert_eq!(certificate.verify(&ca_key.public_key()), Ok(true));
+    assert_eq!(certificate.verify(&leaf_key.public_key()), Ok(false));
+}
+
+#[test]
+fn test_x509_openssl_certificate(){
+    // Made by openssl req -x509 with a 1024 bit key, so the names and dates can
+    // be read but the key is too large for the 64 bit key types.
+    let certificate = Certificate::from_pem(include_str!("../fixtures/x509_rsa_1024.pem")).unwrap();
+    assert_eq!(certificate.get_version(), 3);
+    assert_eq!(certificate.get_serial(), &[0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x01, 0x23]);
+    assert_eq!(certificate.get_subject().to_string(), "CN=openssl.test, O=Fixture Org, C=GB");
+    assert!(certificate.is_self_issued());
+    assert!(certificate.is_ca());
+    assert_eq!(certificate.get_not_before(), 1792377340);
+    assert_eq!(certificate.get_not_after(), 2107737340);
+    assert_eq!(certificate.dns_names(), vec![String::from("openssl.test"), String::from("www.openssl.test")]);
+    assert_eq!(certificate.public_key(), Err(X509Error::Key(KeyError::Der(DerError::IntegerTooLarge))));
+    // sha256WithRSAEncryption, which isn't the tutorial's signature.
+    assert_eq!(certificate.verify(&test_key().public_key()), Err(X509Error::UnsupportedAlgorithm(vec![1, 2, 840, 113549, 1, 1, 11])));
+    assert_eq!(Certificate::from_der(&certificate.to_der()), Ok(certificate));
+}